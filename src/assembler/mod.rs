@@ -0,0 +1,569 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::chip8::Instruction;
+
+pub mod octo;
+
+pub use octo::{compile_octo, OctoProgram};
+
+const PROGRAM_START_ADDRESS: usize = 0x200;
+const MAX_ADDRESS: usize = 0xFFFF;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssemblyError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AssemblyError {}
+
+/// Assembles Cowgod-style source into a ROM image that starts at 0x200.
+///
+/// Besides the instruction mnemonics it understands `label:` definitions, `NAME EQU value`
+/// constants, the `ORG`, `DB`, `DW` and `SPRITE` directives and `;` comments. Every error
+/// found is reported with its line number.
+pub fn assemble(source: &str) -> Result<Vec<u8>, Vec<AssemblyError>> {
+    let mut assembler = Assembler::default();
+    assembler.first_pass(source);
+    assembler.second_pass();
+
+    if assembler.errors.is_empty() {
+        Ok(assembler.output)
+    } else {
+        Err(assembler.errors)
+    }
+}
+
+struct Statement {
+    line: usize,
+    address: usize,
+    mnemonic: String,
+    operands: Vec<String>,
+}
+
+#[derive(Default)]
+struct Assembler {
+    symbols: HashMap<String, i64>,
+    statements: Vec<Statement>,
+    output: Vec<u8>,
+    // Which bytes of `output` were assembled, the others are gaps left by ORG
+    assembled: Vec<bool>,
+    errors: Vec<AssemblyError>,
+}
+
+impl Assembler {
+    // Collects labels and constants and assigns every statement its address.
+    fn first_pass(&mut self, source: &str) {
+        let mut address = PROGRAM_START_ADDRESS;
+
+        for (index, raw_line) in source.lines().enumerate() {
+            let line = index + 1;
+            let mut text = strip_comment(raw_line).trim();
+
+            if let Some((label, rest)) = split_label(text) {
+                self.define(line, label, address as i64);
+                text = rest.trim();
+            }
+
+            if text.is_empty() {
+                continue;
+            }
+
+            let (mnemonic, rest) = split_first_word(text);
+            let (directive, value) = split_first_word(rest);
+
+            if directive.eq_ignore_ascii_case("EQU") {
+                match self.evaluate(value, line) {
+                    Ok(value) => self.define(line, mnemonic, value),
+                    Err(error) => self.errors.push(error),
+                }
+                continue;
+            }
+
+            let mnemonic = mnemonic.to_ascii_uppercase();
+            let operands = split_operands(rest);
+
+            if mnemonic == "ORG" {
+                match operands.first().map(|operand| self.evaluate(operand, line)) {
+                    Some(Ok(value)) if (PROGRAM_START_ADDRESS as i64..=MAX_ADDRESS as i64).contains(&value) => address = value as usize,
+                    Some(Ok(value)) => self.error(line, format!("ORG address 0x{:X} is outside of 0x200-0xFFFF", value)),
+                    Some(Err(error)) => self.errors.push(error),
+                    None => self.error(line, "ORG needs an address".to_string()),
+                }
+                continue;
+            }
+
+            let size = match statement_size(&mnemonic, &operands) {
+                Ok(size) => size,
+                Err(message) => {
+                    self.error(line, message);
+                    continue;
+                }
+            };
+
+            self.statements.push(Statement { line, address, mnemonic, operands });
+            address += size;
+
+            if address > MAX_ADDRESS + 1 {
+                self.error(line, "program does not fit into 64 KiB of memory".to_string());
+                return;
+            }
+        }
+    }
+
+    fn second_pass(&mut self) {
+        let statements = std::mem::take(&mut self.statements);
+
+        for statement in &statements {
+            if let Err(message) = self.encode(statement).and_then(|bytes| self.emit(statement.address, &bytes)) {
+                self.error(statement.line, message);
+            }
+        }
+    }
+
+    fn define(&mut self, line: usize, name: &str, value: i64) {
+        if !is_identifier(name) {
+            self.error(line, format!("'{}' is not a valid name", name));
+        } else if self.symbols.insert(name.to_string(), value).is_some() {
+            self.error(line, format!("'{}' is defined more than once", name));
+        }
+    }
+
+    fn error(&mut self, line: usize, message: String) {
+        self.errors.push(AssemblyError { line, message });
+    }
+
+    // An ORG back into earlier output must not silently replace it.
+    fn emit(&mut self, address: usize, bytes: &[u8]) -> Result<(), String> {
+        let offset = address - PROGRAM_START_ADDRESS;
+        let end = offset + bytes.len();
+        if self.output.len() < end {
+            self.output.resize(end, 0);
+            self.assembled.resize(end, false);
+        }
+        if let Some(overlap) = self.assembled[offset..end].iter().position(|&assembled| assembled) {
+            return Err(format!("address 0x{:X} is already assembled", address + overlap));
+        }
+
+        self.output[offset..end].copy_from_slice(bytes);
+        self.assembled[offset..end].fill(true);
+        Ok(())
+    }
+
+    fn encode(&self, statement: &Statement) -> Result<Vec<u8>, String> {
+        let line = statement.line;
+        let operands = &statement.operands;
+
+        match statement.mnemonic.as_str() {
+            "DB" => operands.iter()
+                .map(|operand| self.value(operand, line, 0xFF).map(|value| value as u8))
+                .collect(),
+            "DW" => {
+                let mut bytes = Vec::new();
+                for operand in operands {
+                    let value = self.value(operand, line, 0xFFFF)?;
+                    bytes.extend_from_slice(&(value as u16).to_be_bytes());
+                }
+                Ok(bytes)
+            }
+            "SPRITE" => {
+                let mut bytes = Vec::new();
+                for operand in operands {
+                    bytes.extend(sprite_row(operand)?);
+                }
+                Ok(bytes)
+            }
+            _ => {
+                let (instruction, long_operand) = self.instruction(statement)?;
+                let mut bytes = instruction.encode().to_be_bytes().to_vec();
+                if let Some(operand) = long_operand {
+                    bytes.extend_from_slice(&operand.to_be_bytes());
+                }
+                Ok(bytes)
+            }
+        }
+    }
+
+    fn instruction(&self, statement: &Statement) -> Result<(Instruction, Option<u16>), String> {
+        let line = statement.line;
+        let ops: Vec<&str> = statement.operands.iter().map(|operand| operand.as_str()).collect();
+        let upper: Vec<String> = ops.iter().map(|operand| operand.to_ascii_uppercase()).collect();
+        let upper: Vec<&str> = upper.iter().map(|operand| operand.as_str()).collect();
+        let mnemonic = statement.mnemonic.as_str();
+
+        let addr = |operand: &str| self.value(operand, line, 0xFFF).map(|value| value as u16);
+        let byte = |operand: &str| self.value(operand, line, 0xFF).map(|value| value as u8);
+        let nibble = |operand: &str| self.value(operand, line, 0xF).map(|value| value as u8);
+
+        let instruction = match (mnemonic, upper.as_slice()) {
+            ("CLS", []) => Instruction::Cls,
+            ("RET", []) => Instruction::Ret,
+            ("SCR", []) => Instruction::Scr,
+            ("SCL", []) => Instruction::Scl,
+            ("EXIT", []) => Instruction::Exit,
+            ("LOW", []) => Instruction::Low,
+            ("HIGH", []) => Instruction::High,
+            ("SCD", [_]) => Instruction::ScdNibble { n: nibble(ops[0])? },
+            ("SCU", [_]) => Instruction::ScuNibble { n: nibble(ops[0])? },
+            ("PLANE", [_]) => Instruction::PlaneN { n: self.value(ops[0], line, 0x3)? as u8 },
+            ("SYS", [_]) => Instruction::Sys { nnn: addr(ops[0])? },
+            ("JP", ["V0", _]) => Instruction::JpV0Addr { nnn: addr(ops[1])? },
+            ("JP", [_]) => Instruction::JpAddr { nnn: addr(ops[0])? },
+            ("CALL", [_]) => Instruction::CallAddr { nnn: addr(ops[0])? },
+            ("SE", [x, y]) if register(x).is_some() && register(y).is_some() => {
+                Instruction::SeVxVy { x: vx(x)?, y: vx(y)? }
+            }
+            ("SE", [x, _]) => Instruction::SeVxByte { x: vx(x)?, kk: byte(ops[1])? },
+            ("SNE", [x, y]) if register(x).is_some() && register(y).is_some() => {
+                Instruction::SneVxVy { x: vx(x)?, y: vx(y)? }
+            }
+            ("SNE", [x, _]) => Instruction::SneVxByte { x: vx(x)?, kk: byte(ops[1])? },
+            ("LD", ["I", value]) if starts_with_keyword(value, "LONG") => {
+                let operand = self.value(ops[1][4..].trim(), line, 0xFFFF)? as u16;
+                return Ok((Instruction::LdILong, Some(operand)));
+            }
+            ("LD", ["I", _]) => Instruction::LdIAddr { nnn: addr(ops[1])? },
+            ("LD", ["DT", x]) => Instruction::LdDtVx { x: vx(x)? },
+            ("LD", ["ST", x]) => Instruction::LdStVx { x: vx(x)? },
+            ("LD", ["F", x]) => Instruction::LdFVx { x: vx(x)? },
+            ("LD", ["HF", x]) => Instruction::LdHfVx { x: vx(x)? },
+            ("LD", ["B", x]) => Instruction::LdBVx { x: vx(x)? },
+            ("LD", ["R", x]) => Instruction::LdRVx { x: vx(x)? },
+            ("LD", ["[I]", range]) if range.contains('-') => {
+                let (x, y) = register_range(range)?;
+                Instruction::LdIVxVy { x, y }
+            }
+            ("LD", ["[I]", x]) => Instruction::LdIVx { x: vx(x)? },
+            ("LD", [range, "[I]"]) if range.contains('-') => {
+                let (x, y) = register_range(range)?;
+                Instruction::LdVxVyI { x, y }
+            }
+            ("LD", [x, "[I]"]) => Instruction::LdVxI { x: vx(x)? },
+            ("LD", [x, "DT"]) => Instruction::LdVxDt { x: vx(x)? },
+            ("LD", [x, "K"]) => Instruction::LdVxK { x: vx(x)? },
+            ("LD", [x, "R"]) => Instruction::LdVxR { x: vx(x)? },
+            ("LD", [x, y]) if register(y).is_some() => Instruction::LdVxVy { x: vx(x)?, y: vx(y)? },
+            ("LD", [x, _]) => Instruction::LdVxByte { x: vx(x)?, kk: byte(ops[1])? },
+            ("ADD", ["I", x]) => Instruction::AddIVx { x: vx(x)? },
+            ("ADD", [x, y]) if register(y).is_some() => Instruction::AddVxVy { x: vx(x)?, y: vx(y)? },
+            ("ADD", [x, _]) => Instruction::AddVxByte { x: vx(x)?, kk: byte(ops[1])? },
+            ("OR", [x, y]) => Instruction::OrVxVy { x: vx(x)?, y: vx(y)? },
+            ("AND", [x, y]) => Instruction::AndVxVy { x: vx(x)?, y: vx(y)? },
+            ("XOR", [x, y]) => Instruction::XorVxVy { x: vx(x)?, y: vx(y)? },
+            ("SUB", [x, y]) => Instruction::SubVxVy { x: vx(x)?, y: vx(y)? },
+            ("SUBN", [x, y]) => Instruction::SubnVxVy { x: vx(x)?, y: vx(y)? },
+            ("SHR", [x]) => Instruction::ShrVxVy { x: vx(x)?, y: vx(x)? },
+            ("SHR", [x, y]) => Instruction::ShrVxVy { x: vx(x)?, y: vx(y)? },
+            ("SHL", [x]) => Instruction::ShlVxVy { x: vx(x)?, y: vx(x)? },
+            ("SHL", [x, y]) => Instruction::ShlVxVy { x: vx(x)?, y: vx(y)? },
+            ("RND", [x, _]) => Instruction::RndVxByte { x: vx(x)?, kk: byte(ops[1])? },
+            ("DRW", [x, y, _]) => Instruction::DrwVxVyNibble { x: vx(x)?, y: vx(y)?, n: nibble(ops[2])? },
+            ("SKP", [x]) => Instruction::SkpVx { x: vx(x)? },
+            ("SKNP", [x]) => Instruction::SknpVx { x: vx(x)? },
+            _ => return Err(format!("unknown instruction '{} {}'", mnemonic, ops.join(", ")).trim_end().to_string()),
+        };
+
+        Ok((instruction, None))
+    }
+
+    fn value(&self, operand: &str, line: usize, max: i64) -> Result<i64, String> {
+        let value = self.evaluate(operand, line).map_err(|error| error.message)?;
+        fit_value(value, max).ok_or_else(|| format!("value {} of '{}' does not fit into 0x{:X}", value, operand, max))
+    }
+
+    // Sums of numbers and symbols, e.g. `sprites + 5` or `0x300 - 1`.
+    fn evaluate(&self, expression: &str, line: usize) -> Result<i64, AssemblyError> {
+        let mut total = 0;
+        let mut sign = 1;
+        let mut expect_term = true;
+
+        for token in tokenize_expression(expression) {
+            match token.as_str() {
+                "+" | "-" if expect_term => sign *= if token == "-" { -1 } else { 1 },
+                "+" | "-" => {
+                    sign = if token == "-" { -1 } else { 1 };
+                    expect_term = true;
+                }
+                _ if expect_term => {
+                    let value = parse_number(&token)
+                        .or_else(|| self.symbols.get(&token).copied())
+                        .ok_or_else(|| AssemblyError { line, message: format!("unknown symbol or number '{}'", token) })?;
+                    total += sign * value;
+                    sign = 1;
+                    expect_term = false;
+                }
+                _ => return Err(AssemblyError { line, message: format!("invalid expression '{}'", expression) }),
+            }
+        }
+
+        if expect_term {
+            return Err(AssemblyError { line, message: format!("invalid expression '{}'", expression) });
+        }
+        Ok(total)
+    }
+}
+
+fn statement_size(mnemonic: &str, operands: &[String]) -> Result<usize, String> {
+    match mnemonic {
+        "DB" => Ok(operands.len()),
+        "DW" => Ok(operands.len() * 2),
+        "SPRITE" => operands.iter().map(|operand| sprite_row(operand).map(|row| row.len())).sum(),
+        "LD" if operands.len() == 2 && operands[0].eq_ignore_ascii_case("I") && starts_with_keyword(&operands[1], "LONG") => Ok(4),
+        _ => Ok(2),
+    }
+}
+
+// A quoted row of 8 or 16 pixels, '#', 'X' or '1' for set and '.', ' ' or '0' for clear.
+fn sprite_row(operand: &str) -> Result<Vec<u8>, String> {
+    let pattern = operand.strip_prefix('"').and_then(|rest| rest.strip_suffix('"'))
+        .ok_or_else(|| format!("sprite rows must be quoted, got {}", operand))?;
+
+    if pattern.len() != 8 && pattern.len() != 16 {
+        return Err(format!("sprite row \"{}\" must be 8 or 16 pixels wide", pattern));
+    }
+
+    let mut bytes = vec![0u8; pattern.len() / 8];
+    for (index, pixel) in pattern.chars().enumerate() {
+        match pixel {
+            '#' | 'X' | 'x' | '1' => bytes[index / 8] |= 0x80 >> (index % 8),
+            '.' | ' ' | '0' => {}
+            _ => return Err(format!("invalid sprite pixel '{}'", pixel)),
+        }
+    }
+    Ok(bytes)
+}
+
+fn vx(operand: &str) -> Result<u8, String> {
+    register(operand).ok_or_else(|| format!("expected a register V0-VF, got '{}'", operand))
+}
+
+fn register(operand: &str) -> Option<u8> {
+    let digit = operand.strip_prefix('V').or_else(|| operand.strip_prefix('v'))?;
+    if digit.len() != 1 {
+        return None;
+    }
+    u8::from_str_radix(digit, 16).ok()
+}
+
+fn register_range(operand: &str) -> Result<(u8, u8), String> {
+    let (x, y) = operand.split_once('-').ok_or_else(|| format!("expected a register range, got '{}'", operand))?;
+    Ok((vx(x.trim())?, vx(y.trim())?))
+}
+
+fn starts_with_keyword(operand: &str, keyword: &str) -> bool {
+    operand.len() > keyword.len()
+        && operand[..keyword.len()].eq_ignore_ascii_case(keyword)
+        && operand[keyword.len()..].starts_with(char::is_whitespace)
+}
+
+// Byte and nibble immediates accept negative values as two's complement, e.g. `ADD V0, -1`.
+// Addresses and words never do.
+fn fit_value(value: i64, max: i64) -> Option<i64> {
+    let value = if value < 0 && max <= 0xFF && value >= -(max + 1) / 2 { value + max + 1 } else { value };
+    (0..=max).contains(&value).then_some(value)
+}
+
+fn parse_number(token: &str) -> Option<i64> {
+    let lower = token.to_ascii_lowercase();
+
+    if let Some(hex) = lower.strip_prefix("0x").or_else(|| lower.strip_prefix('#')).or_else(|| lower.strip_prefix('$')) {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = lower.strip_prefix("0b").or_else(|| lower.strip_prefix('%')) {
+        i64::from_str_radix(binary, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+fn tokenize_expression(expression: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+
+    for character in expression.chars() {
+        match character {
+            '+' | '-' => {
+                if !current.trim().is_empty() {
+                    tokens.push(current.trim().to_string());
+                }
+                current.clear();
+                tokens.push(character.to_string());
+            }
+            _ => current.push(character),
+        }
+    }
+
+    if !current.trim().is_empty() {
+        tokens.push(current.trim().to_string());
+    }
+    tokens
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_quotes = false;
+
+    for (index, character) in line.char_indices() {
+        match character {
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn split_label(text: &str) -> Option<(&str, &str)> {
+    let (first, _) = split_first_word(text);
+    let label = first.strip_suffix(':')?;
+    Some((label, &text[first.len()..]))
+}
+
+fn split_first_word(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    match text.find(char::is_whitespace) {
+        Some(index) => (&text[..index], text[index..].trim()),
+        None => (text, ""),
+    }
+}
+
+fn split_operands(text: &str) -> Vec<String> {
+    if text.trim().is_empty() {
+        return Vec::new();
+    }
+
+    let mut operands = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for character in text.chars() {
+        match character {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(character);
+            }
+            ',' if !in_quotes => {
+                operands.push(current.trim().to_string());
+                current.clear();
+            }
+            _ => current.push(character),
+        }
+    }
+    operands.push(current.trim().to_string());
+    operands
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut characters = name.chars();
+    matches!(characters.next(), Some(first) if first.is_ascii_alphabetic() || first == '_')
+        && characters.all(|character| character.is_ascii_alphanumeric() || character == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::{disassemble, Syntax};
+
+    #[test]
+    fn test_assemble_instructions() {
+        let rom = assemble("
+            CLS
+            LD V1, 0x20
+            ADD V1, V2
+            DRW V1, V2, 5
+            LD [I], V3
+            LD I, LONG 0x1234
+        ").unwrap();
+
+        assert_eq!(rom, [0x00, 0xE0, 0x61, 0x20, 0x81, 0x24, 0xD1, 0x25, 0xF3, 0x55, 0xF0, 0x00, 0x12, 0x34]);
+    }
+
+    #[test]
+    fn test_assemble_labels_and_constants() {
+        let rom = assemble("
+            SPEED EQU 3
+            start:
+                LD V0, SPEED
+                CALL routine
+                JP start
+            routine: RET
+        ").unwrap();
+
+        assert_eq!(rom, [0x60, 0x03, 0x22, 0x06, 0x12, 0x00, 0x00, 0xEE]);
+    }
+
+    #[test]
+    fn test_assemble_data_directives() {
+        let rom = assemble("
+            LD I, sprite + 1  ; skip the first byte
+            sprite:
+            DB 0xFF, 1, %101
+            DW 0xBEEF
+            SPRITE \"#..##..#\", \"########........\"
+        ").unwrap();
+
+        assert_eq!(rom, [0xA2, 0x03, 0xFF, 0x01, 0x05, 0xBE, 0xEF, 0x99, 0xFF, 0x00]);
+    }
+
+    #[test]
+    fn test_assemble_org() {
+        let rom = assemble("
+            JP main
+            ORG 0x210
+            main: JP main
+        ").unwrap();
+
+        assert_eq!(rom.len(), 0x12);
+        assert_eq!(&rom[..2], &[0x12, 0x10]);
+        assert_eq!(&rom[0x10..], &[0x12, 0x10]);
+    }
+
+    #[test]
+    fn test_assemble_org_rejects_overlapping_output() {
+        let errors = assemble("CLS\nCLS\nORG 0x202\nRET\nORG 0x204\nDB 1\nORG 0x205\nDB 2").unwrap_err();
+
+        assert_eq!(errors, [AssemblyError { line: 4, message: "address 0x202 is already assembled".to_string() }]);
+        assert_eq!(assemble("ORG 0x210\nCLS\nORG 0x200\nCLS").unwrap()[..2], [0x00, 0xE0], "gaps can be filled");
+    }
+
+    #[test]
+    fn test_assemble_reports_errors_with_line_numbers() {
+        let errors = assemble("CLS\nLD V1, 0x123\nJP nowhere\nFOO V1").unwrap_err();
+        let lines: Vec<usize> = errors.iter().map(|error| error.line).collect();
+
+        assert_eq!(lines, [2, 3, 4]);
+        assert_eq!(errors[1].to_string(), "line 3: unknown symbol or number 'nowhere'");
+    }
+
+    #[test]
+    fn test_assemble_negative_immediates() {
+        assert_eq!(assemble("ADD V0, -128
+DRW V0, V1, -8").unwrap(), [0x70, 0x80, 0xD0, 0x18]);
+
+        let errors = assemble("ADD V0, -129
+JP -1
+DW -1").unwrap_err();
+        let lines: Vec<usize> = errors.iter().map(|error| error.line).collect();
+        assert_eq!(lines, [1, 2, 3]);
+        assert_eq!(errors[0].to_string(), "line 1: value -129 of '-129' does not fit into 0xFF");
+    }
+
+    #[test]
+    fn test_assemble_round_trips_disassembly() {
+        let source = "LD V1, 0x20\nSE V1, V2\nLD I, LONG 0x1234\nLD V3-V5, [I]\nJP V0, 0x300\nSHR V4, V4\nLD HF, V2";
+        let rom = assemble(source).unwrap();
+
+        let mut memory = vec![0; 0x200];
+        memory.extend_from_slice(&rom);
+        let text: Vec<String> = disassemble(&memory, 0x200, memory.len(), 0, Syntax::Cowgod)
+            .into_iter()
+            .map(|line| line.text)
+            .collect();
+        assert_eq!(text.join("\n"), source);
+    }
+}
@@ -0,0 +1,834 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::{fit_value, parse_number, AssemblyError, MAX_ADDRESS, PROGRAM_START_ADDRESS};
+use crate::chip8::Instruction;
+
+/// A compiled Octo program: the ROM image starting at 0x200 and the address of every label.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OctoProgram {
+    pub bytes: Vec<u8>,
+    pub labels: BTreeMap<String, u16>,
+}
+
+/// Compiles Octo source into a ROM image.
+///
+/// Supports labels, `:const`, `:alias`, `:org`, `:next`, `:macro` and `:calc`, the structured
+/// `loop`/`while`/`again` and `if ... then`/`if ... begin ... else ... end` forms and all
+/// CHIP-8, SUPER-CHIP and XO-CHIP statements. Compilation stops at the first error.
+pub fn compile_octo(source: &str) -> Result<OctoProgram, AssemblyError> {
+    Compiler::new(source).compile()
+}
+
+#[derive(Clone)]
+struct Token {
+    text: String,
+    line: usize,
+}
+
+struct Fixup {
+    address: usize,
+    label: String,
+    line: usize,
+    long: bool,
+}
+
+enum Flow {
+    If { jump_address: usize, line: usize },
+    Else { jump_address: usize, line: usize },
+    Loop { start: usize, breaks: Vec<usize>, line: usize },
+}
+
+struct Macro {
+    parameters: Vec<String>,
+    body: Vec<Token>,
+}
+
+struct Compiler {
+    // Pending tokens in reverse order, so the next one can be popped off the end
+    tokens: Vec<Token>,
+    line: usize,
+    here: usize,
+    bytes: Vec<u8>,
+    // Which bytes were emitted, the others are gaps left by `:org`
+    emitted: Vec<bool>,
+    labels: BTreeMap<String, u16>,
+    constants: HashMap<String, i64>,
+    aliases: HashMap<String, u8>,
+    macros: HashMap<String, Macro>,
+    fixups: Vec<Fixup>,
+    flow: Vec<Flow>,
+}
+
+impl Compiler {
+    fn new(source: &str) -> Compiler {
+        let mut tokens: Vec<Token> = source.lines()
+            .enumerate()
+            .flat_map(|(index, line)| {
+                let code = line.split_once('#').map_or(line, |(code, _)| code);
+                code.split_whitespace().map(move |text| Token { text: text.to_string(), line: index + 1 })
+            })
+            .collect();
+        tokens.reverse();
+
+        Compiler {
+            tokens,
+            line: 1,
+            here: PROGRAM_START_ADDRESS,
+            bytes: Vec::new(),
+            emitted: Vec::new(),
+            labels: BTreeMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            macros: HashMap::new(),
+            fixups: Vec::new(),
+            flow: Vec::new(),
+        }
+    }
+
+    fn compile(mut self) -> Result<OctoProgram, AssemblyError> {
+        // Like Octo, programs that don't start with `: main` get a jump to it at 0x200
+        let starts_with_main = matches!(self.tokens.as_slice(), [.., main, colon] if colon.text == ":" && main.text == "main");
+        if !starts_with_main {
+            self.address_fixup("main", 0, false);
+            self.emit_instruction(Instruction::JpAddr { nnn: 0 })?;
+        }
+
+        while let Some(token) = self.next_token() {
+            self.statement(token)?;
+        }
+
+        if let Some(flow) = self.flow.pop() {
+            let (line, name) = match flow {
+                Flow::If { line, .. } | Flow::Else { line, .. } => (line, "begin"),
+                Flow::Loop { line, .. } => (line, "loop"),
+            };
+            return Err(AssemblyError { line, message: format!("'{}' is never closed", name) });
+        }
+
+        for fixup in std::mem::take(&mut self.fixups) {
+            let value = *self.labels.get(&fixup.label).ok_or_else(|| AssemblyError {
+                line: fixup.line,
+                message: format!("undefined label '{}'", fixup.label),
+            })?;
+
+            if fixup.long {
+                self.bytes[fixup.address..fixup.address + 2].copy_from_slice(&value.to_be_bytes());
+            } else if value > 0xFFF {
+                return Err(AssemblyError { line: fixup.line, message: format!("label '{}' is above 0xFFF", fixup.label) });
+            } else {
+                self.bytes[fixup.address] |= (value >> 8) as u8;
+                self.bytes[fixup.address + 1] = value as u8;
+            }
+        }
+
+        Ok(OctoProgram { bytes: self.bytes, labels: self.labels })
+    }
+
+    fn statement(&mut self, token: Token) -> Result<(), AssemblyError> {
+        match token.text.as_str() {
+            ":" => {
+                let name = self.name()?;
+                self.define_label(name, self.here)
+            }
+            ":next" => {
+                let name = self.name()?;
+                self.define_label(name, self.here + 1)
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.constant()?;
+                self.define_constant(name, value)
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let register = self.register()?;
+                self.aliases.insert(name, register);
+                Ok(())
+            }
+            ":org" => {
+                let address = self.constant()?;
+                if !(PROGRAM_START_ADDRESS as i64..=MAX_ADDRESS as i64).contains(&address) {
+                    return self.error(format!(":org address 0x{:X} is outside of 0x200-0xFFFF", address));
+                }
+                self.here = address as usize;
+                Ok(())
+            }
+            ":macro" => self.define_macro(),
+            ":calc" => {
+                let name = self.name()?;
+                self.expect("{")?;
+                let expression = self.braced_tokens()?;
+                let value = self.calculate(&expression)?;
+                self.define_constant(name, value)
+            }
+            ":call" => {
+                let nnn = self.address(false)?;
+                self.emit_instruction(Instruction::CallAddr { nnn })
+            }
+            ";" | "return" => self.emit_instruction(Instruction::Ret),
+            "clear" => self.emit_instruction(Instruction::Cls),
+            "hires" => self.emit_instruction(Instruction::High),
+            "lores" => self.emit_instruction(Instruction::Low),
+            "exit" => self.emit_instruction(Instruction::Exit),
+            "scroll-left" => self.emit_instruction(Instruction::Scl),
+            "scroll-right" => self.emit_instruction(Instruction::Scr),
+            "scroll-down" => {
+                let n = self.value(0xF)? as u8;
+                self.emit_instruction(Instruction::ScdNibble { n })
+            }
+            "scroll-up" => {
+                let n = self.value(0xF)? as u8;
+                self.emit_instruction(Instruction::ScuNibble { n })
+            }
+            "plane" => {
+                let n = self.value(0x3)? as u8;
+                self.emit_instruction(Instruction::PlaneN { n })
+            }
+            "jump" => {
+                let nnn = self.address(false)?;
+                self.emit_instruction(Instruction::JpAddr { nnn })
+            }
+            "jump0" => {
+                let nnn = self.address(false)?;
+                self.emit_instruction(Instruction::JpV0Addr { nnn })
+            }
+            "native" => {
+                let nnn = self.address(false)?;
+                self.emit_instruction(Instruction::Sys { nnn })
+            }
+            "sprite" => {
+                let x = self.register()?;
+                let y = self.register()?;
+                let n = self.value(0xF)? as u8;
+                self.emit_instruction(Instruction::DrwVxVyNibble { x, y, n })
+            }
+            "bcd" => {
+                let x = self.register()?;
+                self.emit_instruction(Instruction::LdBVx { x })
+            }
+            "save" | "load" => {
+                let x = self.register()?;
+                let range = if self.peek() == Some("-") {
+                    self.next_token();
+                    Some(self.register()?)
+                } else {
+                    None
+                };
+
+                let instruction = match (token.text.as_str(), range) {
+                    ("save", Some(y)) => Instruction::LdIVxVy { x, y },
+                    ("save", None) => Instruction::LdIVx { x },
+                    (_, Some(y)) => Instruction::LdVxVyI { x, y },
+                    (_, None) => Instruction::LdVxI { x },
+                };
+                self.emit_instruction(instruction)
+            }
+            "saveflags" => {
+                let x = self.register()?;
+                self.emit_instruction(Instruction::LdRVx { x })
+            }
+            "loadflags" => {
+                let x = self.register()?;
+                self.emit_instruction(Instruction::LdVxR { x })
+            }
+            "delay" | "buzzer" => {
+                self.expect(":=")?;
+                let x = self.register()?;
+                let instruction = if token.text == "delay" { Instruction::LdDtVx { x } } else { Instruction::LdStVx { x } };
+                self.emit_instruction(instruction)
+            }
+            "i" => self.i_statement(),
+            "loop" => {
+                self.flow.push(Flow::Loop { start: self.here, breaks: Vec::new(), line: token.line });
+                Ok(())
+            }
+            "while" => {
+                let (_, skip_if) = self.condition()?;
+                self.emit_instruction(skip_if)?;
+                let jump_address = self.here;
+                self.emit_instruction(Instruction::JpAddr { nnn: 0 })?;
+
+                match self.flow.iter_mut().rev().find(|flow| matches!(flow, Flow::Loop { .. })) {
+                    Some(Flow::Loop { breaks, .. }) => {
+                        breaks.push(jump_address);
+                        Ok(())
+                    }
+                    _ => self.error("'while' outside of a loop".to_string()),
+                }
+            }
+            "again" => match self.flow.pop() {
+                Some(Flow::Loop { start, breaks, .. }) => {
+                    let nnn = self.check_range("loop", start as i64, 0xFFF)? as u16;
+                    self.emit_instruction(Instruction::JpAddr { nnn })?;
+                    for jump_address in breaks {
+                        self.patch_jump(jump_address)?;
+                    }
+                    Ok(())
+                }
+                _ => self.error("'again' without a matching 'loop'".to_string()),
+            },
+            "if" => {
+                let (skip_unless, skip_if) = self.condition()?;
+                match self.next_token().map(|token| token.text) {
+                    Some(keyword) if keyword == "then" => self.emit_instruction(skip_unless),
+                    Some(keyword) if keyword == "begin" => {
+                        self.emit_instruction(skip_if)?;
+                        self.flow.push(Flow::If { jump_address: self.here, line: token.line });
+                        self.emit_instruction(Instruction::JpAddr { nnn: 0 })
+                    }
+                    _ => self.error("expected 'then' or 'begin' after the condition".to_string()),
+                }
+            }
+            "else" => match self.flow.pop() {
+                Some(Flow::If { jump_address, .. }) => {
+                    let else_jump_address = self.here;
+                    self.emit_instruction(Instruction::JpAddr { nnn: 0 })?;
+                    self.patch_jump(jump_address)?;
+                    self.flow.push(Flow::Else { jump_address: else_jump_address, line: token.line });
+                    Ok(())
+                }
+                _ => self.error("'else' without a matching 'begin'".to_string()),
+            },
+            "end" => match self.flow.pop() {
+                Some(Flow::If { jump_address, .. }) | Some(Flow::Else { jump_address, .. }) => self.patch_jump(jump_address),
+                _ => self.error("'end' without a matching 'begin'".to_string()),
+            },
+            text if self.macros.contains_key(text) => self.expand_macro(text),
+            text if self.register_of(text).is_some() => self.register_statement(self.register_of(text).unwrap()),
+            text => {
+                if let Some(&nnn) = self.labels.get(text) {
+                    let nnn = self.check_range(text, nnn as i64, 0xFFF)? as u16;
+                    return self.emit_instruction(Instruction::CallAddr { nnn });
+                }
+                if let Some(value) = self.lookup_value(text) {
+                    let byte = self.check_range(text, value, 0xFF)?;
+                    return self.emit(&[byte as u8]);
+                }
+                if text.starts_with(':') || text.starts_with(|character: char| character.is_ascii_digit() || character == '-') {
+                    return self.error(format!("unexpected '{}'", text));
+                }
+
+                // Any other name is a call to a (possibly later defined) label
+                self.address_fixup(text, 0, false);
+                self.emit_instruction(Instruction::CallAddr { nnn: 0 })
+            }
+        }
+    }
+
+    fn i_statement(&mut self) -> Result<(), AssemblyError> {
+        match self.next_token().map(|token| token.text).as_deref() {
+            Some(":=") => match self.peek() {
+                Some("hex") => {
+                    self.next_token();
+                    let x = self.register()?;
+                    self.emit_instruction(Instruction::LdFVx { x })
+                }
+                Some("bighex") => {
+                    self.next_token();
+                    let x = self.register()?;
+                    self.emit_instruction(Instruction::LdHfVx { x })
+                }
+                Some("long") => {
+                    self.next_token();
+                    let address = self.address(true)?;
+                    self.emit_instruction(Instruction::LdILong)?;
+                    self.emit(&address.to_be_bytes())
+                }
+                _ => {
+                    let nnn = self.address(false)?;
+                    self.emit_instruction(Instruction::LdIAddr { nnn })
+                }
+            },
+            Some("+=") => {
+                let x = self.register()?;
+                self.emit_instruction(Instruction::AddIVx { x })
+            }
+            _ => self.error("expected ':=' or '+=' after 'i'".to_string()),
+        }
+    }
+
+    fn register_statement(&mut self, x: u8) -> Result<(), AssemblyError> {
+        let operator = self.next_token().map(|token| token.text).unwrap_or_default();
+        let operand = self.peek().unwrap_or_default().to_string();
+        let y = self.register_of(&operand);
+
+        let instruction = match (operator.as_str(), y) {
+            (":=", _) if operand == "random" => {
+                self.next_token();
+                Instruction::RndVxByte { x, kk: self.value(0xFF)? as u8 }
+            }
+            (":=", _) if operand == "delay" => {
+                self.next_token();
+                Instruction::LdVxDt { x }
+            }
+            (":=", _) if operand == "key" => {
+                self.next_token();
+                Instruction::LdVxK { x }
+            }
+            (":=", Some(y)) => Instruction::LdVxVy { x, y },
+            (":=", None) => Instruction::LdVxByte { x, kk: self.value(0xFF)? as u8 },
+            ("+=", Some(y)) => Instruction::AddVxVy { x, y },
+            ("+=", None) => Instruction::AddVxByte { x, kk: self.value(0xFF)? as u8 },
+            ("-=", Some(y)) => Instruction::SubVxVy { x, y },
+            ("-=", None) => Instruction::AddVxByte { x, kk: (self.value(0xFF)? as u8).wrapping_neg() },
+            ("=-", Some(y)) => Instruction::SubnVxVy { x, y },
+            ("|=", Some(y)) => Instruction::OrVxVy { x, y },
+            ("&=", Some(y)) => Instruction::AndVxVy { x, y },
+            ("^=", Some(y)) => Instruction::XorVxVy { x, y },
+            (">>=", Some(y)) => Instruction::ShrVxVy { x, y },
+            ("<<=", Some(y)) => Instruction::ShlVxVy { x, y },
+            _ => return self.error(format!("invalid register operation '{} {}'", operator, operand)),
+        };
+
+        if y.is_some() {
+            self.next_token();
+        }
+        self.emit_instruction(instruction)
+    }
+
+    // Returns the instruction that skips when the condition is false and the one that skips when it is true.
+    fn condition(&mut self) -> Result<(Instruction, Instruction), AssemblyError> {
+        let x = self.register()?;
+        let operator = self.next_token().map(|token| token.text).unwrap_or_default();
+
+        match operator.as_str() {
+            "key" => Ok((Instruction::SknpVx { x }, Instruction::SkpVx { x })),
+            "-key" => Ok((Instruction::SkpVx { x }, Instruction::SknpVx { x })),
+            "==" | "!=" => {
+                let operand = self.peek().unwrap_or_default().to_string();
+                let (equal, not_equal) = match self.register_of(&operand) {
+                    Some(y) => {
+                        self.next_token();
+                        (Instruction::SeVxVy { x, y }, Instruction::SneVxVy { x, y })
+                    }
+                    None => {
+                        let kk = self.value(0xFF)? as u8;
+                        (Instruction::SeVxByte { x, kk }, Instruction::SneVxByte { x, kk })
+                    }
+                };
+
+                if operator == "==" { Ok((not_equal, equal)) } else { Ok((equal, not_equal)) }
+            }
+            _ => self.error(format!("unknown condition operator '{}'", operator)),
+        }
+    }
+
+    fn define_macro(&mut self) -> Result<(), AssemblyError> {
+        let name = self.name()?;
+        let mut parameters = Vec::new();
+
+        loop {
+            match self.next_token() {
+                Some(token) if token.text == "{" => break,
+                Some(token) => parameters.push(token.text),
+                None => return self.error(format!("macro '{}' has no body", name)),
+            }
+        }
+
+        let body = self.braced_tokens()?;
+        self.macros.insert(name, Macro { parameters, body });
+        Ok(())
+    }
+
+    fn expand_macro(&mut self, name: &str) -> Result<(), AssemblyError> {
+        let count = self.macros[name].parameters.len();
+        let mut arguments = HashMap::new();
+
+        for index in 0..count {
+            let Some(argument) = self.next_token() else {
+                return self.error(format!("macro '{}' expects {} arguments", name, count));
+            };
+            arguments.insert(self.macros[name].parameters[index].clone(), argument.text);
+        }
+
+        let expansion: Vec<Token> = self.macros[name].body.iter()
+            .rev()
+            .map(|token| Token { text: arguments.get(&token.text).unwrap_or(&token.text).clone(), line: token.line })
+            .collect();
+        self.tokens.extend(expansion);
+        Ok(())
+    }
+
+    // Collects tokens up to the `}` matching an already consumed `{`.
+    fn braced_tokens(&mut self) -> Result<Vec<Token>, AssemblyError> {
+        let mut tokens = Vec::new();
+        let mut depth = 1;
+
+        while let Some(token) = self.next_token() {
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                return Ok(tokens);
+            }
+            tokens.push(token);
+        }
+        self.error("missing '}'".to_string())
+    }
+
+    // Octo evaluates `:calc` expressions right to left without operator precedence.
+    fn calculate(&self, tokens: &[Token]) -> Result<i64, AssemblyError> {
+        let (value, rest) = self.calculate_expression(tokens)?;
+        match rest.first() {
+            Some(token) => self.error(format!("unexpected '{}' in expression", token.text)),
+            None => Ok(value),
+        }
+    }
+
+    fn calculate_expression<'a>(&self, tokens: &'a [Token]) -> Result<(i64, &'a [Token]), AssemblyError> {
+        let (left, rest) = self.calculate_term(tokens)?;
+        let Some((operator, rest)) = rest.split_first().filter(|(token, _)| token.text != ")") else {
+            return Ok((left, rest));
+        };
+
+        let (right, rest) = self.calculate_expression(rest)?;
+        let value = match operator.text.as_str() {
+            "+" => left.wrapping_add(right),
+            "-" => left.wrapping_sub(right),
+            "*" => left.wrapping_mul(right),
+            "/" | "%" if right == 0 => return self.error("division by zero".to_string()),
+            "/" => left / right,
+            "%" => left % right,
+            "&" => left & right,
+            "|" => left | right,
+            "^" => left ^ right,
+            "<<" => left << (right & 63),
+            ">>" => left >> (right & 63),
+            "min" => left.min(right),
+            "max" => left.max(right),
+            text => return self.error(format!("unknown operator '{}'", text)),
+        };
+        Ok((value, rest))
+    }
+
+    fn calculate_term<'a>(&self, tokens: &'a [Token]) -> Result<(i64, &'a [Token]), AssemblyError> {
+        let Some((token, rest)) = tokens.split_first() else {
+            return self.error("incomplete expression".to_string());
+        };
+
+        match token.text.as_str() {
+            "(" => {
+                let (value, rest) = self.calculate_expression(rest)?;
+                match rest.split_first() {
+                    Some((close, rest)) if close.text == ")" => Ok((value, rest)),
+                    _ => self.error("missing ')'".to_string()),
+                }
+            }
+            "-" => self.calculate_term(rest).map(|(value, rest)| (-value, rest)),
+            "~" => self.calculate_term(rest).map(|(value, rest)| (!value, rest)),
+            "!" => self.calculate_term(rest).map(|(value, rest)| ((value == 0) as i64, rest)),
+            "HERE" => Ok((self.here as i64, rest)),
+            text => match self.lookup_value(text) {
+                Some(value) => Ok((value, rest)),
+                None => self.error(format!("unknown name '{}' in expression", text)),
+            },
+        }
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        let token = self.tokens.pop()?;
+        self.line = token.line;
+        Some(token)
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.last().map(|token| token.text.as_str())
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), AssemblyError> {
+        match self.next_token() {
+            Some(token) if token.text == text => Ok(()),
+            Some(token) => self.error(format!("expected '{}', got '{}'", text, token.text)),
+            None => self.error(format!("expected '{}'", text)),
+        }
+    }
+
+    fn name(&mut self) -> Result<String, AssemblyError> {
+        match self.next_token() {
+            Some(token) if parse_number(&token.text).is_none() => Ok(token.text),
+            Some(token) => self.error(format!("'{}' is not a valid name", token.text)),
+            None => self.error("expected a name".to_string()),
+        }
+    }
+
+    fn register(&mut self) -> Result<u8, AssemblyError> {
+        let token = self.next_token().map(|token| token.text).unwrap_or_default();
+        match self.register_of(&token) {
+            Some(register) => Ok(register),
+            None => self.error(format!("expected a register v0-vf, got '{}'", token)),
+        }
+    }
+
+    fn register_of(&self, text: &str) -> Option<u8> {
+        if let Some(&register) = self.aliases.get(text) {
+            return Some(register);
+        }
+
+        let digit = text.strip_prefix('v').or_else(|| text.strip_prefix('V'))?;
+        if digit.len() != 1 {
+            return None;
+        }
+        u8::from_str_radix(digit, 16).ok()
+    }
+
+    fn lookup_value(&self, text: &str) -> Option<i64> {
+        parse_number(text)
+            .or_else(|| self.constants.get(text).copied())
+            .or_else(|| self.labels.get(text).map(|&address| address as i64))
+    }
+
+    fn constant(&mut self) -> Result<i64, AssemblyError> {
+        let token = self.next_token().map(|token| token.text).unwrap_or_default();
+        match self.lookup_value(&token) {
+            Some(value) => Ok(value),
+            None => self.error(format!("unknown number or constant '{}'", token)),
+        }
+    }
+
+    fn value(&mut self, max: i64) -> Result<i64, AssemblyError> {
+        let token = self.peek().unwrap_or_default().to_string();
+        let value = self.constant()?;
+        self.check_range(&token, value, max)
+    }
+
+    fn check_range(&self, text: &str, value: i64, max: i64) -> Result<i64, AssemblyError> {
+        match fit_value(value, max) {
+            Some(value) => Ok(value),
+            None => self.error(format!("value {} of '{}' does not fit into 0x{:X}", value, text, max)),
+        }
+    }
+
+    // Address operand of the instruction about to be emitted; unknown labels are patched at the end.
+    fn address(&mut self, long: bool) -> Result<u16, AssemblyError> {
+        let Some(token) = self.next_token() else {
+            return self.error("expected an address".to_string());
+        };
+
+        if let Some(value) = parse_number(&token.text).or_else(|| self.constants.get(&token.text).copied()) {
+            let max = if long { 0xFFFF } else { 0xFFF };
+            return self.check_range(&token.text, value, max).map(|value| value as u16);
+        }
+
+        let offset = if long { 2 } else { 0 };
+        self.address_fixup(&token.text, offset, long);
+        Ok(0)
+    }
+
+    fn address_fixup(&mut self, label: &str, offset: usize, long: bool) {
+        self.fixups.push(Fixup { address: self.here + offset - PROGRAM_START_ADDRESS, label: label.to_string(), line: self.line, long });
+    }
+
+    fn patch_jump(&mut self, jump_address: usize) -> Result<(), AssemblyError> {
+        if self.here > 0xFFF {
+            return self.error("structured jump target is above 0xFFF".to_string());
+        }
+
+        let offset = jump_address - PROGRAM_START_ADDRESS;
+        self.bytes[offset..offset + 2].copy_from_slice(&Instruction::JpAddr { nnn: self.here as u16 }.encode().to_be_bytes());
+        Ok(())
+    }
+
+    fn define_label(&mut self, name: String, address: usize) -> Result<(), AssemblyError> {
+        if self.is_defined(&name) {
+            return self.error(format!("'{}' is defined more than once", name));
+        }
+        self.labels.insert(name, address as u16);
+        Ok(())
+    }
+
+    fn define_constant(&mut self, name: String, value: i64) -> Result<(), AssemblyError> {
+        if self.labels.contains_key(&name) {
+            return self.error(format!("'{}' is already a label", name));
+        }
+        self.constants.insert(name, value);
+        Ok(())
+    }
+
+    fn is_defined(&self, name: &str) -> bool {
+        self.labels.contains_key(name) || self.constants.contains_key(name)
+    }
+
+    fn emit_instruction(&mut self, instruction: Instruction) -> Result<(), AssemblyError> {
+        self.emit(&instruction.encode().to_be_bytes())
+    }
+
+    fn emit(&mut self, bytes: &[u8]) -> Result<(), AssemblyError> {
+        if self.here + bytes.len() > MAX_ADDRESS + 1 {
+            return self.error("program does not fit into 64 KiB of memory".to_string());
+        }
+
+        let offset = self.here - PROGRAM_START_ADDRESS;
+        let end = offset + bytes.len();
+        if self.bytes.len() < end {
+            self.bytes.resize(end, 0);
+            self.emitted.resize(end, false);
+        }
+        // An `:org` back into earlier output must not silently replace it
+        if let Some(overlap) = self.emitted[offset..end].iter().position(|&emitted| emitted) {
+            return self.error(format!("address 0x{:X} is already assembled", self.here + overlap));
+        }
+
+        self.bytes[offset..end].copy_from_slice(bytes);
+        self.emitted[offset..end].fill(true);
+        self.here += bytes.len();
+        Ok(())
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, AssemblyError> {
+        Err(AssemblyError { line: self.line, message })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::{disassemble, Syntax};
+
+    #[test]
+    fn test_compile_statements() {
+        let program = compile_octo("
+            : main
+                clear
+                v1 := 0x20
+                v1 += v2
+                v3 -= 1
+                i := long 0x1234
+                sprite v1 v2 5
+                save v1 - v3
+        ").unwrap();
+
+        assert_eq!(program.bytes, [
+            0x00, 0xE0, 0x61, 0x20, 0x81, 0x24, 0x73, 0xFF, 0xF0, 0x00, 0x12, 0x34, 0xD1, 0x25, 0x51, 0x32,
+        ]);
+    }
+
+    #[test]
+    fn test_compile_inserts_jump_to_main() {
+        let program = compile_octo("
+            : draw  # called before it is defined below
+                return
+            : main
+                draw
+                jump main
+        ").unwrap();
+
+        assert_eq!(program.bytes, [0x12, 0x04, 0x00, 0xEE, 0x22, 0x02, 0x12, 0x04]);
+        assert_eq!(program.labels["main"], 0x204);
+        assert_eq!(program.labels["draw"], 0x202);
+    }
+
+    #[test]
+    fn test_compile_const_alias_calc_and_next() {
+        let program = compile_octo("
+            :const SPEED 3
+            :alias ball v5
+            :calc DOUBLE { SPEED * 2 + 1 }
+            : main
+                ball := SPEED
+                ball += DOUBLE
+            :next target
+                v0 := 0
+        ").unwrap();
+
+        assert_eq!(program.bytes, [0x12, 0x02, 0x65, 0x03, 0x75, 0x09, 0x60, 0x00]);
+        assert_eq!(program.labels["target"], 0x207);
+    }
+
+    #[test]
+    fn test_compile_calc_is_right_to_left() {
+        let program = compile_octo(":calc X { 2 * 3 + 1 }\n:calc Y { ( 2 * 3 ) + 1 }\n: main X Y").unwrap();
+
+        assert_eq!(program.bytes, [0x12, 0x02, 8, 7]);
+    }
+
+    #[test]
+    fn test_compile_if_else_and_loops() {
+        let program = compile_octo("
+            : main
+                if v0 == 1 then v1 := 2
+                if v0 key begin
+                    v1 := 3
+                else
+                    v1 := 4
+                end
+                loop
+                    while v2 != 5
+                    v2 += 1
+                again
+        ").unwrap();
+
+        assert_eq!(program.bytes, [
+            0x40, 0x01, 0x61, 0x02, // if v0 == 1 then
+            0xE0, 0x9E, 0x12, 0x0C, 0x61, 0x03, 0x12, 0x0E, 0x61, 0x04, // begin / else / end
+            0x42, 0x05, 0x12, 0x16, 0x72, 0x01, 0x12, 0x0E, // loop / while / again
+        ]);
+    }
+
+    #[test]
+    fn test_compile_macros_and_org() {
+        let program = compile_octo("
+            : main
+            :macro set-both A B { v0 := A v1 := B }
+                set-both 7 9
+            :org 0x20A
+                0xAB
+        ").unwrap();
+
+        assert_eq!(program.bytes, [0x60, 0x07, 0x61, 0x09, 0, 0, 0, 0, 0, 0, 0xAB]);
+    }
+
+    #[test]
+    fn test_compile_org_rejects_overlapping_output() {
+        let error = compile_octo(": main\n  v0 := 1\n:org 0x201\n  0xAB").unwrap_err();
+
+        assert_eq!(error.to_string(), "line 4: address 0x201 is already assembled");
+    }
+
+    #[test]
+    fn test_compile_reports_errors_with_line_numbers() {
+        let unknown = compile_octo(": main\n  v0 := 1\n  v0 @= 2").unwrap_err();
+        let undefined = compile_octo(": main\n  jump nowhere").unwrap_err();
+        let unclosed = compile_octo(": main\n  loop\n  v0 += 1").unwrap_err();
+
+        assert_eq!(unknown.to_string(), "line 3: invalid register operation '@= 2'");
+        assert_eq!(undefined.to_string(), "line 2: undefined label 'nowhere'");
+        assert_eq!(unclosed.to_string(), "line 2: 'loop' is never closed");
+    }
+
+    #[test]
+    fn test_compile_rejects_known_targets_above_0xfff() {
+        let call = compile_octo(":org 0x1000\n: far\n  return\n: main\n  far").unwrap_err();
+        let again = compile_octo(":org 0x1000\n: main\n  loop\n  again").unwrap_err();
+
+        assert_eq!(call.to_string(), "line 5: value 4096 of 'far' does not fit into 0xFFF");
+        assert_eq!(again.to_string(), "line 4: value 4096 of 'loop' does not fit into 0xFFF");
+    }
+
+    #[test]
+    fn test_compile_negative_immediates() {
+        let program = compile_octo(": main\n  v0 := -128").unwrap();
+        let byte = compile_octo(": main\n  v0 := -129").unwrap_err();
+        let address = compile_octo(": main\n  jump -1").unwrap_err();
+
+        assert_eq!(program.bytes, [0x60, 0x80]);
+        assert_eq!(byte.to_string(), "line 2: value -129 of '-129' does not fit into 0xFF");
+        assert_eq!(address.to_string(), "line 2: value -1 of '-1' does not fit into 0xFFF");
+    }
+
+    #[test]
+    fn test_compile_round_trips_octo_disassembly() {
+        let bytes = [
+            0x61, 0x20, 0x31, 0x20, 0x82, 0x16, 0xE3, 0xA1, 0xF4, 0x29, 0xF2, 0x65, 0xD1, 0x2F, 0x22, 0x00,
+        ];
+        let mut memory = vec![0; 0x200];
+        memory.extend_from_slice(&bytes);
+
+        let lines = disassemble(&memory, 0x200, memory.len(), 0, Syntax::Octo);
+        let source: Vec<String> = lines.into_iter().map(|line| line.text).collect();
+        let program = compile_octo(&format!(": main\n{}", source.join("\n"))).unwrap();
+
+        assert_eq!(program.bytes, bytes);
+    }
+}
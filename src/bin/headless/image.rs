@@ -0,0 +1,156 @@
+use chip_8_emulator::chip8::Display;
+
+// Same colours as the web frontend: off, plane 1, plane 2, both planes
+const PALETTE: [[u8; 3]; 4] = [[0, 0, 0], [255, 255, 255], [170, 170, 170], [85, 85, 85]];
+const ASCII_PIXELS: [char; 4] = ['.', '#', '+', '@'];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    Ascii,
+    Pbm,
+    Png,
+}
+
+impl ImageFormat {
+    pub fn from_name(name: &str) -> Option<ImageFormat> {
+        match name {
+            "ascii" | "txt" => Some(ImageFormat::Ascii),
+            "pbm" => Some(ImageFormat::Pbm),
+            "png" => Some(ImageFormat::Png),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &str) -> Option<ImageFormat> {
+        let (_, extension) = path.rsplit_once('.')?;
+        ImageFormat::from_name(&extension.to_ascii_lowercase())
+    }
+}
+
+pub fn encode(display: &Display, format: ImageFormat, scale: usize) -> Vec<u8> {
+    match format {
+        ImageFormat::Ascii => ascii(display).into_bytes(),
+        ImageFormat::Pbm => pbm(display, scale).into_bytes(),
+        ImageFormat::Png => png(display, scale),
+    }
+}
+
+fn scaled_rows(display: &Display, scale: usize) -> impl Iterator<Item = Vec<u8>> + '_ {
+    (0..display.height() * scale).map(move |y| {
+        (0..display.width() * scale).map(|x| display[y / scale][x / scale] & 0x3).collect()
+    })
+}
+
+// One character per pixel, one line per row.
+pub fn ascii(display: &Display) -> String {
+    scaled_rows(display, 1)
+        .map(|row| row.iter().map(|&pixel| ASCII_PIXELS[pixel as usize]).collect::<String>() + "\n")
+        .collect()
+}
+
+// Plain (P1) bitmap, any lit plane counts as black ink.
+pub fn pbm(display: &Display, scale: usize) -> String {
+    let mut output = format!("P1\n{} {}\n", display.width() * scale, display.height() * scale);
+    for row in scaled_rows(display, scale) {
+        let bits: Vec<&str> = row.iter().map(|&pixel| if pixel != 0 { "1" } else { "0" }).collect();
+        output.push_str(&bits.join(" "));
+        output.push('\n');
+    }
+    output
+}
+
+// Indexed colour PNG with uncompressed deflate blocks, which keeps the encoder dependency free.
+pub fn png(display: &Display, scale: usize) -> Vec<u8> {
+    let mut header = Vec::new();
+    header.extend_from_slice(&((display.width() * scale) as u32).to_be_bytes());
+    header.extend_from_slice(&((display.height() * scale) as u32).to_be_bytes());
+    header.extend_from_slice(&[8, 3, 0, 0, 0]); // 8 bit, indexed colour, deflate, no filter, no interlace
+
+    let mut image = Vec::new();
+    for row in scaled_rows(display, scale) {
+        image.push(0); // filter type none
+        image.extend_from_slice(&row);
+    }
+
+    let mut output = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+    write_chunk(&mut output, b"IHDR", &header);
+    write_chunk(&mut output, b"PLTE", &PALETTE.concat());
+    write_chunk(&mut output, b"IDAT", &zlib_stored(&image));
+    write_chunk(&mut output, b"IEND", &[]);
+    output
+}
+
+fn write_chunk(output: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = output.len();
+    output.extend_from_slice(kind);
+    output.extend_from_slice(data);
+    let crc = crc32(&output[start..]);
+    output.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut output = vec![0x78, 0x01];
+    let mut blocks = data.chunks(u16::MAX as usize).peekable();
+
+    if blocks.peek().is_none() {
+        output.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let length = block.len() as u16;
+        output.push(blocks.peek().is_none() as u8);
+        output.extend_from_slice(&length.to_le_bytes());
+        output.extend_from_slice(&(!length).to_le_bytes());
+        output.extend_from_slice(block);
+    }
+    output.extend_from_slice(&adler32(data).to_be_bytes());
+    output
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn test_ascii_and_pbm_of_blank_display() {
+        let display = Display::new(4, 2);
+
+        assert_eq!(ascii(&display), "....\n....\n");
+        assert_eq!(pbm(&display, 2), "P1\n8 4\n0 0 0 0 0 0 0 0\n0 0 0 0 0 0 0 0\n0 0 0 0 0 0 0 0\n0 0 0 0 0 0 0 0\n");
+    }
+
+    #[test]
+    fn test_png_structure() {
+        let image = png(&Display::new(64, 32), 1);
+
+        assert_eq!(&image[..8], &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]);
+        assert_eq!(&image[12..16], b"IHDR");
+        assert_eq!(&image[image.len() - 12..], &[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]);
+    }
+}
@@ -0,0 +1,347 @@
+//! Runs a ROM without a browser and dumps the final display and registers, e.g. for CI:
+//!
+//! ```text
+//! chip8-headless roms/PONG --frames 600 --press 1@10 --release 1@40 --display pong.png --registers -
+//! ```
+
+mod image;
+
+use std::fs;
+use std::io::Write;
+use std::process::ExitCode;
+
+use chip_8_emulator::chip8::{Chip8, Platform, Quirks, RegistersSnapshot, RomDatabase, Syntax};
+use image::ImageFormat;
+
+const TRACE_SIZE: usize = 4096;
+const PROFILE_ENTRIES: usize = 20;
+
+const USAGE: &str = "\
+Usage: chip8-headless <ROM file or embedded name> [options]
+
+Options:
+  --frames <n>            Run n 60 Hz frames (default 60)
+  --instructions <n>      Run n instructions instead, ticking the timers once per frame's worth
+  --press <key>@<at>      Press hex key 0-F before frame (or instruction) number <at>
+  --release <key>@<at>    Release hex key 0-F before frame (or instruction) number <at>
+  --rom-database <path>   programs.json of the CHIP-8 database to detect the ROM's settings with
+  --platform <name>       chip8, super_chip or xo_chip
+  --quirks <profile>      default, cosmac_vip, chip_48, super_chip or xo_chip
+  --speed <n>             Instructions per frame
+  --seed <n>              Seed for the CXKK random numbers
+  --cheats <path>         Apply a cheat code list made for the ROM
+  --display <path|->      Write the final display, format taken from the extension (.txt, .pbm, .png)
+  --format <name>         Force the display format: ascii, pbm or png
+  --scale <n>             Scale PBM and PNG output by n (default 1)
+  --registers <path|->    Write the register snapshot as JSON
+  --profile <path|->      Write where the ROM spent its instructions: hot spots, subroutines and key waits
+  --coverage <path|->     Write a disassembly of the ROM that shows the bytes the run only used as data as data
+  --trace <path|->        Write the last 4096 executed instructions, also when the ROM fails (.json for JSON)
+
+Without --display or --registers the display is printed to stdout as ASCII art.";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Limit {
+    Frames(u64),
+    Instructions(u64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct ScriptedKey {
+    at: u64,
+    key: u8,
+    pressed: bool,
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    rom: String,
+    limit: Limit,
+    keys: Vec<ScriptedKey>,
+    rom_database: Option<String>,
+    platform: Option<Platform>,
+    quirks: Option<Quirks>,
+    speed: Option<u32>,
+    seed: Option<u64>,
+    cheats: Option<String>,
+    display: Option<String>,
+    format: Option<ImageFormat>,
+    scale: usize,
+    registers: Option<String>,
+    profile: Option<String>,
+    coverage: Option<String>,
+    trace: Option<String>,
+}
+
+fn main() -> ExitCode {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    if arguments.is_empty() || arguments.iter().any(|argument| argument == "--help" || argument == "-h") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    match parse_options(&arguments).and_then(|options| run(&options)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("chip8-headless: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_options(arguments: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        rom: String::new(),
+        limit: Limit::Frames(60),
+        keys: Vec::new(),
+        rom_database: None,
+        platform: None,
+        quirks: None,
+        speed: None,
+        seed: None,
+        cheats: None,
+        display: None,
+        format: None,
+        scale: 1,
+        registers: None,
+        profile: None,
+        coverage: None,
+        trace: None,
+    };
+
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        if !argument.starts_with("--") {
+            if !options.rom.is_empty() {
+                return Err(format!("unexpected argument '{}'", argument));
+            }
+            options.rom = argument.clone();
+            continue;
+        }
+
+        let value = arguments.next().ok_or_else(|| format!("{} needs a value", argument))?;
+        match argument.as_str() {
+            "--frames" => options.limit = Limit::Frames(parse_number(value)?),
+            "--instructions" => options.limit = Limit::Instructions(parse_number(value)?),
+            "--press" => options.keys.push(parse_scripted_key(value, true)?),
+            "--release" => options.keys.push(parse_scripted_key(value, false)?),
+            "--rom-database" => options.rom_database = Some(value.clone()),
+            "--platform" => {
+                options.platform = Some(Platform::from_name(value).ok_or_else(|| format!("unknown platform '{}'", value))?)
+            }
+            "--quirks" => {
+                options.quirks = Some(Quirks::from_profile_name(value).ok_or_else(|| format!("unknown quirks profile '{}'", value))?)
+            }
+            "--speed" => options.speed = Some(parse_number(value)? as u32),
+            "--seed" => options.seed = Some(parse_number(value)?),
+            "--cheats" => options.cheats = Some(value.clone()),
+            "--display" => options.display = Some(value.clone()),
+            "--format" => options.format = Some(ImageFormat::from_name(value).ok_or_else(|| format!("unknown format '{}'", value))?),
+            "--scale" => options.scale = parse_number(value)?.max(1) as usize,
+            "--registers" => options.registers = Some(value.clone()),
+            "--profile" => options.profile = Some(value.clone()),
+            "--coverage" => options.coverage = Some(value.clone()),
+            "--trace" => options.trace = Some(value.clone()),
+            _ => return Err(format!("unknown option '{}'", argument)),
+        }
+    }
+
+    if options.rom.is_empty() {
+        return Err("no ROM given".to_string());
+    }
+    options.keys.sort_by_key(|key| key.at);
+    Ok(options)
+}
+
+fn parse_number(text: &str) -> Result<u64, String> {
+    let parsed = match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| format!("'{}' is not a number", text))
+}
+
+fn parse_scripted_key(text: &str, pressed: bool) -> Result<ScriptedKey, String> {
+    let (key, at) = text.split_once('@').ok_or_else(|| format!("expected <key>@<at>, got '{}'", text))?;
+    let key = u8::from_str_radix(key, 16).ok().filter(|&key| key < 16).ok_or_else(|| format!("'{}' is not a key 0-F", key))?;
+    Ok(ScriptedKey { at: parse_number(at)?, key, pressed })
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let mut chip8 = Chip8::new();
+    if let Some(path) = &options.rom_database {
+        let json = fs::read_to_string(path).map_err(|error| format!("cannot read '{}': {}", path, error))?;
+        chip8.set_rom_database(RomDatabase::from_json(&json).map_err(|error| error.to_string())?);
+    }
+
+    // A path on disk wins over an embedded ROM with the same name
+    match fs::read(&options.rom) {
+        Ok(bytes) => chip8.load_rom_bytes(&bytes),
+        Err(_) => chip8.load_rom_in_memory(options.rom.clone()),
+    }
+    .map_err(|error| error.to_string())?;
+
+    // Options override the settings detected for the ROM. Another platform has other memory and
+    // display sizes, so the machine starts over on it
+    if let Some(platform) = options.platform {
+        chip8.set_platform(platform);
+        chip8.reset();
+    }
+    if let Some(quirks) = options.quirks {
+        chip8.set_quirks(quirks);
+    }
+    if let Some(speed) = options.speed {
+        chip8.set_instructions_per_frame(speed);
+    }
+    if let Some(seed) = options.seed {
+        chip8.set_seed(seed);
+    }
+    if let Some(path) = &options.cheats {
+        let text = fs::read_to_string(path).map_err(|error| format!("cannot read '{}': {}", path, error))?;
+        chip8.import_cheats(&text).map_err(|error| error.to_string())?;
+    }
+
+    if options.trace.is_some() {
+        chip8.set_trace_size(TRACE_SIZE);
+    }
+    chip8.set_profiling(options.profile.is_some());
+    let result = execute(&mut chip8, options);
+    // The trace is most useful when the ROM crashed, so it is written before reporting the error
+    if let Some(path) = &options.trace {
+        let trace = if path.ends_with(".json") { chip8.trace().to_json(TRACE_SIZE) } else { chip8.trace().to_text(TRACE_SIZE) };
+        write_output(path, trace.as_bytes())?;
+    }
+    result.map_err(|error| error.to_string())?;
+
+    if options.display.is_none() && options.registers.is_none() {
+        print!("{}", image::ascii(chip8.display()));
+    }
+    if let Some(path) = &options.display {
+        let format = options.format.or_else(|| ImageFormat::from_path(path)).unwrap_or(ImageFormat::Ascii);
+        write_output(path, &image::encode(chip8.display(), format, options.scale))?;
+    }
+    if let Some(path) = &options.registers {
+        write_output(path, registers_json(&chip8.get_register_snapshot()).as_bytes())?;
+    }
+    if let Some(path) = &options.profile {
+        write_output(path, chip8.profile_report(PROFILE_ENTRIES).as_bytes())?;
+    }
+    if let Some(path) = &options.coverage {
+        write_output(path, coverage_listing(&chip8).as_bytes())?;
+    }
+    Ok(())
+}
+
+fn execute(chip8: &mut Chip8, options: &Options) -> Result<(), chip_8_emulator::chip8::Chip8Error> {
+    let (count, per_instruction) = match options.limit {
+        Limit::Frames(frames) => (frames, false),
+        Limit::Instructions(instructions) => (instructions, true),
+    };
+    let mut keys = options.keys.iter().peekable();
+
+    for step in 0..count {
+        while let Some(key) = keys.next_if(|key| key.at <= step) {
+            chip8.set_key(key.key, key.pressed)?;
+        }
+
+        if per_instruction {
+            if chip8.is_halted() {
+                break;
+            }
+            chip8.execute_step()?;
+            if (step + 1) % chip8.get_instructions_per_frame() as u64 == 0 {
+                chip8.finish_frame();
+            }
+        } else {
+            chip8.run_frame()?;
+        }
+    }
+    Ok(())
+}
+
+// The ROM as loaded at 0x200, disassembled with the coverage of the run.
+fn coverage_listing(chip8: &Chip8) -> String {
+    let lines = chip8.disassemble_with_coverage(0x200, 0x200 + chip8.get_rom_size(), Syntax::Cowgod);
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+fn registers_json(registers: &RegistersSnapshot) -> String {
+    let values = [
+        ("V0", registers.V0 as u16), ("V1", registers.V1 as u16), ("V2", registers.V2 as u16), ("V3", registers.V3 as u16),
+        ("V4", registers.V4 as u16), ("V5", registers.V5 as u16), ("V6", registers.V6 as u16), ("V7", registers.V7 as u16),
+        ("V8", registers.V8 as u16), ("V9", registers.V9 as u16), ("VA", registers.VA as u16), ("VB", registers.VB as u16),
+        ("VC", registers.VC as u16), ("VD", registers.VD as u16), ("VE", registers.VE as u16), ("VF", registers.VF as u16),
+        ("I", registers.I),
+        ("delay_timer", registers.delay_timer as u16),
+        ("sound_timer", registers.sound_timer as u16),
+        ("programm_counter", registers.programm_counter),
+        ("stack_pointer", registers.stack_pointer as u16),
+    ];
+
+    let fields: Vec<String> = values.iter().map(|(name, value)| format!("  \"{}\": {}", name, value)).collect();
+    format!("{{\n{}\n}}\n", fields.join(",\n"))
+}
+
+fn write_output(path: &str, bytes: &[u8]) -> Result<(), String> {
+    if path == "-" {
+        return std::io::stdout().write_all(bytes).map_err(|error| error.to_string());
+    }
+    fs::write(path, bytes).map_err(|error| format!("cannot write '{}': {}", path, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chip_8_emulator::chip8::Cheat;
+
+    fn arguments(text: &str) -> Vec<String> {
+        text.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = parse_options(&arguments("pong --instructions 0x100 --release A@20 --press a@5 --display out.png")).unwrap();
+
+        assert_eq!(options.rom, "pong");
+        assert_eq!(options.limit, Limit::Instructions(256));
+        assert_eq!(options.keys, [
+            ScriptedKey { at: 5, key: 0xA, pressed: true },
+            ScriptedKey { at: 20, key: 0xA, pressed: false },
+        ]);
+        assert_eq!(options.display.as_deref(), Some("out.png"));
+    }
+
+    #[test]
+    fn test_parse_options_rejects_bad_input() {
+        assert!(parse_options(&arguments("--frames 10")).is_err());
+        assert!(parse_options(&arguments("pong --press G@1")).is_err());
+        assert!(parse_options(&arguments("pong --frames")).is_err());
+        assert!(parse_options(&arguments("pong --bogus 1")).is_err());
+    }
+
+    #[test]
+    fn test_run_embedded_rom_for_frames() {
+        let options = parse_options(&arguments("pong --frames 30 --seed 1")).unwrap();
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_in_memory(options.rom.clone()).unwrap();
+
+        execute(&mut chip8, &options).unwrap();
+
+        assert!(chip8.display().pixels().iter().any(|&pixel| pixel != 0), "PONG draws its court");
+        assert!(registers_json(&chip8.get_register_snapshot()).contains("\"programm_counter\": "));
+    }
+
+    #[test]
+    fn test_run_for_instructions_finishes_frames() {
+        // LD V0, 0 / LD I, 0x300 / LD [I], V0 / JP 0x200 keeps overwriting the cheat
+        let options = parse_options(&arguments("loop --instructions 40")).unwrap();
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&[0x60, 0x00, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x00]).unwrap();
+        chip8.set_instructions_per_frame(10);
+        chip8.add_cheat(Cheat { address: 0x300, value: 0x42, description: "Frozen".to_string() }).unwrap();
+
+        execute(&mut chip8, &options).unwrap();
+
+        assert_eq!(chip8.get_memory(0x300, 1), [0x42], "the cheat is reapplied at the end of every frame");
+    }
+}
@@ -0,0 +1,258 @@
+//! Plays a ROM in the terminal, e.g. over SSH:
+//!
+//! ```text
+//! chip8-tui roms/BRIX --platform chip8 --speed 15
+//! ```
+//!
+//! Most terminals only report key presses, so a pad key stays down for a short while after each
+//! press and keyboard auto-repeat keeps it held. Terminals with the kitty keyboard protocol report
+//! releases as well and are used as such.
+
+mod render;
+
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use chip_8_emulator::chip8::{Chip8, Platform};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::Print;
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use render::{Renderer, Status};
+
+const USAGE: &str = "\
+Usage: chip8-tui <ROM file or embedded name> [--platform chip8|super_chip|xo_chip] [--speed <instructions per frame>]
+
+Keys: the pad maps to 1234 / QWER / ASDF / ZXCV (Y works as Z).
+      Esc quits, F1 pauses, F2 steps one instruction while paused, F3 resets.";
+
+const FRAME_DURATION: Duration = Duration::from_micros(16_667);
+// Long enough to bridge the keyboard auto-repeat delay when the terminal doesn't report releases
+const KEY_HOLD_DURATION: Duration = Duration::from_millis(300);
+
+fn main() -> ExitCode {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    if arguments.is_empty() || arguments.iter().any(|argument| argument == "--help" || argument == "-h") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let result = load(&arguments).and_then(|mut chip8| play(&mut chip8).map_err(|error| error.to_string()));
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("chip8-tui: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn load(arguments: &[String]) -> Result<Chip8, String> {
+    let mut chip8 = Chip8::new();
+    let (mut rom, mut platform, mut speed) = (None, None, None);
+    let mut arguments = arguments.iter();
+
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--platform" => {
+                let name = arguments.next().ok_or("--platform needs a value")?;
+                platform = Some(Platform::from_name(name).ok_or_else(|| format!("unknown platform '{}'", name))?);
+            }
+            "--speed" => {
+                speed = Some(arguments.next().and_then(|speed| speed.parse().ok()).ok_or("--speed needs a number")?);
+            }
+            _ if rom.is_none() && !argument.starts_with("--") => rom = Some(argument.clone()),
+            _ => return Err(format!("unexpected argument '{}'", argument)),
+        }
+    }
+
+    let rom = rom.ok_or("no ROM given")?;
+    match fs::read(&rom) {
+        Ok(bytes) => chip8.load_rom_bytes(&bytes),
+        Err(_) => chip8.load_rom_in_memory(rom),
+    }
+    .map_err(|error| error.to_string())?;
+
+    // Options override the settings detected for the ROM. Another platform has other memory and
+    // display sizes, so the machine starts over on it
+    if let Some(platform) = platform {
+        chip8.set_platform(platform);
+        chip8.reset();
+    }
+    if let Some(speed) = speed {
+        chip8.set_instructions_per_frame(speed);
+    }
+    Ok(chip8)
+}
+
+pub fn pad_key(code: KeyCode) -> Option<u8> {
+    let KeyCode::Char(character) = code else {
+        return None;
+    };
+
+    match character.to_ascii_lowercase() {
+        '1' => Some(0x1), '2' => Some(0x2), '3' => Some(0x3), '4' => Some(0xC),
+        'q' => Some(0x4), 'w' => Some(0x5), 'e' => Some(0x6), 'r' => Some(0xD),
+        'a' => Some(0x7), 's' => Some(0x8), 'd' => Some(0x9), 'f' => Some(0xE),
+        'z' | 'y' => Some(0xA), 'x' => Some(0x0), 'c' => Some(0xB), 'v' => Some(0xF),
+        _ => None,
+    }
+}
+
+// Raw mode and the alternate screen are undone even when the game loop fails.
+struct TerminalGuard {
+    enhanced_keyboard: bool,
+}
+
+impl TerminalGuard {
+    fn enter() -> io::Result<TerminalGuard> {
+        terminal::enable_raw_mode()?;
+        let enhanced_keyboard = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+        if enhanced_keyboard {
+            execute!(io::stdout(), PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES))?;
+        }
+        Ok(TerminalGuard { enhanced_keyboard })
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        if self.enhanced_keyboard {
+            let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+struct Session {
+    paused: bool,
+    quit: bool,
+    beeping: bool,
+    // When each pad key is released if the terminal never reports it
+    release_at: [Option<Instant>; 16],
+}
+
+fn play(chip8: &mut Chip8) -> io::Result<()> {
+    let guard = TerminalGuard::enter()?;
+    let mut out = BufWriter::new(io::stdout());
+    let mut renderer = Renderer::new();
+    let mut session = Session { paused: false, quit: false, beeping: false, release_at: [None; 16] };
+    let mut last_frame = Instant::now();
+
+    while !session.quit {
+        let timeout = FRAME_DURATION.saturating_sub(last_frame.elapsed());
+        if event::poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                handle_key(chip8, &mut session, &mut renderer, key, guard.enhanced_keyboard);
+            }
+            continue;
+        }
+
+        let now = Instant::now();
+        let elapsed = now - last_frame;
+        last_frame = now;
+        release_expired_keys(chip8, &mut session, now);
+
+        let result = if session.paused { Ok(()) } else { chip8.run_for(elapsed.as_secs_f64() * 1000.0).map(|_| ()) };
+        if let Err(error) = result {
+            session.paused = true;
+            queue!(out, MoveTo(0, 34), Print(format!("Emulation stopped: {}", error)))?;
+        }
+
+        let sound_active = chip8.is_sound_active() && !session.paused;
+        if sound_active && !session.beeping {
+            out.write_all(b"\x07")?;
+        }
+        session.beeping = sound_active;
+        renderer.draw(&mut out, chip8, &Status { paused: session.paused, beeping: session.beeping })?;
+    }
+    Ok(())
+}
+
+fn handle_key(chip8: &mut Chip8, session: &mut Session, renderer: &mut Renderer, key: KeyEvent, reports_releases: bool) {
+    let pressed = key.kind != KeyEventKind::Release;
+    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        session.quit = true;
+        return;
+    }
+
+    if let Some(pad) = pad_key(key.code) {
+        let _ = chip8.set_key(pad, pressed);
+        session.release_at[pad as usize] = (pressed && !reports_releases).then(|| Instant::now() + KEY_HOLD_DURATION);
+        return;
+    }
+    if key.kind != KeyEventKind::Press {
+        return;
+    }
+
+    match key.code {
+        KeyCode::Esc => session.quit = true,
+        KeyCode::F(1) => session.paused = !session.paused,
+        KeyCode::F(2) if session.paused => {
+            let _ = chip8.execute_step();
+        }
+        KeyCode::F(3) => {
+            chip8.reset();
+            renderer.invalidate();
+        }
+        _ => {}
+    }
+}
+
+fn release_expired_keys(chip8: &mut Chip8, session: &mut Session, now: Instant) {
+    for (key, release_at) in session.release_at.iter_mut().enumerate() {
+        if release_at.is_some_and(|deadline| deadline <= now) {
+            let _ = chip8.set_key(key as u8, false);
+            *release_at = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pad_key_matches_web_layout() {
+        assert_eq!(pad_key(KeyCode::Char('4')), Some(0xC));
+        assert_eq!(pad_key(KeyCode::Char('W')), Some(0x5));
+        assert_eq!(pad_key(KeyCode::Char('y')), Some(0xA));
+        assert_eq!(pad_key(KeyCode::Char('v')), Some(0xF));
+        assert_eq!(pad_key(KeyCode::Char('p')), None);
+        assert_eq!(pad_key(KeyCode::Esc), None);
+    }
+
+    #[test]
+    fn test_keys_without_release_events_are_released_after_holding() {
+        let mut chip8 = Chip8::new();
+        let mut renderer = Renderer::new();
+        let mut session = Session { paused: false, quit: false, beeping: false, release_at: [None; 16] };
+        handle_key(&mut chip8, &mut session, &mut renderer, KeyEvent::new(KeyCode::Char('w'), KeyModifiers::NONE), false);
+
+        release_expired_keys(&mut chip8, &mut session, Instant::now());
+        assert!(session.release_at[0x5].is_some());
+
+        release_expired_keys(&mut chip8, &mut session, Instant::now() + KEY_HOLD_DURATION * 2);
+        assert!(session.release_at[0x5].is_none());
+    }
+
+    #[test]
+    fn test_ctrl_c_quits_instead_of_pressing_c() {
+        let mut chip8 = Chip8::new();
+        let mut renderer = Renderer::new();
+        let mut session = Session { paused: false, quit: false, beeping: false, release_at: [None; 16] };
+
+        handle_key(&mut chip8, &mut session, &mut renderer, KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL), false);
+
+        assert!(session.quit);
+        assert!(session.release_at[0xB].is_none());
+    }
+}
@@ -0,0 +1,134 @@
+use std::io::{self, Write};
+
+use chip_8_emulator::chip8::{Chip8, Display, Syntax};
+use crossterm::cursor::MoveTo;
+use crossterm::queue;
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{Clear, ClearType};
+
+// ANSI colours in the order of the web palette: off, plane 1, plane 2, both planes
+const PALETTE: [Color; 4] = [Color::Black, Color::White, Color::Grey, Color::DarkGrey];
+const PANEL_GAP: u16 = 2;
+const NEXT_INSTRUCTIONS: usize = 8;
+
+/// Draws the display with one `▀` per two pixel rows and the debug panel to its right.
+pub struct Renderer {
+    // Display as drawn last time, so unchanged frames don't resend the whole screen over SSH
+    last_pixels: Vec<u8>,
+}
+
+pub struct Status {
+    pub paused: bool,
+    pub beeping: bool,
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Renderer { last_pixels: Vec::new() }
+    }
+
+    pub fn invalidate(&mut self) {
+        self.last_pixels.clear();
+    }
+
+    pub fn draw(&mut self, out: &mut impl Write, chip8: &Chip8, status: &Status) -> io::Result<()> {
+        let display = chip8.display();
+        if display.pixels() != self.last_pixels.as_slice() {
+            if display.pixels().len() != self.last_pixels.len() {
+                queue!(out, ResetColor, Clear(ClearType::All))?;
+            }
+            draw_display(out, display)?;
+            self.last_pixels = display.pixels().to_vec();
+        }
+
+        let panel_column = display.width() as u16 + PANEL_GAP;
+        for (row, line) in panel_lines(chip8, status).iter().enumerate() {
+            queue!(out, ResetColor, MoveTo(panel_column, row as u16), Print(format!("{:<28}", line)))?;
+        }
+        out.flush()
+    }
+}
+
+fn draw_display(out: &mut impl Write, display: &Display) -> io::Result<()> {
+    for row in 0..display.height().div_ceil(2) {
+        queue!(out, MoveTo(0, row as u16))?;
+        let mut current = None;
+
+        for x in 0..display.width() {
+            let colors = half_block_colors(display, x, row * 2);
+            if current != Some(colors) {
+                queue!(out, SetForegroundColor(colors.0), SetBackgroundColor(colors.1))?;
+                current = Some(colors);
+            }
+            queue!(out, Print('▀'))?;
+        }
+    }
+    Ok(())
+}
+
+// The upper pixel is the foreground of `▀`, the lower one its background.
+pub fn half_block_colors(display: &Display, x: usize, y: usize) -> (Color, Color) {
+    let top = display[y][x] & 0x3;
+    let bottom = if y + 1 < display.height() { display[y + 1][x] & 0x3 } else { 0 };
+    (PALETTE[top as usize], PALETTE[bottom as usize])
+}
+
+pub fn panel_lines(chip8: &Chip8, status: &Status) -> Vec<String> {
+    let registers = chip8.get_register_snapshot();
+    let v = [
+        registers.V0, registers.V1, registers.V2, registers.V3, registers.V4, registers.V5, registers.V6, registers.V7,
+        registers.V8, registers.V9, registers.VA, registers.VB, registers.VC, registers.VD, registers.VE, registers.VF,
+    ];
+
+    let state = match (status.paused, chip8.is_halted()) {
+        (_, true) => "HALTED",
+        (true, _) => "PAUSED",
+        _ => "RUNNING",
+    };
+    let sound = if status.beeping { "♪ BEEP" } else { "" };
+
+    let mut lines = vec![format!("{:<8} {}", state, sound), String::new()];
+    for pair in 0..8 {
+        let (a, b) = (pair * 2, pair * 2 + 1);
+        lines.push(format!("V{:X}: 0x{:02X}   V{:X}: 0x{:02X}", a, v[a], b, v[b]));
+    }
+    lines.push(format!("I:  0x{:04X} PC: 0x{:04X}", registers.I, registers.programm_counter));
+    lines.push(format!("DT: {:<6} ST: {:<3} SP: {}", registers.delay_timer, registers.sound_timer, registers.stack_pointer));
+    lines.push(String::new());
+
+    let pc = registers.programm_counter as usize;
+    for line in chip8.disassemble(pc, pc + NEXT_INSTRUCTIONS * 2, Syntax::Cowgod).iter().take(NEXT_INSTRUCTIONS) {
+        let marker = if line.is_current { '>' } else { ' ' };
+        lines.push(format!("{}{:03X} {}", marker, line.address, line.text));
+    }
+    lines.resize(lines.len().max(20), String::new());
+
+    lines.push("Esc quit  F1 pause  F2 step  F3 reset".to_string());
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_half_block_colors() {
+        let mut display = Display::new(2, 3);
+        display[0][0] = 1;
+        display[1][1] = 2;
+        display[2][0] = 3;
+
+        assert_eq!(half_block_colors(&display, 0, 0), (Color::White, Color::Black));
+        assert_eq!(half_block_colors(&display, 1, 0), (Color::Black, Color::Grey));
+        assert_eq!(half_block_colors(&display, 0, 2), (Color::DarkGrey, Color::Black), "the odd last row has no lower half");
+    }
+
+    #[test]
+    fn test_panel_shows_registers_and_next_instructions() {
+        let chip8 = Chip8::new();
+        let lines = panel_lines(&chip8, &Status { paused: true, beeping: true });
+
+        assert!(lines[0].starts_with("PAUSED") && lines[0].contains("BEEP"));
+        assert!(lines.iter().any(|line| line.starts_with(">200 ")));
+    }
+}
@@ -0,0 +1,314 @@
+//! The ROMs bundled with the emulator and what is known about them. Frontends build their ROM
+//! menu and help text from this list, and `load_rom_in_memory` applies the recommended settings.
+
+use std::sync::OnceLock;
+
+use super::sha1::sha1;
+use super::{Platform, Quirks, DEFAULT_INSTRUCTIONS_PER_FRAME};
+
+#[derive(Clone, Copy, Debug)]
+pub struct RomInfo {
+    /// Name passed to `load_rom_in_memory`.
+    pub id: &'static str,
+    pub title: &'static str,
+    pub author: Option<&'static str>,
+    pub year: Option<u16>,
+    pub description: &'static str,
+    /// Keypad keys (0x0-0xF) and what they do in the game.
+    pub keys: &'static [(u8, &'static str)],
+    /// Recommended instructions per frame.
+    pub speed: u32,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    /// Whether the ROM has been played through on this emulator without visible problems.
+    pub known_working: bool,
+    pub data: &'static [u8],
+}
+
+const CLASSIC: RomInfo = RomInfo {
+    id: "",
+    title: "",
+    author: None,
+    year: None,
+    description: "",
+    keys: &[],
+    speed: DEFAULT_INSTRUCTIONS_PER_FRAME,
+    platform: Platform::Chip8,
+    quirks: Quirks::DEFAULT,
+    known_working: false,
+    data: &[],
+};
+
+pub const CATALOG: &[RomInfo] = &[
+    RomInfo {
+        id: "octajam_title",
+        title: "Octojam 1 Title",
+        author: Some("John Earnest"),
+        year: Some(2014),
+        description: "Animated title card of the first Octojam.",
+        known_working: true,
+        data: include_bytes!("./roms/octojam1title.ch8"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "red_october",
+        title: "Red October",
+        description: "XO-CHIP submarine game that fills almost all of the 64 KiB address space.",
+        speed: 1000,
+        platform: Platform::XoChip,
+        quirks: Quirks::XO_CHIP,
+        data: include_bytes!("./roms/redOctober.ch8"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "puzzle_15",
+        title: "15 Puzzle",
+        author: Some("Roger Ivie"),
+        description: "Slide the tiles back into order. Each key moves the tile with that number.",
+        known_working: true,
+        data: include_bytes!("./roms/15PUZZLE"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "blinky",
+        title: "Blinky",
+        author: Some("Hans Christian Egeberg"),
+        year: Some(1991),
+        description: "Pac-Man clone.",
+        keys: &[(0x3, "up"), (0x6, "down"), (0x7, "left"), (0x8, "right")],
+        data: include_bytes!("./roms/BLINKY"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "blitz",
+        title: "Blitz",
+        author: Some("David Winter"),
+        description: "Bomb the city flat before your plane runs into a building.",
+        keys: &[(0x5, "drop bomb")],
+        data: include_bytes!("./roms/BLITZ"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "brix",
+        title: "Brix",
+        author: Some("Andreas Gustafsson"),
+        year: Some(1990),
+        description: "Breakout clone.",
+        keys: &[(0x4, "left"), (0x6, "right")],
+        data: include_bytes!("./roms/BRIX"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "connect",
+        title: "Connect 4",
+        author: Some("David Winter"),
+        description: "Two players take turns dropping discs, four in a row wins.",
+        keys: &[(0x4, "left"), (0x6, "right"), (0x5, "drop disc")],
+        data: include_bytes!("./roms/CONNECT4"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "guess",
+        title: "Guess",
+        author: Some("David Winter"),
+        description: "Think of a number below 64 and answer whether it is shown, the computer guesses it.",
+        keys: &[(0x5, "yes")],
+        known_working: true,
+        data: include_bytes!("./roms/GUESS"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "hidden",
+        title: "Hidden",
+        author: Some("David Winter"),
+        year: Some(1996),
+        description: "Memory game: find the pairs of matching cards.",
+        keys: &[(0x2, "up"), (0x8, "down"), (0x4, "left"), (0x6, "right"), (0x5, "turn card")],
+        data: include_bytes!("./roms/HIDDEN"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "invaders",
+        title: "Space Invaders",
+        author: Some("David Winter"),
+        description: "Shoot the invaders before they land.",
+        keys: &[(0x4, "left"), (0x6, "right"), (0x5, "fire and start")],
+        known_working: true,
+        data: include_bytes!("./roms/INVADERS"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "kaleid",
+        title: "Kaleidoscope",
+        author: Some("Joseph Weisbecker"),
+        year: Some(1978),
+        description: "Draw a pattern that is mirrored into all four corners, then watch it repeat.",
+        keys: &[(0x2, "up"), (0x8, "down"), (0x4, "left"), (0x6, "right"), (0x0, "finish pattern")],
+        data: include_bytes!("./roms/KALEID"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "maze",
+        title: "Maze",
+        author: Some("David Winter"),
+        description: "Draws a random maze, no input.",
+        data: include_bytes!("./roms/MAZE"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "merlin",
+        title: "Merlin",
+        author: Some("David Winter"),
+        description: "Repeat the sequence in which the four squares light up.",
+        keys: &[(0x4, "top left"), (0x5, "top right"), (0x7, "bottom left"), (0x8, "bottom right")],
+        data: include_bytes!("./roms/MERLIN"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "missile",
+        title: "Missile Command",
+        author: Some("David Winter"),
+        description: "Hit the moving targets with your limited supply of missiles.",
+        keys: &[(0x8, "fire")],
+        data: include_bytes!("./roms/MISSILE"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "pong",
+        title: "Pong",
+        author: Some("Paul Vervalin"),
+        year: Some(1990),
+        description: "Two player Pong.",
+        keys: &[(0x1, "left paddle up"), (0x4, "left paddle down"), (0xC, "right paddle up"), (0xD, "right paddle down")],
+        known_working: true,
+        data: include_bytes!("./roms/PONG"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "pong2",
+        title: "Pong 2",
+        author: Some("David Winter"),
+        year: Some(1997),
+        description: "Two player Pong with a few fixes.",
+        keys: &[(0x1, "left paddle up"), (0x4, "left paddle down"), (0xC, "right paddle up"), (0xD, "right paddle down")],
+        known_working: true,
+        data: include_bytes!("./roms/PONG2"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "puzzle",
+        title: "Puzzle",
+        description: "Sliding tile puzzle that shuffles itself.",
+        data: include_bytes!("./roms/PUZZLE"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "syzygy",
+        title: "Syzygy",
+        author: Some("Roy Trevino"),
+        year: Some(1990),
+        description: "Snake game: eat the targets without running into your own tail.",
+        data: include_bytes!("./roms/SYZYGY"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "tank",
+        title: "Tank",
+        description: "Drive the tank and shoot the moving target.",
+        keys: &[(0x2, "up"), (0x8, "down"), (0x4, "left"), (0x6, "right"), (0x5, "fire")],
+        known_working: true,
+        data: include_bytes!("./roms/TANK"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "tetris",
+        title: "Tetris",
+        author: Some("Fran Dachille"),
+        year: Some(1991),
+        description: "Tetris.",
+        keys: &[(0x4, "rotate"), (0x5, "left"), (0x6, "right"), (0x1, "drop")],
+        data: include_bytes!("./roms/TETRIS"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "tictac",
+        title: "Tic-Tac-Toe",
+        author: Some("David Winter"),
+        description: "Two player Tic-Tac-Toe, keys 1-9 pick the square.",
+        data: include_bytes!("./roms/TICTAC"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "ufo",
+        title: "UFO",
+        author: Some("Lutz V"),
+        year: Some(1992),
+        description: "Shoot down the UFOs with a limited number of missiles.",
+        keys: &[(0x4, "fire left"), (0x5, "fire up"), (0x6, "fire right")],
+        data: include_bytes!("./roms/UFO"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "vbrix",
+        title: "Vertical Brix",
+        author: Some("Paul Robson"),
+        year: Some(1996),
+        description: "Breakout turned on its side.",
+        keys: &[(0x1, "up"), (0x4, "down"), (0x7, "start")],
+        data: include_bytes!("./roms/VBRIX"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "vers",
+        title: "Vers",
+        author: Some("JMN"),
+        year: Some(1991),
+        description: "Two player light cycle duel.",
+        data: include_bytes!("./roms/VERS"),
+        ..CLASSIC
+    },
+    RomInfo {
+        id: "wipeoff",
+        title: "Wipe Off",
+        author: Some("Joseph Weisbecker"),
+        description: "Clear all the dots with the ball, Breakout style.",
+        keys: &[(0x4, "left"), (0x6, "right")],
+        data: include_bytes!("./roms/WIPEOFF"),
+        ..CLASSIC
+    },
+];
+
+pub fn find_rom(id: &str) -> Option<&'static RomInfo> {
+    CATALOG.iter().find(|rom| rom.id == id)
+}
+
+/// The bundled ROM with the SHA-1 `hash`. The catalog is hashed once, on the first lookup.
+pub fn find_rom_by_sha1(hash: &[u8; 20]) -> Option<&'static RomInfo> {
+    static HASHES: OnceLock<Vec<[u8; 20]>> = OnceLock::new();
+    let hashes = HASHES.get_or_init(|| CATALOG.iter().map(|rom| sha1(rom.data)).collect());
+    hashes.iter().position(|known| known == hash).map(|index| &CATALOG[index])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog_entries_are_complete_and_unique() {
+        for (index, rom) in CATALOG.iter().enumerate() {
+            assert!(!rom.id.is_empty() && !rom.title.is_empty() && !rom.description.is_empty(), "{}", rom.id);
+            assert!(!rom.data.is_empty(), "{} has no data", rom.id);
+            assert!(rom.data.len() <= rom.platform.memory_size() - 0x200, "{} does not fit its platform", rom.id);
+            assert!(rom.keys.iter().all(|&(key, _)| key < 16), "{} binds a key outside the pad", rom.id);
+            assert!(CATALOG[..index].iter().all(|other| other.id != rom.id), "{} is listed twice", rom.id);
+        }
+    }
+
+    #[test]
+    fn test_find_rom_by_sha1() {
+        let pong = find_rom("pong").unwrap();
+
+        assert_eq!(find_rom_by_sha1(&sha1(pong.data)).map(|rom| rom.id), Some("pong"));
+        assert!(find_rom_by_sha1(&sha1(b"unknown")).is_none());
+    }
+}
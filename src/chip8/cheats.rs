@@ -0,0 +1,266 @@
+//! RAM search and frozen memory cheats.
+//!
+//! A search starts with every address as a candidate and a snapshot of memory. Each filter keeps
+//! the candidates whose current value passes the comparison with the snapshot and then takes a
+//! new snapshot, so "changed" and "unchanged" always refer to the previous search.
+//!
+//! Cheats write their value after every frame. Many games keep their state in the V registers
+//! rather than RAM, those are cheated by freezing an operand of the program itself instead. Cheats
+//! are saved as a plain text code list that only loads for the ROM it was made for:
+//!
+//! ```text
+//! # Space Invaders
+//! rom f100197f0f2f05b4f3c8c31ab9c2c3930d3e9571
+//! 0318:00 Invaders never descend
+//! 0320:00
+//! ```
+
+use std::fmt::Write;
+
+use super::sha1::{parse_sha1, sha1, to_hex};
+use super::{Chip8, Chip8Error};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchFilter {
+    EqualTo(u8),
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+    IncreasedBy(u8),
+    DecreasedBy(u8),
+}
+
+impl SearchFilter {
+    /// `value` is only used by equal, increased_by and decreased_by.
+    pub fn from_name(name: &str, value: u8) -> Option<SearchFilter> {
+        match name {
+            "equal" => Some(SearchFilter::EqualTo(value)),
+            "changed" => Some(SearchFilter::Changed),
+            "unchanged" => Some(SearchFilter::Unchanged),
+            "increased" => Some(SearchFilter::Increased),
+            "decreased" => Some(SearchFilter::Decreased),
+            "increased_by" => Some(SearchFilter::IncreasedBy(value)),
+            "decreased_by" => Some(SearchFilter::DecreasedBy(value)),
+            _ => None,
+        }
+    }
+
+    fn matches(self, previous: u8, current: u8) -> bool {
+        match self {
+            SearchFilter::EqualTo(value) => current == value,
+            SearchFilter::Changed => current != previous,
+            SearchFilter::Unchanged => current == previous,
+            SearchFilter::Increased => current > previous,
+            SearchFilter::Decreased => current < previous,
+            SearchFilter::IncreasedBy(amount) => current == previous.wrapping_add(amount),
+            SearchFilter::DecreasedBy(amount) => current == previous.wrapping_sub(amount),
+        }
+    }
+}
+
+pub struct RamSearch {
+    snapshot: Vec<u8>,
+    candidates: Vec<u16>,
+}
+
+impl RamSearch {
+    pub fn new(memory: &[u8]) -> Self {
+        RamSearch { snapshot: memory.to_vec(), candidates: (0..memory.len()).map(|address| address as u16).collect() }
+    }
+
+    pub fn candidates(&self) -> &[u16] {
+        &self.candidates
+    }
+
+    /// Narrows the candidates down and returns how many are left.
+    pub fn filter(&mut self, memory: &[u8], filter: SearchFilter) -> usize {
+        let snapshot = &self.snapshot;
+        self.candidates.retain(|&address| {
+            let address = address as usize;
+            // Addresses beyond memory after switching to a smaller platform can't match anything
+            match (snapshot.get(address), memory.get(address)) {
+                (Some(&previous), Some(&current)) => filter.matches(previous, current),
+                _ => false,
+            }
+        });
+        self.snapshot = memory.to_vec();
+        self.candidates.len()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cheat {
+    pub address: u16,
+    pub value: u8,
+    pub description: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheatList {
+    pub rom_sha1: [u8; 20],
+    pub cheats: Vec<Cheat>,
+}
+
+impl CheatList {
+    /// `title` goes into a comment on the first line.
+    pub fn to_text(&self, title: Option<&str>) -> String {
+        let mut text = String::new();
+        if let Some(title) = title {
+            writeln!(text, "# {}", title).expect("writing to a String cannot fail");
+        }
+        writeln!(text, "rom {}", to_hex(&self.rom_sha1)).expect("writing to a String cannot fail");
+        for cheat in &self.cheats {
+            let line = format!("{:04X}:{:02X} {}", cheat.address, cheat.value, cheat.description);
+            writeln!(text, "{}", line.trim_end()).expect("writing to a String cannot fail");
+        }
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<CheatList, Chip8Error> {
+        let mut rom_sha1 = None;
+        let mut cheats = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |reason: &str| Chip8Error::InvalidCheats(format!("line {}: {}", index + 1, reason));
+
+            if let Some(hash) = line.strip_prefix("rom ") {
+                rom_sha1 = Some(parse_sha1(hash.trim()).ok_or_else(|| invalid("expected the SHA-1 of the ROM"))?);
+                continue;
+            }
+            let (code, description) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let (address, value) = code.split_once(':').ok_or_else(|| invalid("expected <address>:<value>"))?;
+            cheats.push(Cheat {
+                address: u16::from_str_radix(address, 16).map_err(|_| invalid("invalid address"))?,
+                value: u8::from_str_radix(value, 16).map_err(|_| invalid("invalid value"))?,
+                description: description.trim().to_string(),
+            });
+        }
+
+        let rom_sha1 = rom_sha1.ok_or_else(|| Chip8Error::InvalidCheats("missing rom line".to_string()))?;
+        Ok(CheatList { rom_sha1, cheats })
+    }
+}
+
+impl Chip8 {
+    /// Starts a new RAM search with every address as a candidate.
+    pub fn start_ram_search(&mut self) {
+        self.ram_search = Some(RamSearch::new(&self.memory));
+    }
+
+    /// Narrows the running RAM search down, starting one first if there is none. Returns how many
+    /// candidates are left.
+    pub fn filter_ram_search(&mut self, filter: SearchFilter) -> usize {
+        let search = self.ram_search.get_or_insert_with(|| RamSearch::new(&self.memory));
+        search.filter(&self.memory, filter)
+    }
+
+    pub fn ram_search_candidates(&self) -> &[u16] {
+        self.ram_search.as_ref().map(RamSearch::candidates).unwrap_or_default()
+    }
+
+    /// Freezes `address` at the cheat's value, replacing a cheat on the same address.
+    pub fn add_cheat(&mut self, cheat: Cheat) -> Result<(), Chip8Error> {
+        self.check_cheats_unlocked()?;
+        self.cheats.retain(|existing| existing.address != cheat.address);
+        self.cheats.push(cheat);
+        self.apply_cheats();
+        Ok(())
+    }
+
+    pub fn remove_cheat(&mut self, address: u16) -> Result<bool, Chip8Error> {
+        self.check_cheats_unlocked()?;
+        let count = self.cheats.len();
+        self.cheats.retain(|cheat| cheat.address != address);
+        Ok(self.cheats.len() != count)
+    }
+
+    pub fn clear_cheats(&mut self) -> Result<(), Chip8Error> {
+        self.check_cheats_unlocked()?;
+        self.cheats.clear();
+        Ok(())
+    }
+
+    pub fn cheats(&self) -> &[Cheat] {
+        &self.cheats
+    }
+
+    /// The active cheats as a code list for the loaded ROM.
+    pub fn export_cheats(&self) -> String {
+        let list = CheatList { rom_sha1: sha1(&self.rom), cheats: self.cheats.clone() };
+        list.to_text(self.detected_rom.as_ref().map(|rom| rom.title.as_str()))
+    }
+
+    /// Replaces the active cheats with a code list made for the loaded ROM.
+    pub fn import_cheats(&mut self, text: &str) -> Result<(), Chip8Error> {
+        self.check_cheats_unlocked()?;
+        let list = CheatList::from_text(text)?;
+        if list.rom_sha1 != sha1(&self.rom) {
+            return Err(Chip8Error::CheatRomMismatch);
+        }
+        self.cheats = list.cheats;
+        self.apply_cheats();
+        Ok(())
+    }
+
+    // Movies only store the cheats active when they start.
+    fn check_cheats_unlocked(&self) -> Result<(), Chip8Error> {
+        if self.is_recording() || self.is_playing_movie() {
+            return Err(Chip8Error::CheatsLockedByMovie);
+        }
+        Ok(())
+    }
+
+    // Cheats bypass the watchpoints like any other debugger edit.
+    pub(super) fn apply_cheats(&mut self) {
+        for cheat in &self.cheats {
+            if let Some(byte) = self.memory.get_mut(cheat.address as usize) {
+                *byte = cheat.value;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ram_search_narrows_candidates() {
+        let mut search = RamSearch::new(&[3, 3, 3, 7]);
+
+        assert_eq!(search.filter(&[2, 3, 3, 7], SearchFilter::DecreasedBy(1)), 1);
+        assert_eq!(search.candidates(), [0]);
+
+        let mut search = RamSearch::new(&[3, 3, 3, 7]);
+        search.filter(&[3, 4, 3, 7], SearchFilter::Unchanged);
+        assert_eq!(search.candidates(), [0, 2, 3]);
+        search.filter(&[3, 4, 3, 7], SearchFilter::EqualTo(3));
+        assert_eq!(search.candidates(), [0, 2]);
+        search.filter(&[3], SearchFilter::Unchanged);
+        assert_eq!(search.candidates(), [0], "addresses beyond memory are dropped");
+    }
+
+    #[test]
+    fn test_cheat_list_round_trips_text() {
+        let list = CheatList {
+            rom_sha1: sha1(b"rom"),
+            cheats: vec![
+                Cheat { address: 0x2F5, value: 3, description: "Infinite lives".to_string() },
+                Cheat { address: 0xFFFF, value: 0xAB, description: String::new() },
+            ],
+        };
+
+        let text = list.to_text(Some("Example"));
+
+        assert_eq!(text, format!("# Example\nrom {}\n02F5:03 Infinite lives\nFFFF:AB\n", to_hex(&sha1(b"rom"))));
+        assert_eq!(CheatList::from_text(&text), Ok(list));
+        assert!(matches!(CheatList::from_text("02F5:03"), Err(Chip8Error::InvalidCheats(_))));
+        assert!(matches!(CheatList::from_text("rom abc\n"), Err(Chip8Error::InvalidCheats(_))));
+        assert!(matches!(CheatList::from_text(&format!("rom {}\n2F5=3", to_hex(&sha1(b"rom")))), Err(Chip8Error::InvalidCheats(_))));
+    }
+}
@@ -0,0 +1,74 @@
+//! Which bytes of memory the program used and how, for reverse-engineering ROMs.
+//!
+//! Every byte has a set of flags: executed as the first byte of an instruction, executed as one
+//! of its other bytes, read as sprite data by DXYN, read by FX65 / 5XY3 and written by FX33 /
+//! FX55 / 5XY2. A byte without flags was never touched since the last reset.
+
+use super::{Chip8, DisassembledLine, Syntax};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CoverageMap {
+    flags: Vec<u8>,
+}
+
+impl CoverageMap {
+    pub const OPCODE: u8 = 0x01;
+    pub const OPERAND: u8 = 0x02;
+    pub const SPRITE: u8 = 0x04;
+    pub const READ: u8 = 0x08;
+    pub const WRITTEN: u8 = 0x10;
+    pub const CODE: u8 = CoverageMap::OPCODE | CoverageMap::OPERAND;
+    pub const DATA: u8 = CoverageMap::SPRITE | CoverageMap::READ | CoverageMap::WRITTEN;
+
+    pub fn new(memory_size: usize) -> Self {
+        CoverageMap { flags: vec![0; memory_size] }
+    }
+
+    pub fn flags(&self, address: usize) -> u8 {
+        self.flags.get(address).copied().unwrap_or(0)
+    }
+
+    /// One byte of flags per address of memory.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.flags
+    }
+
+    /// Number of bytes with any of `flags` set.
+    pub fn count(&self, flags: u8) -> usize {
+        self.flags.iter().filter(|&&byte| byte & flags != 0).count()
+    }
+
+    // Memory grows or shrinks with the platform, flags of dropped memory go with it.
+    pub(super) fn resize(&mut self, memory_size: usize) {
+        self.flags.resize(memory_size, 0);
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.flags.fill(0);
+    }
+
+    pub(super) fn mark(&mut self, address: usize, flag: u8) {
+        if let Some(byte) = self.flags.get_mut(address) {
+            *byte |= flag;
+        }
+    }
+
+    pub(super) fn mark_instruction(&mut self, address: usize, size: usize) {
+        self.mark(address, CoverageMap::OPCODE);
+        for operand in address + 1..address + size {
+            self.mark(operand, CoverageMap::OPERAND);
+        }
+    }
+}
+
+impl Chip8 {
+    pub fn coverage(&self) -> &CoverageMap {
+        &self.coverage
+    }
+
+    /// Like `disassemble`, but bytes the program only used as data are shown as data and
+    /// instructions start where they were executed, even at odd addresses.
+    pub fn disassemble_with_coverage(&self, start: usize, end: usize, syntax: Syntax) -> Vec<DisassembledLine> {
+        super::disassembler::disassemble_with_coverage(&self.memory, self.coverage.as_bytes(), start, end, self.position_in_memory, syntax)
+    }
+}
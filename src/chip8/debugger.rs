@@ -0,0 +1,236 @@
+//! Breakpoints and watchpoints, checked by `run_frame` and `run_until_break`.
+//!
+//! A breakpoint stops before the instruction at its address runs, and running again executes
+//! that instruction instead of stopping on it a second time. Watchpoints stop after the
+//! instruction that touched the memory or changed the register. Only data accesses count as
+//! memory reads: sprite data for DXYN, the FX33 / FX55 / FX65 stores and loads and their
+//! XO-CHIP ranges, but not instruction fetches.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use super::{Chip8, Chip8Error};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchedRegister {
+    V(u8),
+    I,
+}
+
+impl WatchedRegister {
+    pub fn from_name(name: &str) -> Option<WatchedRegister> {
+        let name = name.to_ascii_uppercase();
+        if name == "I" {
+            return Some(WatchedRegister::I);
+        }
+        let index = u8::from_str_radix(name.strip_prefix('V')?, 16).ok()?;
+        (index < 16).then_some(WatchedRegister::V(index))
+    }
+}
+
+impl fmt::Display for WatchedRegister {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatchedRegister::V(index) => write!(f, "V{:X}", index),
+            WatchedRegister::I => write!(f, "I"),
+        }
+    }
+}
+
+/// Watches the addresses `start..=end`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryWatchpoint {
+    pub start: u16,
+    pub end: u16,
+    pub on_read: bool,
+    pub on_write: bool,
+}
+
+impl MemoryWatchpoint {
+    fn contains(&self, address: usize) -> bool {
+        (self.start as usize..=self.end as usize).contains(&address)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BreakReason {
+    Breakpoint,
+    MemoryRead { address: u16, value: u8 },
+    MemoryWrite { address: u16, old: u8, new: u8 },
+    RegisterChanged { register: WatchedRegister, old: u16, new: u16 },
+    InstructionLimit,
+    Halted,
+}
+
+impl fmt::Display for BreakReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreakReason::Breakpoint => write!(f, "Breakpoint"),
+            BreakReason::MemoryRead { address, value } => write!(f, "Read 0x{:02X} from 0x{:04X}", value, address),
+            BreakReason::MemoryWrite { address, old, new } => {
+                write!(f, "Wrote 0x{:02X} to 0x{:04X} (was 0x{:02X})", new, address, old)
+            }
+            BreakReason::RegisterChanged { register, old, new } => {
+                write!(f, "{} changed from 0x{:02X} to 0x{:02X}", register, old, new)
+            }
+            BreakReason::InstructionLimit => write!(f, "Instruction limit reached"),
+            BreakReason::Halted => write!(f, "Program halted"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BreakReport {
+    pub reason: BreakReason,
+    /// Address of the instruction that hit the breakpoint or watchpoint, the PC otherwise.
+    pub address: u16,
+    pub instructions_executed: u64,
+}
+
+#[derive(Default)]
+pub(super) struct Debugger {
+    breakpoints: BTreeSet<u16>,
+    memory_watchpoints: Vec<MemoryWatchpoint>,
+    register_watchpoints: Vec<WatchedRegister>,
+    // Breakpoint execution stopped on, the next step runs its instruction instead of stopping again
+    resume_address: Option<u16>,
+    // First memory watchpoint hit by the instruction that is executing
+    memory_hit: Option<BreakReason>,
+    last_break: Option<BreakReport>,
+}
+
+impl Debugger {
+    pub(super) fn record_read(&mut self, address: usize, value: u8) {
+        if self.memory_hit.is_none() && self.memory_watchpoints.iter().any(|watch| watch.on_read && watch.contains(address)) {
+            self.memory_hit = Some(BreakReason::MemoryRead { address: address as u16, value });
+        }
+    }
+
+    pub(super) fn record_write(&mut self, address: usize, old: u8, new: u8) {
+        if self.memory_hit.is_none() && self.memory_watchpoints.iter().any(|watch| watch.on_write && watch.contains(address)) {
+            self.memory_hit = Some(BreakReason::MemoryWrite { address: address as u16, old, new });
+        }
+    }
+}
+
+impl Chip8 {
+    pub fn add_breakpoint(&mut self, address: u16) {
+        self.debugger.breakpoints.insert(address);
+    }
+
+    pub fn remove_breakpoint(&mut self, address: u16) -> bool {
+        self.debugger.breakpoints.remove(&address)
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.debugger.breakpoints.clear();
+    }
+
+    pub fn breakpoints(&self) -> Vec<u16> {
+        self.debugger.breakpoints.iter().copied().collect()
+    }
+
+    pub fn add_memory_watchpoint(&mut self, watchpoint: MemoryWatchpoint) {
+        self.debugger.memory_watchpoints.push(watchpoint);
+    }
+
+    /// Removes the watchpoints on exactly `start..=end`, returns whether there were any.
+    pub fn remove_memory_watchpoint(&mut self, start: u16, end: u16) -> bool {
+        let count = self.debugger.memory_watchpoints.len();
+        self.debugger.memory_watchpoints.retain(|watch| (watch.start, watch.end) != (start, end));
+        self.debugger.memory_watchpoints.len() != count
+    }
+
+    pub fn watch_register(&mut self, register: WatchedRegister) {
+        if !self.debugger.register_watchpoints.contains(&register) {
+            self.debugger.register_watchpoints.push(register);
+        }
+    }
+
+    pub fn unwatch_register(&mut self, register: WatchedRegister) {
+        self.debugger.register_watchpoints.retain(|&watched| watched != register);
+    }
+
+    /// Removes all memory and register watchpoints.
+    pub fn clear_watchpoints(&mut self) {
+        self.debugger.memory_watchpoints.clear();
+        self.debugger.register_watchpoints.clear();
+    }
+
+    /// Why `run_frame` or `run_until_break` stopped the last time they did.
+    pub fn last_break(&self) -> Option<BreakReport> {
+        self.debugger.last_break
+    }
+
+    /// Executes instructions until a breakpoint or watchpoint is hit, the program halts or
+    /// `max_instructions` have run. Timers tick after every frame's worth of instructions.
+    pub fn run_until_break(&mut self, max_instructions: u64) -> Result<BreakReport, Chip8Error> {
+        let mut executed = 0;
+
+        loop {
+            let address = self.position_in_memory as u16;
+            let reason = if executed == max_instructions {
+                Some(BreakReason::InstructionLimit)
+            } else if self.halted {
+                Some(BreakReason::Halted)
+            } else if self.breakpoint_hit() {
+                Some(BreakReason::Breakpoint)
+            } else {
+                if self.frame_instructions == 0 {
+                    self.apply_movie_inputs();
+                }
+                let (_, hit) = self.execute_watched_step()?;
+                executed += 1;
+                self.frame_instructions += 1;
+                if self.frame_instructions >= self.instructions_per_frame {
+                    self.finish_frame();
+                }
+                hit
+            };
+
+            if let Some(reason) = reason {
+                return Ok(self.stop(reason, address, executed));
+            }
+        }
+    }
+
+    pub(super) fn stop(&mut self, reason: BreakReason, address: u16, instructions_executed: u64) -> BreakReport {
+        if reason == BreakReason::Breakpoint {
+            self.debugger.resume_address = Some(address);
+        }
+        let report = BreakReport { reason, address, instructions_executed };
+        self.debugger.last_break = Some(report);
+        report
+    }
+
+    pub(super) fn breakpoint_hit(&mut self) -> bool {
+        let address = self.position_in_memory as u16;
+        if self.debugger.resume_address.take() == Some(address) {
+            return false;
+        }
+        self.debugger.breakpoints.contains(&address)
+    }
+
+    // `execute_step` plus the watchpoint the instruction hit, if any.
+    pub(super) fn execute_watched_step(&mut self) -> Result<(u16, Option<BreakReason>), Chip8Error> {
+        if self.debugger.memory_watchpoints.is_empty() && self.debugger.register_watchpoints.is_empty() {
+            return Ok((self.execute_step()?, None));
+        }
+
+        let (registers, i_register) = (self.registers, self.i_register);
+        self.debugger.memory_hit = None;
+        let opcode = self.execute_step()?;
+
+        if let Some(hit) = self.debugger.memory_hit.take() {
+            return Ok((opcode, Some(hit)));
+        }
+        let changed = self.debugger.register_watchpoints.iter().find_map(|&register| {
+            let (old, new) = match register {
+                WatchedRegister::V(index) => (registers[index as usize] as u16, self.registers[index as usize] as u16),
+                WatchedRegister::I => (i_register, self.i_register),
+            };
+            (old != new).then_some(BreakReason::RegisterChanged { register, old, new })
+        });
+        Ok((opcode, changed))
+    }
+}
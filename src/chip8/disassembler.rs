@@ -0,0 +1,288 @@
+use std::fmt;
+
+use super::{CoverageMap, Instruction};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Syntax {
+    Cowgod,
+    Octo,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisassembledLine {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub text: String,
+    pub is_current: bool,
+}
+
+impl fmt::Display for DisassembledLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let marker = if self.is_current { '>' } else { ' ' };
+        let hex: String = self.bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+
+        write!(f, "{} 0x{:04X}: {:<8} {}", marker, self.address, hex, self.text)
+    }
+}
+
+// Walks `memory[start..end]` as code. Bytes that don't decode are shown as data.
+pub fn disassemble(memory: &[u8], start: usize, end: usize, pc: usize, syntax: Syntax) -> Vec<DisassembledLine> {
+    let end = end.min(memory.len());
+    let mut lines = Vec::new();
+    let mut address = start;
+
+    while address < end {
+        let line = code_line(memory, address, end, pc, syntax);
+        address += line.bytes.len();
+        lines.push(line);
+    }
+
+    lines
+}
+
+// Walks `memory[start..end]` with the flags of a `CoverageMap`. Executed instructions are decoded
+// wherever they start, bytes only used as data are shown as data and untouched bytes are decoded
+// as code like `disassemble` does.
+pub fn disassemble_with_coverage(memory: &[u8], coverage: &[u8], start: usize, end: usize, pc: usize, syntax: Syntax) -> Vec<DisassembledLine> {
+    let end = end.min(memory.len());
+    let flags = |address: usize| coverage.get(address).copied().unwrap_or(0);
+    let mut lines = Vec::new();
+    let mut address = start;
+
+    while address < end {
+        let is_data = |address: usize| flags(address) & CoverageMap::CODE == 0 && flags(address) & CoverageMap::DATA != 0;
+        let line = if flags(address) & CoverageMap::OPCODE != 0 {
+            code_line(memory, address, end, pc, syntax)
+        } else if is_data(address) {
+            let size = if address + 1 < end && is_data(address + 1) { 2 } else { 1 };
+            data_line(memory, address, size, pc, syntax)
+        } else if flags(address) & CoverageMap::OPERAND != 0 || flags(address + 1) & CoverageMap::OPCODE != 0 {
+            // Resynchronise with the executed instructions
+            data_line(memory, address, 1, pc, syntax)
+        } else {
+            code_line(memory, address, end, pc, syntax)
+        };
+
+        address += line.bytes.len();
+        lines.push(line);
+    }
+
+    lines
+}
+
+// The instruction at `address`, or data if it doesn't decode or is cut off by `end`.
+fn code_line(memory: &[u8], address: usize, end: usize, pc: usize, syntax: Syntax) -> DisassembledLine {
+    if address + 1 >= end {
+        return data_line(memory, address, 1, pc, syntax);
+    }
+
+    let opcode = (memory[address] as u16) << 8 | memory[address + 1] as u16;
+    match Instruction::decode(opcode) {
+        Ok(Instruction::LdILong) if address + 3 < end => {
+            let operand = (memory[address + 2] as u16) << 8 | memory[address + 3] as u16;
+            instruction_line(memory, address, Instruction::LdILong, Some(operand), pc, syntax)
+        }
+        Ok(Instruction::LdILong) => data_line(memory, address, 2, pc, syntax),
+        Ok(instruction) => instruction_line(memory, address, instruction, None, pc, syntax),
+        Err(_) => data_line(memory, address, 2, pc, syntax),
+    }
+}
+
+pub fn format_instruction(instruction: Instruction, long_operand: Option<u16>, syntax: Syntax) -> String {
+    match syntax {
+        Syntax::Cowgod => cowgod(instruction, long_operand.unwrap_or(0)),
+        Syntax::Octo => octo(instruction, long_operand.unwrap_or(0)),
+    }
+}
+
+fn instruction_line(memory: &[u8], address: usize, instruction: Instruction, long_operand: Option<u16>, pc: usize, syntax: Syntax) -> DisassembledLine {
+    let size = instruction.size_in_bytes();
+
+    DisassembledLine {
+        address: address as u16,
+        bytes: memory[address..address + size].to_vec(),
+        text: format_instruction(instruction, long_operand, syntax),
+        is_current: address == pc,
+    }
+}
+
+fn data_line(memory: &[u8], address: usize, size: usize, pc: usize, syntax: Syntax) -> DisassembledLine {
+    let bytes = memory[address..address + size].to_vec();
+    let values: Vec<String> = bytes.iter().map(|byte| format!("0x{:02X}", byte)).collect();
+    let text = match syntax {
+        Syntax::Cowgod => format!("DB {}", values.join(", ")),
+        Syntax::Octo => values.join(" "),
+    };
+
+    DisassembledLine { address: address as u16, bytes, text, is_current: address == pc }
+}
+
+fn cowgod(instruction: Instruction, long_operand: u16) -> String {
+    match instruction {
+        Instruction::Sys { nnn } => format!("SYS 0x{:03X}", nnn),
+        Instruction::Cls => "CLS".to_string(),
+        Instruction::Ret => "RET".to_string(),
+        Instruction::ScdNibble { n } => format!("SCD {}", n),
+        Instruction::ScuNibble { n } => format!("SCU {}", n),
+        Instruction::Scr => "SCR".to_string(),
+        Instruction::Scl => "SCL".to_string(),
+        Instruction::Exit => "EXIT".to_string(),
+        Instruction::Low => "LOW".to_string(),
+        Instruction::High => "HIGH".to_string(),
+        Instruction::JpAddr { nnn } => format!("JP 0x{:03X}", nnn),
+        Instruction::CallAddr { nnn } => format!("CALL 0x{:03X}", nnn),
+        Instruction::SeVxByte { x, kk } => format!("SE V{:X}, 0x{:02X}", x, kk),
+        Instruction::SneVxByte { x, kk } => format!("SNE V{:X}, 0x{:02X}", x, kk),
+        Instruction::SeVxVy { x, y } => format!("SE V{:X}, V{:X}", x, y),
+        Instruction::LdIVxVy { x, y } => format!("LD [I], V{:X}-V{:X}", x, y),
+        Instruction::LdVxVyI { x, y } => format!("LD V{:X}-V{:X}, [I]", x, y),
+        Instruction::LdVxByte { x, kk } => format!("LD V{:X}, 0x{:02X}", x, kk),
+        Instruction::AddVxByte { x, kk } => format!("ADD V{:X}, 0x{:02X}", x, kk),
+        Instruction::LdVxVy { x, y } => format!("LD V{:X}, V{:X}", x, y),
+        Instruction::OrVxVy { x, y } => format!("OR V{:X}, V{:X}", x, y),
+        Instruction::AndVxVy { x, y } => format!("AND V{:X}, V{:X}", x, y),
+        Instruction::XorVxVy { x, y } => format!("XOR V{:X}, V{:X}", x, y),
+        Instruction::AddVxVy { x, y } => format!("ADD V{:X}, V{:X}", x, y),
+        Instruction::SubVxVy { x, y } => format!("SUB V{:X}, V{:X}", x, y),
+        Instruction::ShrVxVy { x, y } => format!("SHR V{:X}, V{:X}", x, y),
+        Instruction::SubnVxVy { x, y } => format!("SUBN V{:X}, V{:X}", x, y),
+        Instruction::ShlVxVy { x, y } => format!("SHL V{:X}, V{:X}", x, y),
+        Instruction::SneVxVy { x, y } => format!("SNE V{:X}, V{:X}", x, y),
+        Instruction::LdIAddr { nnn } => format!("LD I, 0x{:03X}", nnn),
+        Instruction::JpV0Addr { nnn } => format!("JP V0, 0x{:03X}", nnn),
+        Instruction::RndVxByte { x, kk } => format!("RND V{:X}, 0x{:02X}", x, kk),
+        Instruction::DrwVxVyNibble { x, y, n } => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        Instruction::SkpVx { x } => format!("SKP V{:X}", x),
+        Instruction::SknpVx { x } => format!("SKNP V{:X}", x),
+        Instruction::LdILong => format!("LD I, LONG 0x{:04X}", long_operand),
+        Instruction::PlaneN { n } => format!("PLANE {}", n),
+        Instruction::LdVxDt { x } => format!("LD V{:X}, DT", x),
+        Instruction::LdVxK { x } => format!("LD V{:X}, K", x),
+        Instruction::LdDtVx { x } => format!("LD DT, V{:X}", x),
+        Instruction::LdStVx { x } => format!("LD ST, V{:X}", x),
+        Instruction::AddIVx { x } => format!("ADD I, V{:X}", x),
+        Instruction::LdFVx { x } => format!("LD F, V{:X}", x),
+        Instruction::LdHfVx { x } => format!("LD HF, V{:X}", x),
+        Instruction::LdBVx { x } => format!("LD B, V{:X}", x),
+        Instruction::LdIVx { x } => format!("LD [I], V{:X}", x),
+        Instruction::LdVxI { x } => format!("LD V{:X}, [I]", x),
+        Instruction::LdRVx { x } => format!("LD R, V{:X}", x),
+        Instruction::LdVxR { x } => format!("LD V{:X}, R", x),
+    }
+}
+
+// Octo expresses skips as `if ... then`, so the condition is the inverse of the skip condition.
+fn octo(instruction: Instruction, long_operand: u16) -> String {
+    match instruction {
+        Instruction::Sys { nnn } => format!("0x{:02X} 0x{:02X}", nnn >> 8, nnn & 0xFF),
+        Instruction::Cls => "clear".to_string(),
+        Instruction::Ret => "return".to_string(),
+        Instruction::ScdNibble { n } => format!("scroll-down {}", n),
+        Instruction::ScuNibble { n } => format!("scroll-up {}", n),
+        Instruction::Scr => "scroll-right".to_string(),
+        Instruction::Scl => "scroll-left".to_string(),
+        Instruction::Exit => "exit".to_string(),
+        Instruction::Low => "lores".to_string(),
+        Instruction::High => "hires".to_string(),
+        Instruction::JpAddr { nnn } => format!("jump 0x{:03X}", nnn),
+        Instruction::CallAddr { nnn } => format!(":call 0x{:03X}", nnn),
+        Instruction::SeVxByte { x, kk } => format!("if v{:x} != 0x{:02X} then", x, kk),
+        Instruction::SneVxByte { x, kk } => format!("if v{:x} == 0x{:02X} then", x, kk),
+        Instruction::SeVxVy { x, y } => format!("if v{:x} != v{:x} then", x, y),
+        Instruction::LdIVxVy { x, y } => format!("save v{:x} - v{:x}", x, y),
+        Instruction::LdVxVyI { x, y } => format!("load v{:x} - v{:x}", x, y),
+        Instruction::LdVxByte { x, kk } => format!("v{:x} := 0x{:02X}", x, kk),
+        Instruction::AddVxByte { x, kk } => format!("v{:x} += 0x{:02X}", x, kk),
+        Instruction::LdVxVy { x, y } => format!("v{:x} := v{:x}", x, y),
+        Instruction::OrVxVy { x, y } => format!("v{:x} |= v{:x}", x, y),
+        Instruction::AndVxVy { x, y } => format!("v{:x} &= v{:x}", x, y),
+        Instruction::XorVxVy { x, y } => format!("v{:x} ^= v{:x}", x, y),
+        Instruction::AddVxVy { x, y } => format!("v{:x} += v{:x}", x, y),
+        Instruction::SubVxVy { x, y } => format!("v{:x} -= v{:x}", x, y),
+        Instruction::ShrVxVy { x, y } => format!("v{:x} >>= v{:x}", x, y),
+        Instruction::SubnVxVy { x, y } => format!("v{:x} =- v{:x}", x, y),
+        Instruction::ShlVxVy { x, y } => format!("v{:x} <<= v{:x}", x, y),
+        Instruction::SneVxVy { x, y } => format!("if v{:x} == v{:x} then", x, y),
+        Instruction::LdIAddr { nnn } => format!("i := 0x{:03X}", nnn),
+        Instruction::JpV0Addr { nnn } => format!("jump0 0x{:03X}", nnn),
+        Instruction::RndVxByte { x, kk } => format!("v{:x} := random 0x{:02X}", x, kk),
+        Instruction::DrwVxVyNibble { x, y, n } => format!("sprite v{:x} v{:x} {}", x, y, n),
+        Instruction::SkpVx { x } => format!("if v{:x} -key then", x),
+        Instruction::SknpVx { x } => format!("if v{:x} key then", x),
+        Instruction::LdILong => format!("i := long 0x{:04X}", long_operand),
+        Instruction::PlaneN { n } => format!("plane {}", n),
+        Instruction::LdVxDt { x } => format!("v{:x} := delay", x),
+        Instruction::LdVxK { x } => format!("v{:x} := key", x),
+        Instruction::LdDtVx { x } => format!("delay := v{:x}", x),
+        Instruction::LdStVx { x } => format!("buzzer := v{:x}", x),
+        Instruction::AddIVx { x } => format!("i += v{:x}", x),
+        Instruction::LdFVx { x } => format!("i := hex v{:x}", x),
+        Instruction::LdHfVx { x } => format!("i := bighex v{:x}", x),
+        Instruction::LdBVx { x } => format!("bcd v{:x}", x),
+        Instruction::LdIVx { x } => format!("save v{:x}", x),
+        Instruction::LdVxI { x } => format!("load v{:x}", x),
+        Instruction::LdRVx { x } => format!("saveflags v{:x}", x),
+        Instruction::LdVxR { x } => format!("loadflags v{:x}", x),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disassemble_cowgod() {
+        let memory = [0x61, 0x20, 0xA2, 0x34, 0xD1, 0x25, 0x00, 0xEE];
+
+        let lines = disassemble(&memory, 0, memory.len(), 2, Syntax::Cowgod);
+        let text: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+
+        assert_eq!(text, ["LD V1, 0x20", "LD I, 0x234", "DRW V1, V2, 5", "RET"]);
+        assert!(lines[1].is_current);
+        assert!(!lines[0].is_current);
+    }
+
+    #[test]
+    fn test_disassemble_octo() {
+        let memory = [0x61, 0x20, 0x31, 0x05, 0xF1, 0x0A];
+
+        let lines = disassemble(&memory, 0, memory.len(), 0, Syntax::Octo);
+        let text: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+
+        assert_eq!(text, ["v1 := 0x20", "if v1 != 0x05 then", "v1 := key"]);
+    }
+
+    #[test]
+    fn test_disassemble_long_load_and_data() {
+        let memory = [0xF0, 0x00, 0x12, 0x34, 0x51, 0x21, 0xFF];
+
+        let lines = disassemble(&memory, 0, memory.len(), 0, Syntax::Cowgod);
+
+        assert_eq!(lines[0].text, "LD I, LONG 0x1234");
+        assert_eq!(lines[0].bytes.len(), 4);
+        assert_eq!(lines[1].text, "DB 0x51, 0x21");
+        assert_eq!(lines[2].text, "DB 0xFF");
+    }
+
+    #[test]
+    fn test_disassemble_with_coverage_separates_code_and_data() {
+        // A byte of padding puts the code at an odd address, followed by a sprite that decodes as JP
+        let memory = [0x00, 0x61, 0x20, 0xD1, 0x25, 0x18, 0x3C, 0xFF, 0xFF];
+        let coverage = [0x00, 0x01, 0x02, 0x01, 0x02, 0x04, 0x04, 0x00, 0x00];
+
+        let lines = disassemble_with_coverage(&memory, &coverage, 0, memory.len(), 1, Syntax::Cowgod);
+        let text: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+
+        assert_eq!(text, ["DB 0x00", "LD V1, 0x20", "DRW V1, V2, 5", "DB 0x18, 0x3C", "DB 0xFF, 0xFF"]);
+        assert!(lines[1].is_current);
+    }
+
+    #[test]
+    fn test_line_display_marks_current_instruction() {
+        let memory = [0x00, 0xE0];
+
+        let lines = disassemble(&memory, 0, memory.len(), 0, Syntax::Cowgod);
+
+        assert_eq!(lines[0].to_string(), "> 0x0000: 00E0     CLS");
+    }
+}
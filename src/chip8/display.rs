@@ -0,0 +1,188 @@
+use std::ops::{Index, IndexMut};
+
+// More dirty regions than this are merged into their bounding box.
+const MAX_DIRTY_REGIONS: usize = 8;
+
+/// Rectangle of pixels that changed since the display was last presented.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DirtyRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl DirtyRect {
+    pub(crate) fn pixel(x: usize, y: usize) -> Self {
+        DirtyRect { x, y, width: 1, height: 1 }
+    }
+
+    // Grows `bounds` to include the pixel, starting it at the pixel if it is empty.
+    pub(crate) fn include(bounds: &mut Option<DirtyRect>, x: usize, y: usize) {
+        let pixel = DirtyRect::pixel(x, y);
+        *bounds = Some(bounds.map_or(pixel, |bounds| bounds.union(pixel)));
+    }
+
+    fn right(&self) -> usize {
+        self.x + self.width
+    }
+
+    fn bottom(&self) -> usize {
+        self.y + self.height
+    }
+
+    fn union(self, other: DirtyRect) -> DirtyRect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        DirtyRect { x, y, width: self.right().max(other.right()) - x, height: self.bottom().max(other.bottom()) - y }
+    }
+
+    // Overlapping or adjacent. Merged regions are bounding boxes and may over-cover, e.g. two
+    // rectangles meeting at a corner also dirty the pixels beside that corner.
+    fn touches(&self, other: &DirtyRect) -> bool {
+        self.x <= other.right() && other.x <= self.right() && self.y <= other.bottom() && other.y <= self.bottom()
+    }
+}
+
+/// Row-major framebuffer with one byte per pixel, indexable as `display[y][x]`.
+///
+/// Clearing, scrolling and drawing through `Chip8` record the regions that changed until the
+/// display is presented. A new, resized or restored display is dirty as a whole.
+pub struct Display {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+    dirty: Vec<DirtyRect>,
+}
+
+impl Display {
+    pub fn new(width: usize, height: usize) -> Self {
+        Display::from_pixels(width, height, vec![0; width * height])
+    }
+
+    // `pixels` must hold exactly `width * height` entries.
+    pub(crate) fn from_pixels(width: usize, height: usize, pixels: Vec<u8>) -> Self {
+        debug_assert_eq!(pixels.len(), width * height);
+        Display { width, height, pixels, dirty: vec![DirtyRect { x: 0, y: 0, width, height }] }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn as_ptr(&self) -> *const u8 {
+        self.pixels.as_ptr()
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+
+    /// Regions changed since the last present, they don't overlap each other.
+    pub fn dirty_regions(&self) -> &[DirtyRect] {
+        &self.dirty
+    }
+
+    pub(crate) fn mark_dirty(&mut self, mut region: DirtyRect) {
+        // Merging can make the region touch ones it didn't before, so keep going until none does
+        while let Some(index) = self.dirty.iter().position(|dirty| dirty.touches(&region)) {
+            region = region.union(self.dirty.swap_remove(index));
+        }
+        self.dirty.push(region);
+
+        if self.dirty.len() > MAX_DIRTY_REGIONS {
+            let bounds = self.dirty.iter().copied().reduce(DirtyRect::union).expect("dirty regions are not empty");
+            self.dirty = vec![bounds];
+        }
+    }
+
+    pub(crate) fn present(&mut self) {
+        self.dirty.clear();
+    }
+
+    // Each pixel stores one bit per XO-CHIP bitplane, `planes` selects the bits affected.
+    pub fn clear(&mut self, planes: u8) {
+        let mut changed = None;
+        for (index, pixel) in self.pixels.iter_mut().enumerate() {
+            if *pixel & planes != 0 {
+                *pixel &= !planes;
+                DirtyRect::include(&mut changed, index % self.width, index / self.width);
+            }
+        }
+        if let Some(changed) = changed {
+            self.mark_dirty(changed);
+        }
+    }
+
+    // Switching resolution always starts from a blank screen.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        *self = Display::new(width, height);
+    }
+
+    pub fn scroll_down(&mut self, rows: usize, planes: u8) {
+        self.shift(0, rows as isize, planes);
+    }
+
+    pub fn scroll_up(&mut self, rows: usize, planes: u8) {
+        self.shift(0, -(rows as isize), planes);
+    }
+
+    pub fn scroll_right(&mut self, columns: usize, planes: u8) {
+        self.shift(columns as isize, 0, planes);
+    }
+
+    pub fn scroll_left(&mut self, columns: usize, planes: u8) {
+        self.shift(-(columns as isize), 0, planes);
+    }
+
+    fn shift(&mut self, dx: isize, dy: isize, planes: u8) {
+        let source = self.pixels.clone();
+        let width = self.width as isize;
+        let height = self.height as isize;
+        let mut changed = None;
+
+        for y in 0..height {
+            for x in 0..width {
+                let source_x = x - dx;
+                let source_y = y - dy;
+                let moved = if (0..width).contains(&source_x) && (0..height).contains(&source_y) {
+                    source[(source_y * width + source_x) as usize]
+                } else {
+                    0
+                };
+
+                let pixel = &mut self.pixels[(y * width + x) as usize];
+                let shifted = (*pixel & !planes) | (moved & planes);
+                if *pixel != shifted {
+                    *pixel = shifted;
+                    DirtyRect::include(&mut changed, x as usize, y as usize);
+                }
+            }
+        }
+        if let Some(changed) = changed {
+            self.mark_dirty(changed);
+        }
+    }
+}
+
+impl Index<usize> for Display {
+    type Output = [u8];
+
+    fn index(&self, y: usize) -> &[u8] {
+        &self.pixels[y * self.width..(y + 1) * self.width]
+    }
+}
+
+impl IndexMut<usize> for Display {
+    fn index_mut(&mut self, y: usize) -> &mut [u8] {
+        &mut self.pixels[y * self.width..(y + 1) * self.width]
+    }
+}
@@ -0,0 +1,56 @@
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Chip8Error {
+    UnknownOpcode { opcode: u16, address: u16 },
+    StackOverflow { address: u16 },
+    StackUnderflow { address: u16 },
+    MemoryOutOfBounds { address: usize },
+    InvalidKey(u8),
+    InvalidRegister(u8),
+    RomTooLarge { size: usize, max_size: usize },
+    RomNotFound(String),
+    InvalidSaveState(String),
+    UnsupportedSaveStateVersion(u16),
+    InvalidMovie(String),
+    MovieRomMismatch,
+    InvalidRomDatabase(String),
+    InvalidCheats(String),
+    CheatRomMismatch,
+    CheatsLockedByMovie,
+    StateLockedByMovie,
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8Error::UnknownOpcode { opcode, address } => {
+                write!(f, "Unknown opcode 0x{:04X} at address 0x{:04X}", opcode, address)
+            }
+            Chip8Error::StackOverflow { address } => write!(f, "Stack overflow at address 0x{:04X}", address),
+            Chip8Error::StackUnderflow { address } => write!(f, "Stack underflow at address 0x{:04X}", address),
+            Chip8Error::MemoryOutOfBounds { address } => {
+                write!(f, "Memory access out of bounds at address 0x{:04X}", address)
+            }
+            Chip8Error::InvalidKey(key) => write!(f, "Invalid key index {}, expected 0x0-0xF", key),
+            Chip8Error::InvalidRegister(index) => write!(f, "Invalid register index {}, expected V0-VF", index),
+            Chip8Error::RomTooLarge { size, max_size } => {
+                write!(f, "ROM is {} bytes, but only {} bytes are available", size, max_size)
+            }
+            Chip8Error::RomNotFound(name) => write!(f, "ROM not found: '{}'", name),
+            Chip8Error::InvalidSaveState(reason) => write!(f, "Invalid save state: {}", reason),
+            Chip8Error::UnsupportedSaveStateVersion(version) => {
+                write!(f, "Save state version {} is not supported, expected {}", version, super::SAVE_STATE_VERSION)
+            }
+            Chip8Error::InvalidMovie(reason) => write!(f, "Invalid movie: {}", reason),
+            Chip8Error::MovieRomMismatch => write!(f, "The movie was recorded with a different ROM"),
+            Chip8Error::InvalidRomDatabase(reason) => write!(f, "Invalid ROM database: {}", reason),
+            Chip8Error::InvalidCheats(reason) => write!(f, "Invalid cheat list: {}", reason),
+            Chip8Error::CheatRomMismatch => write!(f, "The cheat list was made for a different ROM"),
+            Chip8Error::CheatsLockedByMovie => write!(f, "Cheats can't be changed while a movie is recorded or played"),
+            Chip8Error::StateLockedByMovie => write!(f, "Save states can't be loaded while a movie is recorded or played"),
+        }
+    }
+}
+
+impl std::error::Error for Chip8Error {}
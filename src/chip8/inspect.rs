@@ -0,0 +1,79 @@
+//! Reading and patching the machine state from a debugger, e.g. for a hex view while paused.
+//!
+//! Edits bypass the watchpoints and are not recorded in movies or the execution trace.
+
+use super::{Chip8, Chip8Error};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timer {
+    Delay,
+    Sound,
+}
+
+impl Timer {
+    pub fn from_name(name: &str) -> Option<Timer> {
+        match name.to_ascii_lowercase().as_str() {
+            "delay" | "dt" => Some(Timer::Delay),
+            "sound" | "st" => Some(Timer::Sound),
+            _ => None,
+        }
+    }
+}
+
+impl Chip8 {
+    pub fn get_memory_size(&self) -> usize {
+        self.memory.len()
+    }
+
+    /// Size of the loaded ROM, which starts at 0x200.
+    pub fn get_rom_size(&self) -> usize {
+        self.rom.len()
+    }
+
+    /// Up to `length` bytes from `start`, cut off at the end of memory.
+    pub fn get_memory(&self, start: usize, length: usize) -> &[u8] {
+        let start = start.min(self.memory.len());
+        let end = start.saturating_add(length).min(self.memory.len());
+        &self.memory[start..end]
+    }
+
+    /// Return addresses of the active subroutine calls, the outermost first.
+    pub fn get_stack(&self) -> &[u16] {
+        &self.stack[..self.stack_pointer]
+    }
+
+    pub fn get_keys(&self) -> [bool; 16] {
+        self.keyboard
+    }
+
+    pub fn set_register(&mut self, index: u8, value: u8) -> Result<(), Chip8Error> {
+        let register = self.registers.get_mut(index as usize).ok_or(Chip8Error::InvalidRegister(index))?;
+        *register = value;
+        Ok(())
+    }
+
+    pub fn set_i(&mut self, value: u16) {
+        self.i_register = value;
+    }
+
+    pub fn set_pc(&mut self, address: u16) -> Result<(), Chip8Error> {
+        if address as usize >= self.memory.len() {
+            return Err(Chip8Error::MemoryOutOfBounds { address: address as usize });
+        }
+        self.position_in_memory = address as usize;
+        Ok(())
+    }
+
+    pub fn poke(&mut self, address: usize, value: u8) -> Result<(), Chip8Error> {
+        let byte = self.memory.get_mut(address).ok_or(Chip8Error::MemoryOutOfBounds { address })?;
+        *byte = value;
+        Ok(())
+    }
+
+    pub fn set_timer(&mut self, timer: Timer, value: u8) {
+        match timer {
+            Timer::Delay => self.delay_timer = value,
+            Timer::Sound => self.sound_timer = value,
+        }
+    }
+}
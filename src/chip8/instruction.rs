@@ -0,0 +1,278 @@
+use std::fmt;
+
+use super::Platform;
+
+/// A decoded opcode. Operand names follow Cowgod's reference (x, y, n, kk, nnn).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    Sys { nnn: u16 },
+    Cls,
+    Ret,
+    ScdNibble { n: u8 },
+    ScuNibble { n: u8 },
+    Scr,
+    Scl,
+    Exit,
+    Low,
+    High,
+    JpAddr { nnn: u16 },
+    CallAddr { nnn: u16 },
+    SeVxByte { x: u8, kk: u8 },
+    SneVxByte { x: u8, kk: u8 },
+    SeVxVy { x: u8, y: u8 },
+    LdIVxVy { x: u8, y: u8 },
+    LdVxVyI { x: u8, y: u8 },
+    LdVxByte { x: u8, kk: u8 },
+    AddVxByte { x: u8, kk: u8 },
+    LdVxVy { x: u8, y: u8 },
+    OrVxVy { x: u8, y: u8 },
+    AndVxVy { x: u8, y: u8 },
+    XorVxVy { x: u8, y: u8 },
+    AddVxVy { x: u8, y: u8 },
+    SubVxVy { x: u8, y: u8 },
+    ShrVxVy { x: u8, y: u8 },
+    SubnVxVy { x: u8, y: u8 },
+    ShlVxVy { x: u8, y: u8 },
+    SneVxVy { x: u8, y: u8 },
+    LdIAddr { nnn: u16 },
+    JpV0Addr { nnn: u16 },
+    RndVxByte { x: u8, kk: u8 },
+    DrwVxVyNibble { x: u8, y: u8, n: u8 },
+    SkpVx { x: u8 },
+    SknpVx { x: u8 },
+    /// F000 NNNN, the 16 bit address follows in the next two bytes.
+    LdILong,
+    PlaneN { n: u8 },
+    LdVxDt { x: u8 },
+    LdVxK { x: u8 },
+    LdDtVx { x: u8 },
+    LdStVx { x: u8 },
+    AddIVx { x: u8 },
+    LdFVx { x: u8 },
+    LdHfVx { x: u8 },
+    LdBVx { x: u8 },
+    LdIVx { x: u8 },
+    LdVxI { x: u8 },
+    LdRVx { x: u8 },
+    LdVxR { x: u8 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeError {
+    pub opcode: u16,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown opcode 0x{:04X}", self.opcode)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl Instruction {
+    pub fn decode(opcode: u16) -> Result<Instruction, DecodeError> {
+        let x = ((opcode & 0x0F00) >> 8) as u8;
+        let y = ((opcode & 0x00F0) >> 4) as u8;
+        let n = (opcode & 0x000F) as u8;
+        let kk = (opcode & 0x00FF) as u8;
+        let nnn = opcode & 0x0FFF;
+        let unknown = DecodeError { opcode };
+
+        let instruction = match opcode & 0xF000 {
+            0x0000 => match opcode {
+                0x00E0 => Instruction::Cls,
+                0x00EE => Instruction::Ret,
+                0x00C0..=0x00CF => Instruction::ScdNibble { n },
+                0x00D0..=0x00DF => Instruction::ScuNibble { n },
+                0x00FB => Instruction::Scr,
+                0x00FC => Instruction::Scl,
+                0x00FD => Instruction::Exit,
+                0x00FE => Instruction::Low,
+                0x00FF => Instruction::High,
+                _ => Instruction::Sys { nnn },
+            },
+            0x1000 => Instruction::JpAddr { nnn },
+            0x2000 => Instruction::CallAddr { nnn },
+            0x3000 => Instruction::SeVxByte { x, kk },
+            0x4000 => Instruction::SneVxByte { x, kk },
+            0x5000 => match n {
+                0x0 => Instruction::SeVxVy { x, y },
+                0x2 => Instruction::LdIVxVy { x, y },
+                0x3 => Instruction::LdVxVyI { x, y },
+                _ => return Err(unknown),
+            },
+            0x6000 => Instruction::LdVxByte { x, kk },
+            0x7000 => Instruction::AddVxByte { x, kk },
+            0x8000 => match n {
+                0x0 => Instruction::LdVxVy { x, y },
+                0x1 => Instruction::OrVxVy { x, y },
+                0x2 => Instruction::AndVxVy { x, y },
+                0x3 => Instruction::XorVxVy { x, y },
+                0x4 => Instruction::AddVxVy { x, y },
+                0x5 => Instruction::SubVxVy { x, y },
+                0x6 => Instruction::ShrVxVy { x, y },
+                0x7 => Instruction::SubnVxVy { x, y },
+                0xE => Instruction::ShlVxVy { x, y },
+                _ => return Err(unknown),
+            },
+            0x9000 => match n {
+                0x0 => Instruction::SneVxVy { x, y },
+                _ => return Err(unknown),
+            },
+            0xA000 => Instruction::LdIAddr { nnn },
+            0xB000 => Instruction::JpV0Addr { nnn },
+            0xC000 => Instruction::RndVxByte { x, kk },
+            0xD000 => Instruction::DrwVxVyNibble { x, y, n },
+            0xE000 => match kk {
+                0x9E => Instruction::SkpVx { x },
+                0xA1 => Instruction::SknpVx { x },
+                _ => return Err(unknown),
+            },
+            _ => match kk {
+                0x00 if x == 0 => Instruction::LdILong,
+                0x01 => Instruction::PlaneN { n: x },
+                0x07 => Instruction::LdVxDt { x },
+                0x0A => Instruction::LdVxK { x },
+                0x15 => Instruction::LdDtVx { x },
+                0x18 => Instruction::LdStVx { x },
+                0x1E => Instruction::AddIVx { x },
+                0x29 => Instruction::LdFVx { x },
+                0x30 => Instruction::LdHfVx { x },
+                0x33 => Instruction::LdBVx { x },
+                0x55 => Instruction::LdIVx { x },
+                0x65 => Instruction::LdVxI { x },
+                0x75 => Instruction::LdRVx { x },
+                0x85 => Instruction::LdVxR { x },
+                _ => return Err(unknown),
+            },
+        };
+
+        Ok(instruction)
+    }
+
+    pub fn encode(&self) -> u16 {
+        let xy = |prefix: u16, x: u8, y: u8, suffix: u16| prefix | (x as u16) << 8 | (y as u16) << 4 | suffix;
+        let xkk = |prefix: u16, x: u8, kk: u8| prefix | (x as u16) << 8 | kk as u16;
+        let fx = |x: u8, suffix: u16| 0xF000 | (x as u16) << 8 | suffix;
+
+        match *self {
+            Instruction::Sys { nnn } => nnn,
+            Instruction::Cls => 0x00E0,
+            Instruction::Ret => 0x00EE,
+            Instruction::ScdNibble { n } => 0x00C0 | n as u16,
+            Instruction::ScuNibble { n } => 0x00D0 | n as u16,
+            Instruction::Scr => 0x00FB,
+            Instruction::Scl => 0x00FC,
+            Instruction::Exit => 0x00FD,
+            Instruction::Low => 0x00FE,
+            Instruction::High => 0x00FF,
+            Instruction::JpAddr { nnn } => 0x1000 | nnn,
+            Instruction::CallAddr { nnn } => 0x2000 | nnn,
+            Instruction::SeVxByte { x, kk } => xkk(0x3000, x, kk),
+            Instruction::SneVxByte { x, kk } => xkk(0x4000, x, kk),
+            Instruction::SeVxVy { x, y } => xy(0x5000, x, y, 0x0),
+            Instruction::LdIVxVy { x, y } => xy(0x5000, x, y, 0x2),
+            Instruction::LdVxVyI { x, y } => xy(0x5000, x, y, 0x3),
+            Instruction::LdVxByte { x, kk } => xkk(0x6000, x, kk),
+            Instruction::AddVxByte { x, kk } => xkk(0x7000, x, kk),
+            Instruction::LdVxVy { x, y } => xy(0x8000, x, y, 0x0),
+            Instruction::OrVxVy { x, y } => xy(0x8000, x, y, 0x1),
+            Instruction::AndVxVy { x, y } => xy(0x8000, x, y, 0x2),
+            Instruction::XorVxVy { x, y } => xy(0x8000, x, y, 0x3),
+            Instruction::AddVxVy { x, y } => xy(0x8000, x, y, 0x4),
+            Instruction::SubVxVy { x, y } => xy(0x8000, x, y, 0x5),
+            Instruction::ShrVxVy { x, y } => xy(0x8000, x, y, 0x6),
+            Instruction::SubnVxVy { x, y } => xy(0x8000, x, y, 0x7),
+            Instruction::ShlVxVy { x, y } => xy(0x8000, x, y, 0xE),
+            Instruction::SneVxVy { x, y } => xy(0x9000, x, y, 0x0),
+            Instruction::LdIAddr { nnn } => 0xA000 | nnn,
+            Instruction::JpV0Addr { nnn } => 0xB000 | nnn,
+            Instruction::RndVxByte { x, kk } => xkk(0xC000, x, kk),
+            Instruction::DrwVxVyNibble { x, y, n } => xy(0xD000, x, y, n as u16),
+            Instruction::SkpVx { x } => xkk(0xE000, x, 0x9E),
+            Instruction::SknpVx { x } => xkk(0xE000, x, 0xA1),
+            Instruction::LdILong => 0xF000,
+            Instruction::PlaneN { n } => fx(n, 0x01),
+            Instruction::LdVxDt { x } => fx(x, 0x07),
+            Instruction::LdVxK { x } => fx(x, 0x0A),
+            Instruction::LdDtVx { x } => fx(x, 0x15),
+            Instruction::LdStVx { x } => fx(x, 0x18),
+            Instruction::AddIVx { x } => fx(x, 0x1E),
+            Instruction::LdFVx { x } => fx(x, 0x29),
+            Instruction::LdHfVx { x } => fx(x, 0x30),
+            Instruction::LdBVx { x } => fx(x, 0x33),
+            Instruction::LdIVx { x } => fx(x, 0x55),
+            Instruction::LdVxI { x } => fx(x, 0x65),
+            Instruction::LdRVx { x } => fx(x, 0x75),
+            Instruction::LdVxR { x } => fx(x, 0x85),
+        }
+    }
+
+    pub fn size_in_bytes(&self) -> usize {
+        match self {
+            Instruction::LdILong => 4,
+            _ => 2,
+        }
+    }
+
+    pub fn is_supported_by(&self, platform: Platform) -> bool {
+        match self {
+            Instruction::ScuNibble { .. }
+            | Instruction::LdIVxVy { .. }
+            | Instruction::LdVxVyI { .. }
+            | Instruction::LdILong
+            | Instruction::PlaneN { .. } => platform.supports_xo_chip(),
+            Instruction::ScdNibble { .. }
+            | Instruction::Scr
+            | Instruction::Scl
+            | Instruction::Exit
+            | Instruction::Low
+            | Instruction::High
+            | Instruction::LdHfVx { .. }
+            | Instruction::LdRVx { .. }
+            | Instruction::LdVxR { .. } => platform.supports_super_chip(),
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_encode_round_trip() {
+        for opcode in 0..=u16::MAX {
+            if let Ok(instruction) = Instruction::decode(opcode) {
+                assert_eq!(instruction.encode(), opcode, "{:?} did not round-trip", instruction);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_operands() {
+        assert_eq!(Instruction::decode(0xD12F), Ok(Instruction::DrwVxVyNibble { x: 1, y: 2, n: 0xF }));
+        assert_eq!(Instruction::decode(0x6ABC), Ok(Instruction::LdVxByte { x: 0xA, kk: 0xBC }));
+        assert_eq!(Instruction::decode(0xB123), Ok(Instruction::JpV0Addr { nnn: 0x123 }));
+        assert_eq!(Instruction::decode(0xF301), Ok(Instruction::PlaneN { n: 3 }));
+    }
+
+    #[test]
+    fn test_decode_rejects_unknown_variants() {
+        // 5XY1 used to be executed as 5XY0
+        assert_eq!(Instruction::decode(0x5121), Err(DecodeError { opcode: 0x5121 }));
+        assert_eq!(Instruction::decode(0x9121), Err(DecodeError { opcode: 0x9121 }));
+        assert_eq!(Instruction::decode(0x812F), Err(DecodeError { opcode: 0x812F }));
+        assert_eq!(Instruction::decode(0xE1FF), Err(DecodeError { opcode: 0xE1FF }));
+        assert_eq!(Instruction::decode(0xF1FF), Err(DecodeError { opcode: 0xF1FF }));
+    }
+
+    #[test]
+    fn test_platform_support() {
+        assert!(!Instruction::High.is_supported_by(Platform::Chip8));
+        assert!(Instruction::High.is_supported_by(Platform::SuperChip));
+        assert!(!Instruction::LdILong.is_supported_by(Platform::SuperChip));
+        assert!(Instruction::LdILong.is_supported_by(Platform::XoChip));
+    }
+}
@@ -0,0 +1,242 @@
+//! Just enough of a JSON reader for the ROM database, which keeps the core dependency free.
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = Parser { text: text.as_bytes(), position: 0 };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.position != parser.text.len() {
+            return Err(parser.error("unexpected data after the document"));
+        }
+        Ok(value)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> &[Json] {
+        match self {
+            Json::Array(items) => items,
+            _ => &[],
+        }
+    }
+
+    pub fn as_object(&self) -> &[(String, Json)] {
+        match self {
+            Json::Object(members) => members,
+            _ => &[],
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> String {
+        format!("{} at byte {}", message, self.position)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.text.get(self.position).is_some_and(|byte| byte.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.text.get(self.position) != Some(&byte) {
+            return Err(self.error(&format!("expected '{}'", byte as char)));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    // Consumes `byte` if it comes next.
+    fn eat(&mut self, byte: u8) -> bool {
+        self.skip_whitespace();
+        let found = self.text.get(self.position) == Some(&byte);
+        self.position += found as usize;
+        found
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.text.get(self.position) {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b't') => self.literal("true", Json::Bool(true)),
+            Some(b'f') => self.literal("false", Json::Bool(false)),
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        if !self.text[self.position..].starts_with(word.as_bytes()) {
+            return Err(self.error("expected a value"));
+        }
+        self.position += word.len();
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.position;
+        while self.text.get(self.position).is_some_and(|byte| matches!(byte, b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) {
+            self.position += 1;
+        }
+        let text = std::str::from_utf8(&self.text[start..self.position]).expect("digits are ASCII");
+        text.parse().map(Json::Number).map_err(|_| self.error("invalid number"))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+
+        loop {
+            let byte = *self.text.get(self.position).ok_or_else(|| self.error("unterminated string"))?;
+            self.position += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escape = *self.text.get(self.position).ok_or_else(|| self.error("unterminated string"))?;
+                    self.position += 1;
+                    let character = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{C}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    bytes.extend_from_slice(character.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                _ => bytes.push(byte),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.error("string is not UTF-8"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits = self.text.get(self.position..self.position + 4).ok_or_else(|| self.error("truncated \\u escape"))?;
+        let value = std::str::from_utf8(digits).ok().and_then(|digits| u32::from_str_radix(digits, 16).ok());
+        self.position += 4;
+        value.ok_or_else(|| self.error("invalid \\u escape"))
+    }
+
+    // \uXXXX, where characters outside the BMP come as a surrogate pair
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.text[self.position..].starts_with(b"\\u") {
+                return Err(self.error("unpaired surrogate"));
+            }
+            self.position += 2;
+            let low = self.hex4()?;
+            0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid \\u escape"))
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        if self.eat(b']') {
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            if self.eat(b']') {
+                return Ok(Json::Array(items));
+            }
+            self.expect(b',')?;
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect(b'{')?;
+        let mut members = Vec::new();
+        if self.eat(b'}') {
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let name = self.string()?;
+            self.expect(b':')?;
+            members.push((name, self.value()?));
+            if self.eat(b'}') {
+                return Ok(Json::Object(members));
+            }
+            self.expect(b',')?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nested_document() {
+        let json = Json::parse(r#" { "title": "Pong \u00e9\ud83d\ude00", "tickrate": 15, "keys": [1, -2.5e1], "ok": true, "x": null } "#).unwrap();
+
+        assert_eq!(json.get("title").and_then(Json::as_str), Some("Pong é😀"));
+        assert_eq!(json.get("tickrate").and_then(Json::as_f64), Some(15.0));
+        assert_eq!(json.get("keys").unwrap().as_array(), &[Json::Number(1.0), Json::Number(-25.0)]);
+        assert_eq!(json.get("ok").and_then(Json::as_bool), Some(true));
+        assert_eq!(json.get("x"), Some(&Json::Null));
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_documents() {
+        assert!(Json::parse(r#"{"a": 1,}"#).is_err());
+        assert!(Json::parse(r#"["unterminated]"#).is_err());
+        assert!(Json::parse("[1] 2").is_err());
+        assert!(Json::parse("").is_err());
+    }
+}
@@ -1,37 +1,61 @@
-use fastrand;
+mod catalog;
+mod cheats;
+mod coverage;
+mod debugger;
+mod disassembler;
+mod display;
+mod error;
+mod inspect;
+mod instruction;
+mod json;
+mod movie;
+mod platform;
+mod profiler;
+mod quirks;
+mod random;
+mod rewind;
+mod rom_database;
+mod save_state;
+mod sha1;
+mod trace;
+
+use cheats::RamSearch;
+use debugger::Debugger;
+use movie::MovieMode;
+
+pub use catalog::{find_rom, find_rom_by_sha1, RomInfo, CATALOG};
+pub use cheats::{Cheat, CheatList, SearchFilter};
+pub use coverage::CoverageMap;
+pub use debugger::{BreakReason, BreakReport, MemoryWatchpoint, WatchedRegister};
+pub use disassembler::{disassemble, disassemble_with_coverage, DisassembledLine, Syntax};
+pub use display::{DirtyRect, Display};
+pub use error::Chip8Error;
+pub use inspect::Timer;
+pub use instruction::{DecodeError, Instruction};
+pub use movie::{KeyEvent, Movie, MOVIE_VERSION};
+pub use platform::Platform;
+pub use profiler::{Profiler, SubroutineProfile};
+pub use quirks::{MemoryIncrement, Quirks};
+pub use random::RandomSource;
+pub use rewind::RewindBuffer;
+pub use rom_database::{RomDatabase, RomSettings};
+pub use save_state::SAVE_STATE_VERSION;
+pub use sha1::{sha1, to_hex};
+pub use trace::{ExecutionTrace, RegisterChange, TraceEntry};
 
 pub const DISPLAY_WIDTH: u8 = 64;
 pub const DISPLAY_HEIGHT: u8 = 32;
+pub const HIGH_RES_DISPLAY_WIDTH: u8 = 128;
+pub const HIGH_RES_DISPLAY_HEIGHT: u8 = 64;
 const FONT_START_ADDRESS: u8 = 0;
 const FONT_CHAR_SIZE_IN_BYTES: u8 = 5;
+const BIG_FONT_START_ADDRESS: u8 = 0x50;
+const BIG_FONT_CHAR_SIZE_IN_BYTES: u8 = 10;
 const TIMER_DECREMENT_FEQUENCY: u8 = 60;
-
-const OCTAJAM_TITLE: &[u8] = include_bytes!("./roms/octojam1title.ch8");
-const PUZZLE_15: &[u8] = include_bytes!("./roms/15PUZZLE");
-const INVADERS: &[u8] = include_bytes!("./roms/INVADERS");
-const GUESS: &[u8] = include_bytes!("./roms/GUESS");
-const PONG: &[u8] = include_bytes!("./roms/PONG");
-const PONG2: &[u8] = include_bytes!("./roms/PONG2");
-const TANK: &[u8] = include_bytes!("./roms/TANK");
-
-const RED_OCTOBER: &[u8] = include_bytes!("./roms/redOctober.ch8");
-const BLINKY: &[u8] = include_bytes!("./roms/BLINKY");
-const BLITZ: &[u8] = include_bytes!("./roms/BLITZ");
-const BRIX: &[u8] = include_bytes!("./roms/BRIX");
-const CONNECT: &[u8] = include_bytes!("./roms/CONNECT4");
-const HIDDEN: &[u8] = include_bytes!("./roms/HIDDEN");
-const KALEID: &[u8] = include_bytes!("./roms/KALEID");
-const MAZE: &[u8] = include_bytes!("./roms/MAZE");
-const MERLIN: &[u8] = include_bytes!("./roms/MERLIN");
-const MISSILE: &[u8] = include_bytes!("./roms/MISSILE");
-const PUZZLE: &[u8] = include_bytes!("./roms/PUZZLE");
-const SYZYGY: &[u8] = include_bytes!("./roms/SYZYGY");
-const TETRIS: &[u8] = include_bytes!("./roms/TETRIS");
-const TICTAC: &[u8] = include_bytes!("./roms/TICTAC");
-const UFO: &[u8] = include_bytes!("./roms/UFO");
-const VBRIX: &[u8] = include_bytes!("./roms/VBRIX");
-const VERS: &[u8] = include_bytes!("./roms/VERS");
-const WIPEOFF: &[u8] = include_bytes!("./roms/WIPEOFF");
+// Roughly 700 instructions per second, the speed the web frontend always ran at
+const DEFAULT_INSTRUCTIONS_PER_FRAME: u32 = 11;
+// Longer gaps (e.g. a backgrounded tab) are dropped instead of being caught up
+const MAX_FRAMES_PER_RUN: u32 = 15;
 
 pub struct Chip8 { registers: [u8; 16],
     i_register: u16,
@@ -39,13 +63,47 @@ pub struct Chip8 { registers: [u8; 16],
     delay_timer: u8,
     accumulator_timer: f64,
     position_in_memory: usize,
-    memory: [u8; 4096],
+    memory: Vec<u8>,
     stack: [u16; 16],
     stack_pointer: usize,
-    display: [[u8; DISPLAY_WIDTH as usize]; DISPLAY_HEIGHT as usize],
-    keyboard: [bool; 16]
+    display: Display,
+    keyboard: [bool; 16],
+    quirks: Quirks,
+    vertical_blank: bool,
+    platform: Platform,
+    high_resolution: bool,
+    rpl_flags: [u8; 16],
+    halted: bool,
+    selected_planes: u8,
+    instructions_per_frame: u32,
+    random: RandomSource,
+    rewind_buffer: RewindBuffer,
+    rewinding: bool,
+    rom: Vec<u8>,
+    movie: MovieMode,
+    rom_database: RomDatabase,
+    detected_rom: Option<RomSettings>,
+    debugger: Debugger,
+    trace: ExecutionTrace,
+    ram_search: Option<RamSearch>,
+    cheats: Vec<Cheat>,
+    profiler: Option<Profiler>,
+    coverage: CoverageMap,
+    // Instructions already run in the current frame, non-zero when a breakpoint stopped it midway
+    frame_instructions: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameReport {
+    pub instructions_executed: u32,
+    pub frames_completed: u32,
+    pub sound_active: bool,
+    pub last_opcode: u16,
+    /// A breakpoint or watchpoint stopped the frame early, see `last_break`.
+    pub break_hit: bool,
 }
 
+#[allow(non_snake_case)]
 pub struct RegistersSnapshot {
     pub V0: u8,
     pub V1: u8,
@@ -70,6 +128,12 @@ pub struct RegistersSnapshot {
     pub stack_pointer: u8,
 }
 
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Chip8 {
 
     pub fn new() -> Self {
@@ -79,16 +143,39 @@ impl Chip8 {
             sound_timer: 0, 
             accumulator_timer: 0.0,
             delay_timer: 0,
-            memory: [0; 4096],
+            memory: vec![0; Platform::Chip8.memory_size()],
             position_in_memory: 0,
             stack: [0; 16],
             stack_pointer: 0,
-            display: [[0; DISPLAY_WIDTH as usize]; DISPLAY_HEIGHT as usize],
-            keyboard: [false; 16]
+            display: Display::new(DISPLAY_WIDTH as usize, DISPLAY_HEIGHT as usize),
+            keyboard: [false; 16],
+            quirks: Quirks::default(),
+            vertical_blank: false,
+            platform: Platform::Chip8,
+            high_resolution: false,
+            rpl_flags: [0; 16],
+            halted: false,
+            selected_planes: 1,
+            instructions_per_frame: DEFAULT_INSTRUCTIONS_PER_FRAME,
+            random: RandomSource::default(),
+            rewind_buffer: RewindBuffer::new(0),
+            rewinding: false,
+            rom: Vec::new(),
+            movie: MovieMode::Idle,
+            rom_database: RomDatabase::default(),
+            detected_rom: None,
+            debugger: Debugger::default(),
+            trace: ExecutionTrace::new(0),
+            ram_search: None,
+            cheats: Vec::new(),
+            profiler: None,
+            coverage: CoverageMap::new(Platform::Chip8.memory_size()),
+            frame_instructions: 0,
         };
 
         chip8.fill_reserved_memory();
-        chip8.load_rom_in_memory("octajam_title".to_string());
+        chip8.load_rom_in_memory("octajam_title".to_string())
+            .expect("the default ROM is embedded and fits into memory");
         chip8
     }
 
@@ -131,10 +218,107 @@ impl Chip8 {
         for (i, &byte) in fontset.iter().enumerate() {
           self.memory[i] = byte;
         }
+
+        // SUPER-CHIP 8x10 font, extended with A-F as in Octo
+        let big_fontset: [u8; 160] = [
+            0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+            0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+            0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+            0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+            0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+            0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+            0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+            0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+            0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+            0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+            0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+            0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+            0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+            0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+            0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+            0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+        ];
+
+        let start = BIG_FONT_START_ADDRESS as usize;
+        self.memory[start..start + big_fontset.len()].copy_from_slice(&big_fontset);
+    }
+
+    // Power cycles the machine with the current ROM, keeping platform, quirks, speed and RNG source.
+    pub fn reset(&mut self) {
+        self.registers = [0; 16];
+        self.i_register = 0;
+        self.sound_timer = 0;
+        self.delay_timer = 0;
+        self.accumulator_timer = 0.0;
+        self.memory = vec![0; self.platform.memory_size()];
+        self.fill_reserved_memory();
+        // A platform with less memory may have been chosen after loading, it gets what fits
+        let length = self.rom.len().min(self.memory.len() - 0x200);
+        self.memory[0x200..0x200 + length].copy_from_slice(&self.rom[..length]);
+        self.position_in_memory = 0x200;
+        self.stack = [0; 16];
+        self.stack_pointer = 0;
+        self.keyboard = [false; 16];
+        self.vertical_blank = false;
+        self.rpl_flags = [0; 16];
+        self.halted = false;
+        self.selected_planes = 1;
+        self.set_high_resolution(false);
+        self.rewind_buffer.clear();
+        self.movie = MovieMode::Idle;
+        self.frame_instructions = 0;
+        self.trace.clear();
+        self.reset_profiler();
+        self.coverage.clear();
+        self.coverage.resize(self.memory.len());
+    }
+
+    pub fn display(&self) -> &Display {
+        &self.display
     }
 
     pub fn get_display(&self) -> *const u8 {
-        self.display.as_ptr() as *const u8 
+        self.display.as_ptr()
+    }
+
+    /// Whether the display changed since it was last presented.
+    pub fn is_display_dirty(&self) -> bool {
+        self.display.is_dirty()
+    }
+
+    pub fn get_dirty_regions(&self) -> &[DirtyRect] {
+        self.display.dirty_regions()
+    }
+
+    // Called by renderers and recorders once they showed or stored the display.
+    pub fn present_display(&mut self) {
+        self.display.present();
+    }
+
+    pub fn get_display_width(&self) -> usize {
+        self.display.width()
+    }
+
+    pub fn get_display_height(&self) -> usize {
+        self.display.height()
+    }
+
+    pub fn get_platform(&self) -> Platform {
+        self.platform
+    }
+
+    // Selecting a platform also applies its quirks, resizes memory and starts in low resolution.
+    pub fn set_platform(&mut self, platform: Platform) {
+        self.platform = platform;
+        self.quirks = platform.default_quirks();
+        self.memory.resize(platform.memory_size(), 0);
+        self.coverage.resize(platform.memory_size());
+        self.selected_planes = 1;
+        self.set_high_resolution(false);
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
     }
 
     pub fn get_register_snapshot(&self) -> RegistersSnapshot {
@@ -163,222 +347,496 @@ impl Chip8 {
         }
     }
 
-    pub fn set_key(&mut self, key: u8, is_pressed: bool)
+    // While a movie plays back it owns the keypad and user input is ignored.
+    pub fn set_key(&mut self, key: u8, is_pressed: bool) -> Result<(), Chip8Error>
     {
+        let was_pressed = *self.keyboard.get(key as usize).ok_or(Chip8Error::InvalidKey(key))?;
+        if self.is_playing_movie() || was_pressed == is_pressed {
+            return Ok(());
+        }
+
         self.keyboard[key as usize] = is_pressed;
+        self.record_key(key, is_pressed);
+        Ok(())
     } 
 
-    pub fn execute_step(&mut self) -> u16 {
-        
-        let  opcode = self.read_opcode();
+    pub fn get_quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    // Two machines with the same seed, ROM and inputs produce the same CXKK results.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.random.set_seed(seed);
+    }
+
+    pub fn set_random_source(&mut self, random: RandomSource) {
+        self.random = random;
+    }
+
+    // On error the program counter is left on the faulting instruction.
+    pub fn execute_step(&mut self) -> Result<u16, Chip8Error> {
+        let address = self.position_in_memory;
+        let result = self.execute_opcode();
+
+        if result.is_err() {
+            self.position_in_memory = address;
+        }
+        result
+    }
+
+    fn execute_opcode(&mut self) -> Result<u16, Chip8Error> {
+        let address = self.position_in_memory as u16;
+        let opcode = self.read_opcode()?;
+        let instruction = match Instruction::decode(opcode) {
+            Ok(instruction) if instruction.is_supported_by(self.platform) => instruction,
+            // Extension opcodes in the 0NNN range are plain machine code calls on older platforms
+            Ok(_) if opcode < 0x1000 => Instruction::Sys { nnn: opcode },
+            _ => return Err(Chip8Error::UnknownOpcode { opcode, address }),
+        };
+
+        let (registers, i_register) = (self.registers, self.i_register);
         self.position_in_memory += 2;
+        if let Err(error) = self.execute_instruction(instruction) {
+            // The faulting instruction ends the trace, without the registers it may have half changed
+            self.record_trace(address, opcode, instruction, self.registers, self.i_register);
+            return Err(error);
+        }
+        self.record_trace(address, opcode, instruction, registers, i_register);
+        self.record_profile(address, instruction);
+        self.coverage.mark_instruction(address as usize, instruction.size_in_bytes());
+        Ok(opcode)
+    }
+
+    fn execute_instruction(&mut self, instruction: Instruction) -> Result<(), Chip8Error> {
+        match instruction {
+            Instruction::Sys { .. } => {}, // Jump to machine code routine at nnn
+            Instruction::Cls => self.cls(), // Clear the display
+            Instruction::Ret => self.ret()?, // Return from a subroutine
+            Instruction::ScdNibble { n } => self.scd_nibble(n), // Scroll down n lines
+            Instruction::ScuNibble { n } => self.scu_nibble(n), // Scroll up n lines
+            Instruction::Scr => self.scr(), // Scroll right 4 pixels
+            Instruction::Scl => self.scl(), // Scroll left 4 pixels
+            Instruction::Exit => self.exit(), // Exit the interpreter
+            Instruction::Low => self.set_high_resolution(false), // Low resolution mode
+            Instruction::High => self.set_high_resolution(true), // High resolution mode
+            Instruction::JpAddr { nnn } => self.jp_addr(nnn), // Jump to location nnn
+            Instruction::CallAddr { nnn } => self.call_addr(nnn)?, // Call subroutine at nnn
+            Instruction::SeVxByte { x, kk } => self.se_vx_byte(x, kk), // Skip next instruction if Vx == kk
+            Instruction::SneVxByte { x, kk } => self.sne_vx_byte(x, kk), // Skip next instruction if Vx != kk
+            Instruction::SeVxVy { x, y } => self.se_vx_vy(x, y), // Skip next instruction if Vx == Vy
+            Instruction::LdIVxVy { x, y } => self.ld_i_vx_vy(x, y)?, // Store Vx..Vy at I
+            Instruction::LdVxVyI { x, y } => self.ld_vx_vy_i(x, y)?, // Load Vx..Vy from I
+            Instruction::LdVxByte { x, kk } => self.ld_vx_byte(x, kk), // Set Vx = kk
+            Instruction::AddVxByte { x, kk } => self.add_vx_byte(x, kk), // Set Vx = Vx + kk
+            Instruction::LdVxVy { x, y } => self.ld_vx_vy(x, y),
+            Instruction::OrVxVy { x, y } => self.or_vx_vy(x, y),
+            Instruction::AndVxVy { x, y } => self.and_vx_vy(x, y),
+            Instruction::XorVxVy { x, y } => self.xor_vx_vy(x, y),
+            Instruction::AddVxVy { x, y } => self.add_vx_vy(x, y),
+            Instruction::SubVxVy { x, y } => self.sub_vx_vy(x, y),
+            Instruction::ShrVxVy { x, y } => self.shr_vx_vy(x, y),
+            Instruction::SubnVxVy { x, y } => self.subn_vx_vy(x, y),
+            Instruction::ShlVxVy { x, y } => self.shl_vx_vy(x, y),
+            Instruction::SneVxVy { x, y } => self.sne_vx_vy(x, y),
+            Instruction::LdIAddr { nnn } => self.ld_i_addr(nnn),
+            Instruction::JpV0Addr { nnn } => self.jp_v0_addr(nnn),
+            Instruction::RndVxByte { x, kk } => self.rnd_vx_byte(x, kk),
+            Instruction::DrwVxVyNibble { x, y, n } => self.drw_vx_vy_nibble(x, y, n)?,
+            Instruction::SkpVx { x } => self.skp_vx(x),
+            Instruction::SknpVx { x } => self.sknp_vx(x),
+            Instruction::LdILong => self.ld_i_long()?,
+            Instruction::PlaneN { n } => self.plane_n(n),
+            Instruction::LdVxDt { x } => self.ld_vx_dt(x),
+            Instruction::LdVxK { x } => self.ld_vx_k(x),
+            Instruction::LdDtVx { x } => self.ld_dt_vx(x),
+            Instruction::LdStVx { x } => self.ld_st_vx(x),
+            Instruction::AddIVx { x } => self.add_i_vx(x),
+            Instruction::LdFVx { x } => self.ld_f_vx(x),
+            Instruction::LdHfVx { x } => self.ld_hf_vx(x),
+            Instruction::LdBVx { x } => self.ld_b_vx(x)?,
+            Instruction::LdIVx { x } => self.ld_i_vx(x)?,
+            Instruction::LdVxI { x } => self.ld_vx_i(x)?,
+            Instruction::LdRVx { x } => self.ld_r_vx(x),
+            Instruction::LdVxR { x } => self.ld_vx_r(x),
+        }
+        Ok(())
+    }
 
-        match opcode {
-            0x00E0 => { self.cls(); } // Clear the display
-            0x00EE => { self.ret(); }, // Return from a subroutine
-            0x0000..=0x0FFF => {}, // Jump to machine code routine at nnn
-            0x1000..=0x1FFF => { self.jp_addr(opcode); }, // Jump to location nnn
-            0x2000..=0x2FFF => { self.call_addr(opcode); }, // Call subroutine at nnn
-            0x3000..=0x4FFF => { self.se_and_sne_vx_byte(opcode)} // Skip next instruction if Vx == kk
-            0x5000..=0x5FF0 => { self.se_vx_vy(opcode) }, // Skip next instruction if Vx == Vy
-            0x6000..=0x6FFF => { self.ld_vx_byte(opcode) }, // Set Vx = kk
-            0x7000..=0x7FFF => { self.add_vx_byte(opcode) }, // set Vx = Vx + kk
-            0x8000..=0x8FFF => {
-                let x = ((opcode & 0x0F00) >> 8) as u8;
-                let y = ((opcode & 0x00F0) >> 4) as u8;
-                let operation_type = (opcode & 0x000F) as u8;
-
-                match (operation_type)
-                {
-                    0x0 => self.ld_vx_vy(x, y),
-                    0x1 => self.or_vx_vy(x, y),
-                    0x2 => self.and_vx_vy(x,y),
-                    0x3 => self.xor_vx_vy(x,y),
-                    0x4 => self.add_vx_vy(x,y),
-                    0x5 => self.sub_vx_vy(x,y),
-                    0x6 => self.shr_vx_vy(x,y),
-                    0x7 => self.subn_vx_vy(x, y),
-                    0xE => self.shl_vx_vy(x, y),
-                    _ => todo!("opcode {:04x}", opcode),
-                }
+    pub fn get_instructions_per_frame(&self) -> u32 {
+        self.instructions_per_frame
+    }
+
+    pub fn set_instructions_per_frame(&mut self, instructions: u32) {
+        self.instructions_per_frame = instructions.max(1);
+    }
+
+    pub fn set_cpu_frequency(&mut self, hz: u32) {
+        let instructions = (hz as f64 / TIMER_DECREMENT_FEQUENCY as f64).round() as u32;
+        self.set_instructions_per_frame(instructions);
+    }
+
+    // Runs as many whole 60 Hz frames as fit into the elapsed time, the remainder is carried over.
+    pub fn run_for(&mut self, elapsed_ms: f64) -> Result<FrameReport, Chip8Error> {
+        let frame_duration_ms = Chip8::frame_duration_ms();
+        self.accumulator_timer += elapsed_ms;
+
+        let mut report = FrameReport { sound_active: self.is_sound_active(), ..FrameReport::default() };
+
+        while self.accumulator_timer >= frame_duration_ms {
+            self.accumulator_timer -= frame_duration_ms;
+
+            if report.frames_completed == MAX_FRAMES_PER_RUN {
+                self.accumulator_timer %= frame_duration_ms;
+                break;
             }
-            0x9000..=0x9FFF => { self.sne_vx_vy(opcode); }
-            0xA000..=0xAFFF => { self.ld_i_addr(opcode); }
-            0xB000..=0xBFFF => { self.jp_v0_addr(opcode); }
-            0xC000..=0xCFFF => { self.rnd_vx_byte(opcode); }
-            0xD000..=0xDFFF => { self.drw_vx_vy_nibble(opcode); }
-            0xE000..=0xEFFF => { 
-                let operation_type = (opcode & 0x00FF)  as u16;
-                
-                match (operation_type)
-                {
-                    0x9E => self.skp_vx(opcode),
-                    0xA1 => self.sknp_vx(opcode),
-                    _ => todo!("opcode {:04x}", opcode)
-                }
+
+            let frame = self.run_frame()?;
+            report.instructions_executed += frame.instructions_executed;
+            report.frames_completed += frame.frames_completed;
+            report.sound_active = frame.sound_active;
+            if frame.instructions_executed > 0 {
+                report.last_opcode = frame.last_opcode;
             }
-            0xF000..=0xFFFF => { 
-                let operation_type = (opcode & 0x00FF) as u16;
-                
-                match (operation_type)
-                {
-                    0x07 => self.ld_vx_dt(opcode),
-                    0x0A => self.ld_vx_k(opcode),
-                    0x15 => self.ld_dt_vx(opcode),
-                    0x18 => self.ld_st_vx(opcode),
-                    0x1E => self.add_i_vx(opcode),
-                    0x29 => self.ld_f_vx(opcode),
-                    0x33 => self.ld_b_vx(opcode),
-                    0x55 => self.ld_i_fx(opcode),
-                    0x65 => self.ld_vx_i(opcode),
-                    _ => todo!("opcode {:04x}", opcode)
-                }
+
+            // Time spent stopped at a break isn't caught up afterwards
+            if frame.break_hit {
+                report.break_hit = true;
+                self.accumulator_timer = 0.0;
+                break;
+            }
+        }
+
+        Ok(report)
+    }
+
+    pub fn run_frame(&mut self) -> Result<FrameReport, Chip8Error> {
+        let mut report = FrameReport::default();
+
+        // Holding rewind replaces the frame with stepping one frame back in time
+        if self.rewinding {
+            self.rewind(1);
+            report.frames_completed = 1;
+            return Ok(report);
+        }
+
+        if self.frame_instructions == 0 {
+            self.apply_movie_inputs();
+        }
+
+        while self.frame_instructions < self.instructions_per_frame {
+            if self.halted {
+                break;
             }
-             _ => todo!("opcode {:04x}", opcode),
+
+            let address = self.position_in_memory as u16;
+            let hit = if self.breakpoint_hit() {
+                Some(BreakReason::Breakpoint)
+            } else {
+                let (opcode, hit) = self.execute_watched_step()?;
+                report.last_opcode = opcode;
+                report.instructions_executed += 1;
+                self.frame_instructions += 1;
+                hit
+            };
+
+            if let Some(reason) = hit {
+                self.stop(reason, address, report.instructions_executed as u64);
+                report.break_hit = true;
+                report.sound_active = self.is_sound_active();
+                return Ok(report);
+            }
+        }
+
+        self.finish_frame();
+        report.frames_completed = 1;
+        report.sound_active = self.is_sound_active();
+        Ok(report)
+    }
+
+    // Ticks the timers and does the rest of the frame's bookkeeping, for callers that step
+    // instructions themselves.
+    pub fn finish_frame(&mut self) {
+        self.frame_instructions = 0;
+        self.tick_timers();
+        self.apply_cheats();
+        self.end_profiled_frame();
+        self.advance_movie();
+        if self.rewind_buffer.capacity() > 0 {
+            self.rewind_buffer.push(self.save_state());
+        }
+    }
+
+    // Every completed frame is recorded, a depth of 0 seconds disables recording.
+    pub fn set_rewind_depth(&mut self, seconds: f64) {
+        let frames = (seconds * TIMER_DECREMENT_FEQUENCY as f64).round() as usize;
+        self.rewind_buffer.set_capacity(frames);
+    }
+
+    pub fn get_rewind_frames(&self) -> usize {
+        self.rewind_buffer.len()
+    }
+
+    // Returns the number of frames actually stepped back. Keys currently held stay pressed.
+    // Movies can't be rewound, so nothing happens while one is recorded or played back.
+    pub fn rewind(&mut self, frames: usize) -> usize {
+        if !matches!(self.movie, MovieMode::Idle) {
+            return 0;
         }
-        opcode
+
+        let (rewound, state) = self.rewind_buffer.rewind(frames);
+        let Some(state) = state.filter(|_| rewound > 0).map(<[u8]>::to_vec) else {
+            return 0;
+        };
+
+        // Held keys and the time budget of `run_for` belong to the present, not the rewound frame
+        let keyboard = self.keyboard;
+        let accumulator_timer = self.accumulator_timer;
+        self.load_state(&state).expect("rewind frames are written by save_state");
+        self.keyboard = keyboard;
+        self.accumulator_timer = accumulator_timer;
+        rewound
+    }
+
+    pub fn set_rewinding(&mut self, active: bool) {
+        self.rewinding = active;
     }
 
-    pub fn update_timers(self: &mut Self, elapsed_ms: u16)
+    pub fn is_rewinding(&self) -> bool {
+        self.rewinding
+    }
+
+    pub fn update_timers(&mut self, elapsed_ms: u16)
     {
         self.accumulator_timer += elapsed_ms as f64;
         
-        let duration_ms_between_decrements = 1000.0 / TIMER_DECREMENT_FEQUENCY as f64;
+        let duration_ms_between_decrements = Chip8::frame_duration_ms();
         
-        if (self.accumulator_timer > duration_ms_between_decrements)
+        while self.accumulator_timer >= duration_ms_between_decrements
         {
-            self.delay_timer = self.delay_timer.saturating_sub(1);
-            self.sound_timer = self.sound_timer.saturating_sub(1);
+            self.accumulator_timer -= duration_ms_between_decrements;
+            self.tick_timers();
         }
     }
 
-    pub fn is_sound_active(self: &Self) -> bool
+    fn tick_timers(&mut self) {
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+        self.vertical_blank = true;
+    }
+
+    fn frame_duration_ms() -> f64 {
+        1000.0 / TIMER_DECREMENT_FEQUENCY as f64
+    }
+
+    pub fn is_sound_active(&self) -> bool
     {
         self.sound_timer > 0
     }
 
-    pub fn load_rom_in_memory(self: &mut Self, name: String)
+    pub fn load_rom_in_memory(&mut self, name: String) -> Result<(), Chip8Error>
     {
-        let rom = Chip8::get_rom(name.as_str())
-            .ok_or_else( || format!("ROM not found: '{}'", name)).unwrap();
+        let rom = find_rom(&name).ok_or(Chip8Error::RomNotFound(name))?;
 
-        let start = 0x200;
-        let end = start + rom.len();
-        self.memory[start..end].copy_from_slice(rom);
-        
-        self.position_in_memory = 0x200;
+        self.load_rom_bytes(rom.data)
     }
 
-    fn get_rom(name: &str) -> Option<&[u8]> {
-        match name {
-            "octajam_title" => Some(OCTAJAM_TITLE),
-            "red_october" => Some(RED_OCTOBER),
-            "puzzle_15" => Some(PUZZLE_15),
-            "blinky" => Some(BLINKY),
-            "blitz" => Some(BLITZ),
-            "brix" => Some(BRIX),
-            "connect" => Some(CONNECT),
-            "guess" => Some(GUESS),
-            "hidden" => Some(HIDDEN),
-            "invaders" => Some(INVADERS),
-            "kaleid" => Some(KALEID),
-            "maze" => Some(MAZE),
-            "merlin" => Some(MERLIN),
-            "missile" => Some(MISSILE),
-            "pong" => Some(PONG),
-            "pong2" => Some(PONG2),
-            "puzzle" => Some(PUZZLE),
-            "syzygy" => Some(SYZYGY),
-            "tank" => Some(TANK),
-            "tetris" => Some(TETRIS),
-            "tictac" => Some(TICTAC),
-            "ufo" => Some(UFO),
-            "vbrix" => Some(VBRIX),
-            "vers" => Some(VERS),
-            "wipeoff" => Some(WIPEOFF),
-            _ => None,
+    /// Loads a ROM image at 0x200 and power-cycles the machine, so nothing of the previous ROM
+    /// survives in memory, registers or on the display.
+    ///
+    /// Platform, quirks and speed are set from the ROM database, or from the catalog for the
+    /// bundled ROMs. Unknown ROMs get the defaults, on XO-CHIP if they need its larger memory.
+    /// Change the settings after loading to override them.
+    pub fn load_rom_bytes(&mut self, rom: &[u8]) -> Result<(), Chip8Error>
+    {
+        let hash = sha1(rom);
+        let detected = self.rom_database.find(&hash).cloned()
+            .or_else(|| find_rom_by_sha1(&hash).map(RomSettings::from));
+
+        let (platform, quirks, speed) = match &detected {
+            Some(settings) => (settings.platform, settings.quirks, settings.speed),
+            None if rom.len() > Platform::Chip8.memory_size() - 0x200 => (Platform::XoChip, Quirks::XO_CHIP, None),
+            None => (Platform::Chip8, Quirks::default(), None),
+        };
+
+        let max_size = platform.memory_size() - 0x200;
+        if rom.len() > max_size {
+            return Err(Chip8Error::RomTooLarge { size: rom.len(), max_size });
         }
+
+        self.set_platform(platform);
+        self.quirks = quirks;
+        self.instructions_per_frame = speed.unwrap_or(DEFAULT_INSTRUCTIONS_PER_FRAME);
+        self.detected_rom = detected;
+        self.rom = rom.to_vec();
+        // Addresses found for the previous ROM mean nothing for this one
+        self.ram_search = None;
+        self.cheats.clear();
+        self.reset();
+        Ok(())
+    }
+
+    /// Replaces the database `load_rom_bytes` detects ROMs with. Affects the next load only.
+    pub fn set_rom_database(&mut self, database: RomDatabase) {
+        self.rom_database = database;
+    }
+
+    /// Database entry of the loaded ROM, `None` if it wasn't recognised.
+    pub fn detected_rom(&self) -> Option<&RomSettings> {
+        self.detected_rom.as_ref()
     }
 
-    fn read_opcode(self: &Self) -> u16 {
+    pub fn disassemble(&self, start: usize, end: usize, syntax: Syntax) -> Vec<DisassembledLine> {
+        disassembler::disassemble(&self.memory, start, end, self.position_in_memory, syntax)
+    }
+
+    // Listing with `context` instructions before and after the current one. The lines before stop
+    // at the PC so it always starts a line, the coverage keeps them in step with the instructions
+    // that ran, e.g. after a four byte `LD I, LONG` or data at an odd address.
+    pub fn disassemble_around_pc(&self, context: usize, syntax: Syntax) -> Vec<DisassembledLine> {
+        let pc = self.position_in_memory;
+        // Instructions are at most four bytes, the same parity as the PC suits the common case
+        // `context` comes straight from the frontends, a huge one just lists all of memory
+        let start = pc.saturating_sub(context.saturating_mul(4));
+        let end = pc.saturating_add(context.saturating_add(1).saturating_mul(4)).min(self.memory.len());
+
+        let before = self.disassemble_with_coverage(start, pc, syntax);
+        let after = self.disassemble_with_coverage(pc, end, syntax);
+        let skipped = before.len().saturating_sub(context);
+        before.into_iter().skip(skipped).chain(after.into_iter().take(context.saturating_add(1))).collect()
+    }
+
+    pub fn peek_opcode(&self) -> Result<u16, Chip8Error> {
+        self.read_opcode()
+    }
+
+    fn read_opcode(&self) -> Result<u16, Chip8Error> {
         let p = self.position_in_memory;
-        let op_byte1 = self.memory[p] as u16;
-        let op_byte2 = self.memory[p + 1] as u16;
+        let op_byte1 = self.read_memory(p)? as u16;
+        let op_byte2 = self.read_memory(p + 1)? as u16;
 
-        (op_byte1 << 8) | op_byte2
+        Ok((op_byte1 << 8) | op_byte2)
     }
 
-    fn cls(self: &mut Self) {
-        for row in self.display.iter_mut() {
-            for pixel in row.iter_mut()
-            {
-                *pixel = 0;
-            }
+    fn read_memory(&self, address: usize) -> Result<u8, Chip8Error> {
+        self.memory.get(address).copied().ok_or(Chip8Error::MemoryOutOfBounds { address })
+    }
+
+    // Data reads go through here so watchpoints and the coverage map see them, instruction fetches
+    // don't. `usage` is `CoverageMap::SPRITE` or `CoverageMap::READ`.
+    fn read_data(&mut self, address: usize, usage: u8) -> Result<u8, Chip8Error> {
+        let value = self.read_memory(address)?;
+        self.debugger.record_read(address, value);
+        self.coverage.mark(address, usage);
+        Ok(value)
+    }
+
+    fn write_memory(&mut self, address: usize, value: u8) -> Result<(), Chip8Error> {
+        let byte = self.memory.get_mut(address).ok_or(Chip8Error::MemoryOutOfBounds { address })?;
+        let old = std::mem::replace(byte, value);
+        self.debugger.record_write(address, old, value);
+        self.coverage.mark(address, CoverageMap::WRITTEN);
+        Ok(())
+    }
+
+    fn cls(&mut self) {
+        self.display.clear(self.selected_planes);
+    }
+
+    fn scd_nibble(&mut self, n: u8) {
+        self.display.scroll_down(n as usize, self.selected_planes);
+    }
+
+    fn scu_nibble(&mut self, n: u8) {
+        self.display.scroll_up(n as usize, self.selected_planes);
+    }
+
+    fn scr(&mut self) {
+        self.display.scroll_right(4, self.selected_planes);
+    }
+
+    fn scl(&mut self) {
+        self.display.scroll_left(4, self.selected_planes);
+    }
+
+    fn exit(&mut self) {
+        self.halted = true;
+        self.position_in_memory -= 2;
+    }
+
+    fn set_high_resolution(&mut self, enabled: bool) {
+        self.high_resolution = enabled;
+
+        if enabled {
+            self.display.resize(HIGH_RES_DISPLAY_WIDTH as usize, HIGH_RES_DISPLAY_HEIGHT as usize);
+        } else {
+            self.display.resize(DISPLAY_WIDTH as usize, DISPLAY_HEIGHT as usize);
         }
     }
 
-    fn ret(&mut self) {
+    fn ret(&mut self) -> Result<(), Chip8Error> {
         if self.stack_pointer == 0 {
-            panic!("Stack underflow");
+            return Err(Chip8Error::StackUnderflow { address: self.position_in_memory as u16 - 2 });
         }
 
         self.stack_pointer -= 1;
         self.position_in_memory = self.stack[self.stack_pointer] as usize;
+        Ok(())
     }
 
-    fn jp_addr(&mut self, opcode: u16) {
-        let addr = opcode & 0x0FFF;
-        self.position_in_memory = addr as usize;
+    fn jp_addr(&mut self, nnn: u16) {
+        self.position_in_memory = nnn as usize;
     }
 
-    fn call_addr(&mut self, opcode: u16) {
-        let addr = opcode & 0x0FFF;
-
+    fn call_addr(&mut self, nnn: u16) -> Result<(), Chip8Error> {
         let sp = self.stack_pointer;
         let stack = &mut self.stack;
         
         if sp >= stack.len() {
-            panic!("Stack overflow!")
+            return Err(Chip8Error::StackOverflow { address: self.position_in_memory as u16 - 2 });
         }
 
         stack[sp] = self.position_in_memory as u16;
         self.stack_pointer += 1;
-        self.position_in_memory = addr as usize;
+        self.position_in_memory = nnn as usize;
+        Ok(())
     }
 
 
-    fn se_and_sne_vx_byte(&mut self, opcode: u16) {
-        let should_be_equal = ((opcode & 0xF000) >> 12) == 0x3;
-
-        let x = ((opcode & 0x0F00) >> 8) as u8;
-        let kk = (opcode & 0x00FF) as u8;
+    fn se_vx_byte(&mut self, x: u8, kk: u8) {
+        if self.registers[x as usize] == kk {
+            self.skip_next_instruction();
+        }
+    }
 
-        let is_equal = self.registers[x as usize] == kk;
-        
-        if (should_be_equal == is_equal) {
-            self.position_in_memory += 2;
+    fn sne_vx_byte(&mut self, x: u8, kk: u8) {
+        if self.registers[x as usize] != kk {
+            self.skip_next_instruction();
         }
     }
 
-    fn se_vx_vy(&mut self, opcode: u16) {
-        let x = ((opcode & 0x0F00) >> 8) as u8;
-        let y = ((opcode & 0x00F0) >> 4) as u8;
+    fn se_vx_vy(&mut self, x: u8, y: u8) {
 
-        if (self.registers[x as usize] == self.registers[y as usize]) {
-            self.position_in_memory += 2;
+        if self.registers[x as usize] == self.registers[y as usize] {
+            self.skip_next_instruction();
         }
     }
 
-    fn ld_vx_byte(&mut self, opcode: u16) {
-        let kk = (opcode & 0x00FF) as u8;
-        let x = ((opcode & 0x0F00) >> 8) as u8;
+    // F000 NNNN is four bytes long, so skips have to step over its operand as well
+    fn skip_next_instruction(&mut self) {
+        let next_is_long_load = self.platform.supports_xo_chip() && self.read_opcode() == Ok(0xF000);
+        self.position_in_memory += if next_is_long_load { 4 } else { 2 };
+    }
+
+    fn ld_vx_byte(&mut self, x: u8, kk: u8) {
 
         self.registers[x as usize] = kk;
     }
 
-    fn add_vx_byte(&mut self, opcode: u16) {
-        let kk = (opcode & 0x00FF) as u8;
-        let x = ((opcode & 0x0F00) >> 8) as u8;
+    fn add_vx_byte(&mut self, x: u8, kk: u8) {
 
        self.registers[x as usize] = self.registers[x as usize].wrapping_add(kk); 
     }
@@ -390,14 +848,23 @@ impl Chip8 {
    
     fn or_vx_vy(&mut self, x: u8, y: u8) {
         self.registers[x as usize]  |= self.registers[y as usize];
+        self.reset_vf_if_quirk();
     }
     
     fn and_vx_vy(&mut self, x: u8, y: u8) {
         self.registers[x as usize]  &= self.registers[y as usize];
+        self.reset_vf_if_quirk();
     }
     
     fn xor_vx_vy(&mut self, x: u8, y: u8) {
         self.registers[x as usize]  ^= self.registers[y as usize];
+        self.reset_vf_if_quirk();
+    }
+
+    fn reset_vf_if_quirk(&mut self) {
+        if self.quirks.vf_reset {
+            self.registers[0xF] = 0;
+        }
     }
     
     fn add_vx_vy(&mut self, x: u8, y: u8) {
@@ -413,14 +880,15 @@ impl Chip8 {
     }
     
     fn shr_vx_vy(&mut self, x: u8, y: u8) {
-        let lsb = self.registers[x as usize] & 1;
-        self.registers[0xF] = lsb;
+        let source = if self.quirks.shift_uses_vy { y } else { x };
+        let value = self.registers[source as usize];
 
-        self.registers[x as usize] /= 2;
+        self.registers[x as usize] = value / 2;
+        self.registers[0xF] = value & 1;
     }
     
     fn subn_vx_vy(&mut self, x: u8, y: u8) {
-        if (self.registers[x as usize] < self.registers[y as usize])
+        if self.registers[x as usize] < self.registers[y as usize]
         {
             self.registers[0xF] = 1;
         }
@@ -433,100 +901,156 @@ impl Chip8 {
     }
     
     fn shl_vx_vy(&mut self, x: u8, y: u8) {
-        let msb = self.registers[x as usize] >> 7;
-        self.registers[0xF] = msb;
-        
-        self.registers[x as usize] = self.registers[x as usize].wrapping_mul(2);
+        let source = if self.quirks.shift_uses_vy { y } else { x };
+        let value = self.registers[source as usize];
+
+        self.registers[x as usize] = value.wrapping_mul(2);
+        self.registers[0xF] = value >> 7;
     }
 
-    fn sne_vx_vy(&mut self, opcode: u16) {
-        let x = ((opcode & 0x0F00) >> 8) as u8;
-        let y = ((opcode & 0x00F0) >> 4) as u8;
+    fn sne_vx_vy(&mut self, x: u8, y: u8) {
 
-        if (self.registers[x as usize] != self.registers[y as usize]) {
-            self.position_in_memory += 2;
+        if self.registers[x as usize] != self.registers[y as usize] {
+            self.skip_next_instruction();
         }
     }
 
-    fn ld_i_addr(&mut self, opcode: u16)
+    fn ld_i_addr(&mut self, nnn: u16)
     {
-        let addr = opcode & 0x0FFF;
-        self.i_register = addr;
+        self.i_register = nnn;
     }
 
-    fn jp_v0_addr(&mut self, opcode: u16)
+    fn jp_v0_addr(&mut self, nnn: u16)
     {
-        let addr = opcode & 0x0FFF;
-        self.position_in_memory = (addr + self.registers[0 as usize] as u16) as usize
+        let offset_register = if self.quirks.jump_uses_vx { nnn >> 8 } else { 0 };
+        self.position_in_memory = (nnn + self.registers[offset_register as usize] as u16) as usize
     }
 
-    fn rnd_vx_byte(&mut self, opcode: u16)
+    fn rnd_vx_byte(&mut self, x: u8, kk: u8)
     {
-        let x = ((opcode & 0x0F00) >> 8) as u8;
-        let kk = (opcode & 0x00FF) as u8;
-
-        let number: u8 = fastrand::u8(0..=255);
-        self.registers[x as usize] = (number & kk);
+        let number: u8 = self.random.next_byte();
+        self.registers[x as usize] = number & kk;
     }
 
-    fn drw_vx_vy_nibble(&mut self, opcode: u16)
+    fn drw_vx_vy_nibble(&mut self, x: u8, y: u8, n: u8) -> Result<(), Chip8Error>
     {
-        let x= ((opcode & 0x0F00) >> 8) as u8;
-        let y= ((opcode & 0x00F0) >> 4) as u8;
-        let n= (opcode & 0x000F) as u8;
-        
-        let x_pos = self.registers[x as usize];
-        let y_pos = self.registers[y as usize];
-        let mut is_switched_off = false;
         
-        for byte_row_index in 0..n {
-            let byte = self.memory[(self.i_register) as usize + byte_row_index as usize];
+        if self.quirks.display_wait {
+            if !self.vertical_blank {
+                self.position_in_memory -= 2;
+                return Ok(());
+            }
+            self.vertical_blank = false;
+        }
+
+        let x_pos = self.registers[x as usize] as usize % self.display.width();
+        let y_pos = self.registers[y as usize] as usize % self.display.height();
+
+        // DXY0 draws a 16x16 sprite on SUPER-CHIP
+        let (sprite_width, sprite_height) = if n == 0 && self.platform.supports_super_chip() {
+            (16, 16)
+        } else {
+            (8, n as usize)
+        };
+        let sprite_size = sprite_width / 8 * sprite_height;
+        let mut address = self.i_register as usize;
+        let mut collided_rows: u8 = 0;
+
+        // With several XO-CHIP planes selected, the sprite data for each plane follows the previous one
+        for plane in [1, 2] {
+            if self.selected_planes & plane == 0 {
+                continue;
+            }
+
+            collided_rows += self.draw_sprite_plane(plane, address, x_pos, y_pos, sprite_width, sprite_height)?;
+            address += sprite_size;
+        }
+
+        self.registers[0xF] = if self.high_resolution && self.platform == Platform::SuperChip {
+            collided_rows
+        } else if collided_rows > 0 {
+            1
+        } else {
+            0
+        };
+        Ok(())
+    }
+
+    fn draw_sprite_plane(&mut self, plane: u8, address: usize, x_pos: usize, y_pos: usize, sprite_width: usize, sprite_height: usize) -> Result<u8, Chip8Error>
+    {
+        let width = self.display.width();
+        let height = self.display.height();
+        let bytes_per_row = sprite_width / 8;
+        let mut collided_rows: u8 = 0;
+        // Changed pixels by whether they wrapped around the right and bottom edge, so a wrapping
+        // sprite dirties its pieces instead of everything between them
+        let mut changed: [Option<DirtyRect>; 4] = [None; 4];
+        
+        for byte_row_index in 0..sprite_height {
+            if self.quirks.clip_sprites && y_pos + byte_row_index >= height {
+                // SUPER-CHIP counts rows clipped at the bottom edge as collisions in high resolution
+                if self.high_resolution && self.platform == Platform::SuperChip {
+                    collided_rows += 1;
+                }
+                continue;
+            }
+
+            let pixel_y = (y_pos + byte_row_index) % height; 
+            let mut is_switched_off = false;
 
-            for bit_index in 0..8 {
-                let pixel_x = (x_pos + bit_index) % DISPLAY_WIDTH; 
-                let pixel_y = (y_pos + byte_row_index) % DISPLAY_HEIGHT; 
+            for bit_index in 0..sprite_width {
+                if self.quirks.clip_sprites && x_pos + bit_index >= width {
+                    continue;
+                }
+
+                let byte = self.read_data(address + byte_row_index * bytes_per_row + bit_index / 8, CoverageMap::SPRITE)?;
+                let pixel_x = (x_pos + bit_index) % width; 
                 
-                let bit_value = (byte >> (7 - bit_index)) & 1;
-                let old_pixel = self.display[pixel_y as usize][pixel_x as usize];
-                self.display[pixel_y as usize][pixel_x as usize] ^= bit_value; 
-            
-                if old_pixel == 1 && self.display[pixel_y as usize][pixel_x as usize] == 0
-                {
+                if (byte >> (7 - bit_index % 8)) & 1 == 0 {
+                    continue;
+                }
+
+                if self.display[pixel_y][pixel_x] & plane != 0 {
                    is_switched_off = true; 
                 }
+                self.display[pixel_y][pixel_x] ^= plane; 
+                DirtyRect::include(&mut changed[(pixel_x < x_pos) as usize | ((pixel_y < y_pos) as usize) << 1], pixel_x, pixel_y);
             } 
+
+            if is_switched_off {
+                collided_rows += 1;
+            }
         }
 
-        self.registers[0xF] = if is_switched_off { 1 } else { 0 };
+        for region in changed.into_iter().flatten() {
+            self.display.mark_dirty(region);
+        }
+        Ok(collided_rows)
     }
 
-    fn skp_vx(&mut self, opcode: u16)
+    fn skp_vx(&mut self, x: u8)
     {
-        let x = ((opcode & 0x0F00) >> 8) as u8;  
-        if self.keyboard[self.registers[x as usize] as usize]
+        if self.keyboard[(self.registers[x as usize] & 0x0F) as usize]
         {
-            self.position_in_memory +=2;
+            self.skip_next_instruction();
         }
     }
 
-    fn sknp_vx(&mut self, opcode: u16)
+    fn sknp_vx(&mut self, x: u8)
     {
-        let x = ((opcode & 0x0F00) >> 8) as u8;  
-        if !self.keyboard[self.registers[x as usize] as usize]
+        if !self.keyboard[(self.registers[x as usize] & 0x0F) as usize]
         {
-            self.position_in_memory +=2;
+            self.skip_next_instruction();
         }
     }
 
-    fn ld_vx_dt(&mut self, opcode: u16)
+    fn ld_vx_dt(&mut self, x: u8)
     {
-        let x = ((opcode & 0x0F00) >> 8) as u8;  
         self.registers[x as usize] = self.delay_timer
     }
 
-    fn ld_vx_k(&mut self, opcode: u16) 
+    fn ld_vx_k(&mut self, x: u8) 
     {
-        let x = ((opcode & 0x0F00) >> 8) as u8;
 
         for (index, pressed) in self.keyboard.iter().enumerate()
         {
@@ -540,71 +1064,140 @@ impl Chip8 {
         self.position_in_memory -= 2;
     }
 
-    fn ld_dt_vx(&mut self, opcode: u16)
+    fn ld_dt_vx(&mut self, x: u8)
     {
-        let x = ((opcode & 0x0F00) >> 8) as u8;  
         self.delay_timer = self.registers[x as usize];
     }
 
-    fn ld_st_vx(&mut self, opcode: u16)
+    fn ld_st_vx(&mut self, x: u8)
     {
-        let x = ((opcode & 0x0F00) >> 8) as u8;  
         self.sound_timer = self.registers[x as usize];
     }
 
-    fn add_i_vx(&mut self, opcode: u16)
+    fn add_i_vx(&mut self, x: u8)
     {
-        let x = ((opcode & 0x0F00) >> 8) as u8;  
-        self.i_register += self.registers[x as usize] as u16;
+        self.advance_i(self.registers[x as usize] as u16);
     }
 
-    fn ld_f_vx(&mut self, opcode: u16)
+    fn ld_f_vx(&mut self, x: u8)
     {
-        let x = ((opcode & 0x0F00) >> 8) as u8;  
         let digit = self.registers[x as usize] as u16;
         self.i_register = FONT_START_ADDRESS as u16 + FONT_CHAR_SIZE_IN_BYTES as u16 * digit;
     }
 
-    fn ld_b_vx(&mut self, opcode: u16)
+    fn ld_hf_vx(&mut self, x: u8)
+    {
+        let digit = (self.registers[x as usize] & 0x0F) as u16;
+        self.i_register = BIG_FONT_START_ADDRESS as u16 + BIG_FONT_CHAR_SIZE_IN_BYTES as u16 * digit;
+    }
+
+    fn ld_b_vx(&mut self, x: u8) -> Result<(), Chip8Error>
     {
-        let x = ((opcode & 0x0F00) >> 8) as u8;
         let value = self.registers[x as usize];
 
         let hundrets = value / 100; 
         let tens = (value % 100) / 10;
         let ones = value % 10;
     
-        self.memory[self.i_register as usize] = hundrets;
-        self.memory[(self.i_register + 1) as usize] = tens;
-        self.memory[(self.i_register + 2) as usize] = ones;
+        let i = self.i_register as usize;
+        self.write_memory(i, hundrets)?;
+        self.write_memory(i + 1, tens)?;
+        self.write_memory(i + 2, ones)
     }
 
-    fn ld_i_fx(&mut self, opcode: u16)
+    fn ld_i_vx(&mut self, x: u8) -> Result<(), Chip8Error>
     {
-        let x = ((opcode & 0x0F00) >> 8) as u8;  
-        let mut i = self.i_register;
+        let i = self.i_register as usize;
         
-        for n in 0..=x 
+        for n in 0..=x as usize
         {
-            self.memory[i as usize] = self.registers[n as usize];
-            i += 1;
+            self.write_memory(i + n, self.registers[n])?;
         }
 
-        self.i_register += (x + 1) as u16
+        self.increment_i_after_memory_access(x);
+        Ok(())
     }
 
-    fn ld_vx_i(&mut self, opcode: u16)
+    fn ld_vx_i(&mut self, x: u8) -> Result<(), Chip8Error>
     {
-        let x = ((opcode & 0x0F00) >> 8) as u8;  
-        let mut i = self.i_register;
+        let i = self.i_register as usize;
         
-        for n in 0..=x 
+        for n in 0..=x as usize
         {
-            self.registers[n as usize] = self.memory[i as usize];
-            i += 1;
+            self.registers[n] = self.read_data(i + n, CoverageMap::READ)?;
         }
  
-        self.i_register += (x + 1) as u16
+        self.increment_i_after_memory_access(x);
+        Ok(())
+    }
+
+    fn ld_i_long(&mut self) -> Result<(), Chip8Error>
+    {
+        self.i_register = self.read_opcode()?;
+        self.position_in_memory += 2;
+        Ok(())
+    }
+
+    fn plane_n(&mut self, n: u8)
+    {
+        self.selected_planes = n & 0x3;
+    }
+
+    fn ld_i_vx_vy(&mut self, x: u8, y: u8) -> Result<(), Chip8Error>
+    {
+        let i = self.i_register as usize;
+
+        for (offset, register) in Chip8::register_range(x as usize, y as usize).enumerate() {
+            self.write_memory(i + offset, self.registers[register])?;
+        }
+        Ok(())
+    }
+
+    fn ld_vx_vy_i(&mut self, x: u8, y: u8) -> Result<(), Chip8Error>
+    {
+        let i = self.i_register as usize;
+
+        for (offset, register) in Chip8::register_range(x as usize, y as usize).enumerate() {
+            self.registers[register] = self.read_data(i + offset, CoverageMap::READ)?;
+        }
+        Ok(())
+    }
+
+    // Vx..Vy, walked backwards when x > y
+    fn register_range(x: usize, y: usize) -> Box<dyn Iterator<Item = usize>> {
+        if x <= y {
+            Box::new(x..=y)
+        } else {
+            Box::new((y..=x).rev())
+        }
+    }
+
+    fn ld_r_vx(&mut self, x: u8)
+    {
+        let x = x as usize;
+        self.rpl_flags[..=x].copy_from_slice(&self.registers[..=x]);
+    }
+
+    fn ld_vx_r(&mut self, x: u8)
+    {
+        let x = x as usize;
+        self.registers[..=x].copy_from_slice(&self.rpl_flags[..=x]);
+    }
+
+    fn increment_i_after_memory_access(&mut self, x: u8)
+    {
+        match self.quirks.memory_increment {
+            MemoryIncrement::None => {},
+            MemoryIncrement::ByX => self.advance_i(x as u16),
+            MemoryIncrement::ByXPlusOne => self.advance_i(x as u16 + 1),
+        }
+    }
+
+    // I wraps around within the address space of the platform, 4 KiB or 64 KiB on XO-CHIP.
+    fn advance_i(&mut self, amount: u16)
+    {
+        let mask = (self.platform.memory_size() - 1) as u16;
+        self.i_register = self.i_register.wrapping_add(amount) & mask;
     }
 }
 
@@ -631,7 +1224,7 @@ mod tests {
         chip8.display[31][63] = 1;
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
 
         // Assert: The entire display should be cleared
         for y in 0..DISPLAY_HEIGHT as usize {
@@ -649,7 +1242,7 @@ mod tests {
         chip8.stack[0] = 0x350; // Return address
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
 
         // Assert: PC should be the return address and stack pointer decremented
         assert_eq!(chip8.position_in_memory, 0x350);
@@ -661,7 +1254,7 @@ mod tests {
         let mut chip8 = setup_with_opcode(0x1ABC);
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert: PC is set to nnn, not pc + 2
         assert_eq!(chip8.position_in_memory, 0xABC);
@@ -673,7 +1266,7 @@ mod tests {
         let initial_pc = chip8.position_in_memory;
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert
         assert_eq!(chip8.position_in_memory, 0xABC, "PC should be at the new address");
@@ -688,7 +1281,7 @@ mod tests {
         chip8.registers[5] = 0xAB;
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert: PC is incremented by 4 (2 for the instruction, 2 for the skip)
         assert_eq!(chip8.position_in_memory, 0x204);
@@ -701,7 +1294,7 @@ mod tests {
         chip8.registers[5] = 0xAC;
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert: PC is incremented by 2
         assert_eq!(chip8.position_in_memory, 0x202);
@@ -714,7 +1307,7 @@ mod tests {
         chip8.registers[5] = 0xAC;
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert: PC is incremented by 4
         assert_eq!(chip8.position_in_memory, 0x204);
@@ -727,7 +1320,7 @@ mod tests {
         chip8.registers[5] = 0xAB;
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert: PC is incremented by 2
         assert_eq!(chip8.position_in_memory, 0x202);
@@ -741,7 +1334,7 @@ mod tests {
         chip8.registers[2] = 0xDD;
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert: PC is skipped
         assert_eq!(chip8.position_in_memory, 0x204);
@@ -755,7 +1348,7 @@ mod tests {
         chip8.registers[2] = 0xDE;
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert: PC is not skipped
         assert_eq!(chip8.position_in_memory, 0x202);
@@ -766,7 +1359,7 @@ mod tests {
         let mut chip8 = setup_with_opcode(0x6ABC);
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert: VA now holds the value 0xBC
         assert_eq!(chip8.registers[0xA], 0xBC);
@@ -779,7 +1372,7 @@ mod tests {
         chip8.registers[3] = 0x05;
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert: V3 is now 0x05 + 0x10 = 0x15
         assert_eq!(chip8.registers[3], 0x15);
@@ -792,7 +1385,7 @@ mod tests {
         chip8.registers[3] = 0xFF;
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert: V3 wraps around (0xFF + 0x10 = 0x10F -> 0x0F)
         // VF is NOT changed by this operation.
@@ -807,7 +1400,7 @@ mod tests {
         chip8.registers[2] = 0xCC;
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert: V1 now holds the value from V2
         assert_eq!(chip8.registers[1], 0xCC);
@@ -821,7 +1414,7 @@ mod tests {
         chip8.registers[2] = 0b0101_1010;
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
 
         // Assert: V1 is the bitwise OR
         assert_eq!(chip8.registers[1], 0b1111_1110);
@@ -835,7 +1428,7 @@ mod tests {
         chip8.registers[2] = 0b0101_1010;
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
 
         // Assert: V1 is the bitwise AND
         assert_eq!(chip8.registers[1], 0b0000_1000);
@@ -849,7 +1442,7 @@ mod tests {
         chip8.registers[2] = 0b0101_1010;
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
 
         // Assert: V1 is the bitwise XOR
         assert_eq!(chip8.registers[1], 0b1111_0110);
@@ -863,7 +1456,7 @@ mod tests {
         chip8.registers[2] = 50;
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
 
         // Assert
         assert_eq!(chip8.registers[1], 150);
@@ -878,7 +1471,7 @@ mod tests {
         chip8.registers[2] = 100;
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
 
         // Assert: 200 + 100 = 300 -> 44 (with carry)
         assert_eq!(chip8.registers[1], 44);
@@ -893,7 +1486,7 @@ mod tests {
         chip8.registers[2] = 50;
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
 
         // Assert: VF is 1 (NOT borrow)
         assert_eq!(chip8.registers[1], 50);
@@ -908,7 +1501,7 @@ mod tests {
         chip8.registers[2] = 100;
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
 
         // Assert: VF is 0 (borrow)
         assert_eq!(chip8.registers[1], 206); // 50 - 100 wraps
@@ -922,7 +1515,7 @@ mod tests {
         chip8.registers[1] = 0b10101011;
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert
         assert_eq!(chip8.registers[1], 0b01010101);
@@ -936,7 +1529,7 @@ mod tests {
         chip8.registers[1] = 0b10101010;
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert
         assert_eq!(chip8.registers[1], 0b01010101);
@@ -951,7 +1544,7 @@ mod tests {
         chip8.registers[2] = 100;
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
 
         // Assert: Vx = Vy - Vx, VF is 1 (NOT borrow)
         assert_eq!(chip8.registers[1], 50);
@@ -966,7 +1559,7 @@ mod tests {
         chip8.registers[2] = 50;
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
 
         // Assert: Vx = Vy - Vx, VF is 0 (borrow)
         assert_eq!(chip8.registers[1], 206); // 50 - 100 wraps
@@ -980,7 +1573,7 @@ mod tests {
         chip8.registers[1] = 0b10101010;
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert
         assert_eq!(chip8.registers[1], 0b01010100);
@@ -994,7 +1587,7 @@ mod tests {
         chip8.registers[1] = 0b01010101;
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert
         assert_eq!(chip8.registers[1], 0b10101010);
@@ -1009,7 +1602,7 @@ mod tests {
         chip8.registers[2] = 0xBB;
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert
         assert_eq!(chip8.position_in_memory, 0x204);
@@ -1020,7 +1613,7 @@ mod tests {
         let mut chip8 = setup_with_opcode(0xA123);
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert
         assert_eq!(chip8.i_register, 0x123);
@@ -1033,7 +1626,7 @@ mod tests {
         chip8.registers[0] = 0x50;
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert
         assert_eq!(chip8.position_in_memory, 0x350);
@@ -1045,7 +1638,7 @@ mod tests {
         // Arrange: mask is 0x0F
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert: We can't know the random number, but we can know it was ANDed with the mask.
         // The result in V1 must be between 0 and 15.
@@ -1063,7 +1656,7 @@ mod tests {
         chip8.memory[0x300] = 0b10101010;
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
 
         // Assert: Check pixels were drawn correctly
         assert_eq!(chip8.display[20][10], 1);
@@ -1089,7 +1682,7 @@ mod tests {
         chip8.display[20][10] = 1;
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
 
         // Assert: Pixel is turned off (1 XOR 1 = 0)
         assert_eq!(chip8.display[20][10], 0);
@@ -1111,7 +1704,7 @@ mod tests {
         chip8.memory[0x300] = 0b11110000;
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
 
         // Assert: Check pixels on both sides of the screen
         assert_eq!(chip8.display[10][62], 1);
@@ -1129,7 +1722,7 @@ mod tests {
         chip8.keyboard[0xA] = true; // Key 'A' is pressed
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert
         assert_eq!(chip8.position_in_memory, 0x204);
@@ -1143,7 +1736,7 @@ mod tests {
         chip8.keyboard[0xA] = false; // Key 'A' is NOT pressed
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert
         assert_eq!(chip8.position_in_memory, 0x204);
@@ -1156,7 +1749,7 @@ mod tests {
         chip8.delay_timer = 55;
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert
         assert_eq!(chip8.registers[3], 55);
@@ -1169,7 +1762,7 @@ mod tests {
         chip8.keyboard = [false; 16];
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert: PC does not advance (it is decremented after being incremented)
         assert_eq!(chip8.position_in_memory, 0x200);
@@ -1182,7 +1775,7 @@ mod tests {
         chip8.keyboard[0xC] = true;
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert: PC advances and V3 gets the key value
         assert_eq!(chip8.position_in_memory, 0x202);
@@ -1196,7 +1789,7 @@ mod tests {
         chip8.registers[8] = 99;
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert
         assert_eq!(chip8.delay_timer, 99);
@@ -1209,7 +1802,7 @@ mod tests {
         chip8.registers[8] = 123;
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert
         assert_eq!(chip8.sound_timer, 123);
@@ -1223,12 +1816,35 @@ mod tests {
         chip8.registers[5] = 0x50;
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert
         assert_eq!(chip8.i_register, 0x150);
     }
     
+    #[test]
+    fn test_fx1e_add_i_vx_wraps_around_memory() {
+        let mut chip8 = setup_with_opcode(0xF51E);
+        chip8.i_register = 0xFFF;
+        chip8.registers[5] = 2;
+
+        chip8.execute_step().unwrap();
+        assert_eq!(chip8.i_register, 0x001, "4 KiB of memory wrap at 0xFFF");
+
+        // Arrange: LD I, 0xFFFF then ADD I, V5 on XO-CHIP
+        let mut chip8 = setup_with_opcode(0xF000);
+        chip8.set_platform(Platform::XoChip);
+        chip8.memory[0x202..0x206].copy_from_slice(&[0xFF, 0xFF, 0xF5, 0x1E]);
+        chip8.registers[5] = 2;
+
+        // Act
+        chip8.execute_step().unwrap();
+        chip8.execute_step().unwrap();
+
+        // Assert
+        assert_eq!(chip8.i_register, 0x0001);
+    }
+
     #[test]
     fn test_fx29_ld_f_vx() {
         let mut chip8 = setup_with_opcode(0xF229);
@@ -1236,7 +1852,7 @@ mod tests {
         chip8.registers[2] = 0x2;
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert: I should point to the location of sprite '2'
         // Each font character is 5 bytes long.
@@ -1251,7 +1867,7 @@ mod tests {
         chip8.i_register = 0x300;
 
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert: BCD representation is stored in memory
         assert_eq!(chip8.memory[0x300], 2); // Hundreds
@@ -1270,7 +1886,7 @@ mod tests {
         chip8.registers[3] = 0x44;
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert: Memory contains register values
         assert_eq!(chip8.memory[0x400], 0x11);
@@ -1293,7 +1909,7 @@ mod tests {
         chip8.memory[0x403] = 0xDD;
         
         // Act
-        chip8.execute_step();
+        chip8.execute_step().unwrap();
         
         // Assert: Registers contain memory values
         assert_eq!(chip8.registers[0], 0xAA);
@@ -1304,4 +1920,1320 @@ mod tests {
         // Assert: Check I register modification (based on your implementation)
         assert_eq!(chip8.i_register, 0x400 + (3 + 1));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_8xy6_shr_uses_vy_quirk() {
+        let mut chip8 = setup_with_opcode(0x8126);
+        chip8.set_quirks(Quirks::COSMAC_VIP);
+        // Arrange: Vy holds the value to shift, Vx is overwritten
+        chip8.registers[1] = 0xFF;
+        chip8.registers[2] = 0b0000_0011;
+
+        // Act
+        chip8.execute_step().unwrap();
+
+        // Assert
+        assert_eq!(chip8.registers[1], 0b0000_0001);
+        assert_eq!(chip8.registers[0xF], 1);
+    }
+
+    #[test]
+    fn test_8xy1_vf_reset_quirk() {
+        let mut chip8 = setup_with_opcode(0x8121);
+        chip8.set_quirks(Quirks::COSMAC_VIP);
+        // Arrange
+        chip8.registers[0xF] = 0x42;
+
+        // Act
+        chip8.execute_step().unwrap();
+
+        // Assert
+        assert_eq!(chip8.registers[0xF], 0);
+    }
+
+    #[test]
+    fn test_fx55_memory_increment_quirks() {
+        let mut chip8 = setup_with_opcode(0xF355);
+        chip8.set_quirks(Quirks::SUPER_CHIP);
+        chip8.i_register = 0x400;
+
+        chip8.execute_step().unwrap();
+        assert_eq!(chip8.i_register, 0x400, "SUPER-CHIP leaves I untouched");
+
+        let mut chip8 = setup_with_opcode(0xF365);
+        chip8.set_quirks(Quirks::CHIP_48);
+        chip8.i_register = 0x400;
+
+        chip8.execute_step().unwrap();
+        assert_eq!(chip8.i_register, 0x403, "CHIP-48 increments I by X");
+    }
+
+    #[test]
+    fn test_bxnn_jump_uses_vx_quirk() {
+        let mut chip8 = setup_with_opcode(0xB300);
+        chip8.set_quirks(Quirks::CHIP_48);
+        // Arrange
+        chip8.registers[0] = 0x50;
+        chip8.registers[3] = 0x10;
+
+        // Act
+        chip8.execute_step().unwrap();
+
+        // Assert: XNN + VX
+        assert_eq!(chip8.position_in_memory, 0x310);
+    }
+
+    #[test]
+    fn test_dxyn_drw_clipping_quirk() {
+        let mut chip8 = setup_with_opcode(0xD011);
+        chip8.set_quirks(Quirks::SUPER_CHIP);
+        // Arrange
+        chip8.registers[0] = 62;
+        chip8.registers[1] = 10;
+        chip8.i_register = 0x300;
+        chip8.memory[0x300] = 0b11110000;
+
+        // Act
+        chip8.execute_step().unwrap();
+
+        // Assert: pixels past the right edge are dropped
+        assert_eq!(chip8.display[10][62], 1);
+        assert_eq!(chip8.display[10][63], 1);
+        assert_eq!(chip8.display[10][0], 0);
+        assert_eq!(chip8.display[10][1], 0);
+    }
+
+    #[test]
+    fn test_dxyn_drw_clipped_rows_do_not_collide_in_low_resolution() {
+        let mut chip8 = setup_with_opcode(0xD015);
+        chip8.set_quirks(Quirks { display_wait: false, ..Quirks::COSMAC_VIP });
+        // Arrange: a 5 row sprite with 3 rows below the bottom edge
+        chip8.registers[1] = 30;
+        chip8.i_register = 0x300;
+        chip8.memory[0x300..0x305].copy_from_slice(&[0xFF; 5]);
+
+        // Act
+        chip8.execute_step().unwrap();
+
+        // Assert
+        assert_eq!(chip8.display[31][0], 1);
+        assert_eq!(chip8.registers[0xF], 0);
+    }
+
+    #[test]
+    fn test_dxyn_drw_display_wait_quirk() {
+        let mut chip8 = setup_with_opcode(0xD011);
+        chip8.set_quirks(Quirks::COSMAC_VIP);
+        chip8.i_register = 0x300;
+        chip8.memory[0x300] = 0b10000000;
+
+        // Act: no vertical blank yet, the draw is retried
+        chip8.execute_step().unwrap();
+        assert_eq!(chip8.position_in_memory, 0x200);
+        assert_eq!(chip8.display[0][0], 0);
+
+        // Act: after a timer tick the sprite is drawn
+        chip8.update_timers(17);
+        chip8.execute_step().unwrap();
+        assert_eq!(chip8.position_in_memory, 0x202);
+        assert_eq!(chip8.display[0][0], 1);
+    }
+
+    #[test]
+    fn test_00ff_high_resolution_mode() {
+        let mut chip8 = setup_with_opcode(0x00FF);
+        chip8.set_platform(Platform::SuperChip);
+
+        // Act
+        chip8.execute_step().unwrap();
+
+        // Assert
+        assert_eq!(chip8.get_display_width(), HIGH_RES_DISPLAY_WIDTH as usize);
+        assert_eq!(chip8.get_display_height(), HIGH_RES_DISPLAY_HEIGHT as usize);
+    }
+
+    #[test]
+    fn test_00ff_ignored_on_chip8() {
+        let mut chip8 = setup_with_opcode(0x00FF);
+
+        // Act
+        chip8.execute_step().unwrap();
+
+        // Assert: treated as a SYS call, resolution unchanged
+        assert_eq!(chip8.get_display_width(), DISPLAY_WIDTH as usize);
+        assert_eq!(chip8.position_in_memory, 0x202);
+    }
+
+    #[test]
+    fn test_00cn_scroll_down() {
+        let mut chip8 = setup_with_opcode(0x00C3);
+        chip8.set_platform(Platform::SuperChip);
+        chip8.display[0][5] = 1;
+
+        // Act
+        chip8.execute_step().unwrap();
+
+        // Assert
+        assert_eq!(chip8.display[0][5], 0);
+        assert_eq!(chip8.display[3][5], 1);
+    }
+
+    #[test]
+    fn test_00fb_00fc_scroll_right_and_left() {
+        let mut chip8 = setup_with_opcode(0x00FB);
+        chip8.set_platform(Platform::SuperChip);
+        chip8.memory[0x202] = 0x00;
+        chip8.memory[0x203] = 0xFC;
+        chip8.display[1][10] = 1;
+
+        chip8.execute_step().unwrap();
+        assert_eq!(chip8.display[1][14], 1);
+        assert_eq!(chip8.display[1][10], 0);
+
+        chip8.execute_step().unwrap();
+        assert_eq!(chip8.display[1][10], 1);
+        assert_eq!(chip8.display[1][14], 0);
+    }
+
+    #[test]
+    fn test_00fd_exit() {
+        let mut chip8 = setup_with_opcode(0x00FD);
+        chip8.set_platform(Platform::SuperChip);
+
+        // Act
+        chip8.execute_step().unwrap();
+
+        // Assert: the interpreter stays on the exit instruction
+        assert!(chip8.is_halted());
+        assert_eq!(chip8.position_in_memory, 0x200);
+    }
+
+    #[test]
+    fn test_dxy0_draws_16x16_sprite_with_row_collisions() {
+        let mut chip8 = setup_with_opcode(0xD010);
+        chip8.set_platform(Platform::SuperChip);
+        chip8.set_high_resolution(true);
+        chip8.i_register = 0x300;
+        for byte in 0..32 {
+            chip8.memory[0x300 + byte] = 0xFF;
+        }
+        // Arrange: two rows already contain a lit pixel
+        chip8.display[0][15] = 1;
+        chip8.display[7][3] = 1;
+
+        // Act
+        chip8.execute_step().unwrap();
+
+        // Assert
+        assert_eq!(chip8.display[15][15], 1);
+        assert_eq!(chip8.display[0][15], 0);
+        assert_eq!(chip8.display[0][16], 0);
+        assert_eq!(chip8.registers[0xF], 2);
+    }
+
+    #[test]
+    fn test_fx30_ld_hf_vx() {
+        let mut chip8 = setup_with_opcode(0xF230);
+        chip8.set_platform(Platform::SuperChip);
+        chip8.registers[2] = 0x7;
+
+        // Act
+        chip8.execute_step().unwrap();
+
+        // Assert
+        assert_eq!(chip8.i_register, (BIG_FONT_START_ADDRESS + 7 * BIG_FONT_CHAR_SIZE_IN_BYTES) as u16);
+    }
+
+    #[test]
+    fn test_fx75_fx85_rpl_flags() {
+        let mut chip8 = setup_with_opcode(0xF275);
+        chip8.set_platform(Platform::SuperChip);
+        chip8.memory[0x202] = 0xF2;
+        chip8.memory[0x203] = 0x85;
+        chip8.registers[0] = 1;
+        chip8.registers[1] = 2;
+        chip8.registers[2] = 3;
+
+        chip8.execute_step().unwrap();
+        chip8.registers[..3].fill(0);
+        chip8.execute_step().unwrap();
+
+        // Assert
+        assert_eq!(&chip8.registers[..3], &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_xo_chip_memory_size() {
+        let mut chip8 = Chip8::new();
+        chip8.set_platform(Platform::XoChip);
+
+        assert_eq!(chip8.memory.len(), 0x10000);
+    }
+
+    #[test]
+    fn test_f000_nnnn_ld_i_long() {
+        let mut chip8 = setup_with_opcode(0xF000);
+        chip8.set_platform(Platform::XoChip);
+        chip8.memory[0x202] = 0xBE;
+        chip8.memory[0x203] = 0xEF;
+
+        // Act
+        chip8.execute_step().unwrap();
+
+        // Assert: I holds the 16 bit operand and PC skipped it
+        assert_eq!(chip8.i_register, 0xBEEF);
+        assert_eq!(chip8.position_in_memory, 0x204);
+    }
+
+    #[test]
+    fn test_skip_steps_over_long_load() {
+        let mut chip8 = setup_with_opcode(0x3100);
+        chip8.set_platform(Platform::XoChip);
+        chip8.memory[0x202] = 0xF0;
+        chip8.memory[0x203] = 0x00;
+
+        // Act
+        chip8.execute_step().unwrap();
+
+        // Assert: the skip covers all four bytes of F000 NNNN
+        assert_eq!(chip8.position_in_memory, 0x206);
+    }
+
+    #[test]
+    fn test_fn01_dxyn_draws_selected_planes() {
+        let mut chip8 = setup_with_opcode(0xF301);
+        chip8.set_platform(Platform::XoChip);
+        chip8.memory[0x202] = 0xD0;
+        chip8.memory[0x203] = 0x01;
+        chip8.i_register = 0x300;
+        chip8.memory[0x300] = 0b1100_0000; // plane 1
+        chip8.memory[0x301] = 0b1010_0000; // plane 2
+
+        // Act
+        chip8.execute_step().unwrap();
+        chip8.execute_step().unwrap();
+
+        // Assert: each pixel holds one bit per plane
+        assert_eq!(chip8.display[0][0], 0b11);
+        assert_eq!(chip8.display[0][1], 0b01);
+        assert_eq!(chip8.display[0][2], 0b10);
+        assert_eq!(chip8.registers[0xF], 0);
+    }
+
+    #[test]
+    fn test_00e0_cls_only_clears_selected_planes() {
+        let mut chip8 = setup_with_opcode(0xF201);
+        chip8.set_platform(Platform::XoChip);
+        chip8.memory[0x202] = 0x00;
+        chip8.memory[0x203] = 0xE0;
+        chip8.display[4][4] = 0b11;
+
+        // Act
+        chip8.execute_step().unwrap();
+        chip8.execute_step().unwrap();
+
+        // Assert
+        assert_eq!(chip8.display[4][4], 0b01);
+    }
+
+    #[test]
+    fn test_5xy2_5xy3_register_ranges() {
+        let mut chip8 = setup_with_opcode(0x5132);
+        chip8.set_platform(Platform::XoChip);
+        chip8.memory[0x202] = 0x53;
+        chip8.memory[0x203] = 0x13;
+        chip8.i_register = 0x400;
+        chip8.registers[1] = 0x11;
+        chip8.registers[2] = 0x22;
+        chip8.registers[3] = 0x33;
+
+        // Act: save V1..V3, then load them back reversed into V3..V1
+        chip8.execute_step().unwrap();
+        chip8.execute_step().unwrap();
+
+        // Assert
+        assert_eq!(&chip8.memory[0x400..0x403], &[0x11, 0x22, 0x33]);
+        assert_eq!(chip8.registers[3], 0x11);
+        assert_eq!(chip8.registers[1], 0x33);
+        assert_eq!(chip8.i_register, 0x400, "I is not modified");
+    }
+
+    #[test]
+    fn test_00dn_scroll_up() {
+        let mut chip8 = setup_with_opcode(0x00D2);
+        chip8.set_platform(Platform::XoChip);
+        chip8.display[5][7] = 1;
+
+        // Act
+        chip8.execute_step().unwrap();
+
+        // Assert
+        assert_eq!(chip8.display[3][7], 1);
+        assert_eq!(chip8.display[5][7], 0);
+    }
+
+    #[test]
+    fn test_unknown_opcode_returns_error() {
+        let mut chip8 = setup_with_opcode(0x8128);
+
+        // Act
+        let result = chip8.execute_step();
+
+        // Assert: PC stays on the faulting instruction
+        assert_eq!(result, Err(Chip8Error::UnknownOpcode { opcode: 0x8128, address: 0x200 }));
+        assert_eq!(chip8.position_in_memory, 0x200);
+    }
+
+    #[test]
+    fn test_00ee_ret_stack_underflow() {
+        let mut chip8 = setup_with_opcode(0x00EE);
+
+        // Act
+        let result = chip8.execute_step();
+
+        // Assert
+        assert_eq!(result, Err(Chip8Error::StackUnderflow { address: 0x200 }));
+    }
+
+    #[test]
+    fn test_2nnn_call_addr_stack_overflow() {
+        let mut chip8 = setup_with_opcode(0x2200);
+        chip8.stack_pointer = 16;
+
+        // Act
+        let result = chip8.execute_step();
+
+        // Assert
+        assert_eq!(result, Err(Chip8Error::StackOverflow { address: 0x200 }));
+    }
+
+    #[test]
+    fn test_fx55_out_of_bounds_memory_access() {
+        let mut chip8 = setup_with_opcode(0xF355);
+        chip8.i_register = 0xFFE;
+
+        // Act
+        let result = chip8.execute_step();
+
+        // Assert
+        assert_eq!(result, Err(Chip8Error::MemoryOutOfBounds { address: 0x1000 }));
+    }
+
+    #[test]
+    fn test_set_key_invalid_index() {
+        let mut chip8 = Chip8::new();
+
+        assert_eq!(chip8.set_key(0x10, true), Err(Chip8Error::InvalidKey(0x10)));
+        assert_eq!(chip8.set_key(0xF, true), Ok(()));
+    }
+
+    #[test]
+    fn test_load_unknown_rom() {
+        let mut chip8 = Chip8::new();
+
+        let result = chip8.load_rom_in_memory("does_not_exist".to_string());
+
+        assert_eq!(result, Err(Chip8Error::RomNotFound("does_not_exist".to_string())));
+    }
+
+    #[test]
+    fn test_update_timers_carries_fractional_time() {
+        let mut chip8 = Chip8::new();
+        chip8.delay_timer = 10;
+
+        // Act: 20 ms is a single tick, the remaining 3.3 ms are carried over
+        chip8.update_timers(20);
+        chip8.update_timers(10);
+        assert_eq!(chip8.delay_timer, 9);
+
+        // Act: 55 ms total are three ticks
+        chip8.update_timers(25);
+        assert_eq!(chip8.delay_timer, 7);
+    }
+
+    #[test]
+    fn test_run_frame_executes_instructions_and_ticks_timers() {
+        let mut chip8 = setup_with_opcode(0x7101); // V1 += 1
+        chip8.memory[0x202] = 0x12; // JP 0x200
+        chip8.memory[0x203] = 0x00;
+        chip8.set_instructions_per_frame(10);
+        chip8.sound_timer = 2;
+
+        // Act
+        let report = chip8.run_frame().unwrap();
+
+        // Assert
+        assert_eq!(report.instructions_executed, 10);
+        assert_eq!(report.frames_completed, 1);
+        assert_eq!(chip8.registers[1], 5);
+        assert_eq!(chip8.sound_timer, 1);
+        assert!(report.sound_active);
+    }
+
+    #[test]
+    fn test_run_for_runs_whole_frames_only() {
+        let mut chip8 = setup_with_opcode(0x1200); // JP 0x200
+        chip8.set_cpu_frequency(600);
+
+        // Act
+        let first = chip8.run_for(10.0).unwrap();
+        let second = chip8.run_for(30.0).unwrap();
+
+        // Assert: 40 ms are two full frames of 10 instructions each
+        assert_eq!(first.frames_completed, 0);
+        assert_eq!(second.frames_completed, 2);
+        assert_eq!(second.instructions_executed, 20);
+    }
+
+    #[test]
+    fn test_run_for_limits_catch_up() {
+        let mut chip8 = setup_with_opcode(0x1200); // JP 0x200
+
+        // Act
+        let report = chip8.run_for(10_000.0).unwrap();
+
+        // Assert
+        assert_eq!(report.frames_completed, MAX_FRAMES_PER_RUN);
+        assert!(chip8.accumulator_timer < Chip8::frame_duration_ms());
+    }
+
+    #[test]
+    fn test_5xy1_is_not_executed_as_se() {
+        let mut chip8 = setup_with_opcode(0x5121);
+        chip8.registers[1] = 0xDD;
+        chip8.registers[2] = 0xDD;
+
+        // Act
+        let result = chip8.execute_step();
+
+        // Assert
+        assert_eq!(result, Err(Chip8Error::UnknownOpcode { opcode: 0x5121, address: 0x200 }));
+        assert_eq!(chip8.position_in_memory, 0x200);
+    }
+
+    #[test]
+    fn test_disassemble_around_pc() {
+        let mut chip8 = setup_with_opcode(0x6120);
+        chip8.memory[0x1FE] = 0x00;
+        chip8.memory[0x1FF] = 0xE0;
+        chip8.memory[0x202] = 0x12;
+        chip8.memory[0x203] = 0x00;
+
+        let lines = chip8.disassemble_around_pc(1, Syntax::Cowgod);
+        let text: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+
+        assert_eq!(text, ["CLS", "LD V1, 0x20", "JP 0x200"]);
+        assert!(lines[1].is_current);
+    }
+
+    #[test]
+    fn test_disassemble_around_pc_with_huge_context_lists_all_memory() {
+        let chip8 = setup_with_opcode(0x00E0);
+
+        let lines = chip8.disassemble_around_pc(usize::MAX, Syntax::Cowgod);
+
+        assert_eq!(lines.first().map(|line| line.address), Some(0x000));
+        assert_eq!(lines.last().map(|line| line.address as usize + line.bytes.len()), Some(chip8.memory.len()));
+        assert!(lines.iter().any(|line| line.is_current && line.address == 0x200));
+    }
+
+    #[test]
+    fn test_disassemble_around_pc_stays_in_step_after_long_instructions() {
+        // Arrange: LD I, 0x1234 (four bytes), a data byte, then the PC at an odd address
+        let mut chip8 = setup_with_opcode(0xF000);
+        chip8.set_platform(Platform::XoChip);
+        chip8.memory[0x202..0x208].copy_from_slice(&[0x12, 0x34, 0xAA, 0x00, 0xE0, 0x00]);
+        chip8.execute_step().unwrap();
+        chip8.coverage.mark(0x204, CoverageMap::READ);
+        chip8.position_in_memory = 0x205;
+
+        // Act
+        let lines = chip8.disassemble_around_pc(2, Syntax::Cowgod);
+
+        // Assert
+        let text: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(text.len(), 5);
+        assert_eq!(text[..3], ["LD I, LONG 0x1234", "DB 0xAA", "CLS"]);
+        assert!(lines[2].is_current);
+    }
+
+    #[test]
+    fn test_save_state_round_trip() {
+        // Arrange
+        let mut chip8 = setup_with_opcode(0xC0FF); // RND V0, 0xFF
+        chip8.set_platform(Platform::XoChip);
+        chip8.registers[5] = 0x42;
+        chip8.i_register = 0x1234;
+        chip8.stack[0] = 0x300;
+        chip8.stack_pointer = 1;
+        chip8.delay_timer = 7;
+        chip8.accumulator_timer = 3.5;
+        chip8.memory[0xFFFF] = 0xAB;
+        chip8.display[3][4] = 2;
+        chip8.set_key(0xA, true).unwrap();
+        let state = chip8.save_state();
+        let mut restored = Chip8::new();
+
+        // Act
+        restored.load_state(&state).unwrap();
+        chip8.execute_step().unwrap();
+        restored.execute_step().unwrap();
+
+        // Assert
+        assert_eq!(restored.get_platform(), Platform::XoChip);
+        assert_eq!(restored.get_quirks(), Quirks::XO_CHIP);
+        assert_eq!(restored.registers[5], 0x42);
+        assert_eq!(restored.i_register, 0x1234);
+        assert_eq!(restored.stack_pointer, 1);
+        assert_eq!(restored.stack[0], 0x300);
+        assert_eq!(restored.delay_timer, 7);
+        assert_eq!(restored.accumulator_timer, 3.5);
+        assert_eq!(restored.memory[0xFFFF], 0xAB);
+        assert_eq!(restored.display[3][4], 2);
+        assert!(restored.keyboard[0xA]);
+        assert_eq!(restored.registers[0], chip8.registers[0], "the RNG continues from the saved state");
+        assert_eq!(restored.save_state(), chip8.save_state());
+    }
+
+    #[test]
+    fn test_load_state_rejects_invalid_states() {
+        // Arrange
+        let mut chip8 = Chip8::new();
+        let mut state = chip8.save_state();
+        chip8.registers[0] = 0x99;
+        let mut newer_version = state.clone();
+        newer_version[4..6].copy_from_slice(&(SAVE_STATE_VERSION + 1).to_be_bytes());
+
+        // Act & Assert
+        assert!(matches!(chip8.load_state(b"nope"), Err(Chip8Error::InvalidSaveState(_))));
+        assert_eq!(chip8.load_state(&newer_version), Err(Chip8Error::UnsupportedSaveStateVersion(SAVE_STATE_VERSION + 1)));
+        state.pop();
+        assert!(matches!(chip8.load_state(&state), Err(Chip8Error::InvalidSaveState(_))));
+        assert_eq!(chip8.registers[0], 0x99, "a rejected state leaves the machine untouched");
+    }
+
+    #[test]
+    fn test_load_state_rejects_impossible_machine_states() {
+        let rejected = |change: fn(&mut Chip8)| {
+            let mut chip8 = Chip8::new();
+            change(&mut chip8);
+            let state = chip8.save_state();
+            Chip8::new().load_state(&state)
+        };
+
+        assert_eq!(
+            rejected(|chip8| chip8.display = Display::new(0, 0)),
+            Err(Chip8Error::InvalidSaveState("display size does not match the resolution".to_string()))
+        );
+        assert_eq!(
+            rejected(|chip8| chip8.high_resolution = true),
+            Err(Chip8Error::InvalidSaveState("display size does not match the resolution".to_string()))
+        );
+        assert_eq!(rejected(|chip8| chip8.selected_planes = 4), Err(Chip8Error::InvalidSaveState("unknown bitplanes selected".to_string())));
+        assert_eq!(
+            rejected(|chip8| chip8.instructions_per_frame = 0),
+            Err(Chip8Error::InvalidSaveState("speed of 0 instructions per frame".to_string()))
+        );
+        assert_eq!(rejected(|chip8| chip8.set_high_resolution(true)), Ok(()));
+    }
+
+    #[test]
+    fn test_rewind_steps_back_whole_frames() {
+        // Arrange
+        let mut chip8 = setup_with_opcode(0x7001); // ADD V0, 1
+        chip8.memory[0x202] = 0x12; // JP 0x200
+        chip8.memory[0x203] = 0x00;
+        chip8.set_instructions_per_frame(2);
+        chip8.set_rewind_depth(1.0);
+        for _ in 0..5 {
+            chip8.run_frame().unwrap();
+        }
+
+        // Act
+        let rewound = chip8.rewind(2);
+
+        // Assert
+        assert_eq!(rewound, 2);
+        assert_eq!(chip8.registers[0], 3);
+        assert_eq!(chip8.get_rewind_frames(), 3);
+    }
+
+    #[test]
+    fn test_rewind_depth_bounds_recorded_frames() {
+        // Arrange
+        let mut chip8 = setup_with_opcode(0x1200); // JP 0x200
+        chip8.set_rewind_depth(0.5);
+
+        // Act
+        for _ in 0..100 {
+            chip8.run_frame().unwrap();
+        }
+
+        // Assert
+        assert_eq!(chip8.get_rewind_frames(), 30);
+    }
+
+    #[test]
+    fn test_hold_to_rewind_replaces_frames() {
+        // Arrange
+        let mut chip8 = setup_with_opcode(0x7001); // ADD V0, 1
+        chip8.memory[0x202] = 0x12; // JP 0x200
+        chip8.memory[0x203] = 0x00;
+        chip8.set_instructions_per_frame(2);
+        chip8.set_rewind_depth(1.0);
+        for _ in 0..4 {
+            chip8.run_frame().unwrap();
+        }
+        chip8.set_key(0x5, true).unwrap();
+
+        // Act
+        chip8.set_rewinding(true);
+        let report = chip8.run_frame().unwrap();
+
+        // Assert
+        assert_eq!(report.instructions_executed, 0);
+        assert_eq!(chip8.registers[0], 3);
+        assert!(chip8.keyboard[0x5], "held keys survive rewinding");
+    }
+
+    #[test]
+    fn test_hold_to_rewind_keeps_the_frame_budget() {
+        // Arrange: frames recorded while time was left over
+        let mut chip8 = setup_with_opcode(0x1200); // JP 0x200
+        chip8.set_rewind_depth(1.0);
+        chip8.accumulator_timer = 15.0;
+        for _ in 0..10 {
+            chip8.run_frame().unwrap();
+        }
+        chip8.accumulator_timer = 0.0;
+
+        // Act
+        chip8.set_rewinding(true);
+        let report = chip8.run_for(Chip8::frame_duration_ms() * 3.5).unwrap();
+
+        // Assert
+        assert_eq!(report.frames_completed, 3);
+        assert_eq!(chip8.get_rewind_frames(), 7);
+    }
+
+    #[test]
+    fn test_ld_vx_rnd_is_reproducible_with_a_seed() {
+        // Arrange
+        let mut first = setup_with_opcode(0xC0FF); // RND V0, 0xFF
+        let mut second = setup_with_opcode(0xC0FF);
+        first.set_seed(1234);
+        second.set_seed(1234);
+
+        let mut first_numbers = Vec::new();
+        let mut second_numbers = Vec::new();
+
+        // Act
+        for _ in 0..8 {
+            first.position_in_memory = 0x200;
+            second.position_in_memory = 0x200;
+            first.execute_step().unwrap();
+            second.execute_step().unwrap();
+            first_numbers.push(first.registers[0]);
+            second_numbers.push(second.registers[0]);
+        }
+
+        // Assert
+        assert_eq!(first_numbers, second_numbers);
+    }
+
+    #[test]
+    fn test_ld_vx_rnd_cosmac_vip_source_survives_save_state() {
+        // Arrange
+        let interpreter: Vec<u8> = (0..0x200).map(|address| (address * 7) as u8).collect();
+        let mut chip8 = setup_with_opcode(0xC00F); // RND V0, 0x0F
+        chip8.set_random_source(RandomSource::cosmac_vip(&interpreter, 0x1234).unwrap());
+        chip8.execute_step().unwrap();
+        let state = chip8.save_state();
+        let mut restored = Chip8::new();
+        restored.load_state(&state).unwrap();
+
+        // Act
+        chip8.position_in_memory = 0x200;
+        restored.position_in_memory = 0x200;
+        chip8.execute_step().unwrap();
+        restored.execute_step().unwrap();
+
+        // Assert
+        assert_eq!(restored.registers[0], chip8.registers[0]);
+        assert_eq!(chip8.registers[0], 0x0F); // (0x12 + 0x73 + 0x7A) & 0x0F
+    }
+
+    fn setup_with_movie_test_program() -> Chip8 {
+        let mut chip8 = Chip8::new();
+        chip8.rom = crate::assembler::assemble("
+                LD V3, 5
+            main:
+                RND V0, 0x3F
+                RND V1, 0x1F
+                LD I, 0
+                SKP V3
+                DRW V0, V1, 5
+                JP main
+        ").unwrap();
+        chip8.reset();
+        chip8
+    }
+
+    #[test]
+    fn test_movie_playback_reproduces_every_frame() {
+        // Arrange
+        let mut chip8 = setup_with_movie_test_program();
+        let mut recorded_frames = Vec::new();
+        chip8.start_recording(99);
+        for frame in 0..60 {
+            if frame == 10 || frame == 30 {
+                chip8.set_key(5, true).unwrap();
+            }
+            if frame == 20 || frame == 45 {
+                chip8.set_key(5, false).unwrap();
+            }
+            chip8.run_frame().unwrap();
+            recorded_frames.push(chip8.display.pixels().to_vec());
+        }
+        let movie = Movie::from_bytes(&chip8.stop_recording().unwrap().to_bytes()).unwrap();
+
+        // Act
+        chip8.start_playback(movie).unwrap();
+        let mut played_frames = Vec::new();
+        for _ in 0..60 {
+            chip8.set_key(5, true).unwrap(); // ignored during playback
+            chip8.run_frame().unwrap();
+            played_frames.push(chip8.display.pixels().to_vec());
+        }
+
+        // Assert
+        assert_eq!(played_frames, recorded_frames);
+        assert!(!chip8.is_playing_movie(), "playback ends after the last recorded frame");
+    }
+
+    #[test]
+    fn test_movie_playback_requires_the_recorded_rom() {
+        // Arrange
+        let mut chip8 = setup_with_movie_test_program();
+        chip8.start_recording(1);
+        chip8.run_frame().unwrap();
+        let movie = chip8.stop_recording().unwrap();
+        chip8.load_rom_in_memory("pong".to_string()).unwrap();
+
+        // Act
+        let result = chip8.start_playback(movie);
+
+        // Assert
+        assert_eq!(result, Err(Chip8Error::MovieRomMismatch));
+    }
+
+    #[test]
+    fn test_movie_from_bytes_rejects_truncated_movies() {
+        assert!(matches!(Movie::from_bytes(b"C8MV"), Err(Chip8Error::InvalidMovie(_))));
+    }
+
+    #[test]
+    fn test_movie_replays_random_source_and_cheats_on_a_fresh_machine() {
+        // Arrange: RND V0, 0xFF / JP 0x200 with the VIP random source and a cheat
+        let interpreter: Vec<u8> = (0..=255).cycle().take(0x200).collect();
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&[0xC0, 0xFF, 0x12, 0x00]).unwrap();
+        chip8.set_random_source(RandomSource::cosmac_vip(&interpreter, 0).unwrap());
+        chip8.add_cheat(Cheat { address: 0x300, value: 0x42, description: "Frozen".to_string() }).unwrap();
+        chip8.start_recording(7);
+        assert_eq!(chip8.clear_cheats(), Err(Chip8Error::CheatsLockedByMovie));
+        let mut recorded = Vec::new();
+        for _ in 0..10 {
+            chip8.run_frame().unwrap();
+            recorded.push(chip8.registers[0]);
+        }
+        let movie = Movie::from_bytes(&chip8.stop_recording().unwrap().to_bytes()).unwrap();
+
+        // Act
+        let mut fresh = Chip8::new();
+        fresh.load_rom_bytes(&[0xC0, 0xFF, 0x12, 0x00]).unwrap();
+        fresh.start_playback(movie).unwrap();
+        let mut played = Vec::new();
+        for _ in 0..10 {
+            fresh.run_frame().unwrap();
+            played.push(fresh.registers[0]);
+        }
+
+        // Assert
+        assert_eq!(played, recorded);
+        assert_eq!(fresh.memory[0x300], 0x42);
+    }
+
+    #[test]
+    fn test_load_rom_bytes_clears_previous_rom_and_cpu_state() {
+        // Arrange
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&[0x11, 0x22, 0x33, 0x44]).unwrap();
+        chip8.registers[3] = 0x42;
+        chip8.i_register = 0x300;
+        chip8.stack_pointer = 2;
+        chip8.position_in_memory = 0x202;
+        chip8.display[0][0] = 1;
+
+        // Act
+        chip8.load_rom_bytes(&[0xAA, 0xBB]).unwrap();
+
+        // Assert
+        assert_eq!(&chip8.memory[0x200..0x204], &[0xAA, 0xBB, 0x00, 0x00], "leftover bytes are cleared");
+        assert_eq!(chip8.registers[3], 0);
+        assert_eq!(chip8.i_register, 0);
+        assert_eq!(chip8.stack_pointer, 0);
+        assert_eq!(chip8.position_in_memory, 0x200);
+        assert!(chip8.display.pixels().iter().all(|&pixel| pixel == 0));
+    }
+
+    #[test]
+    fn test_load_rom_bytes_rejects_rom_larger_than_memory() {
+        let mut chip8 = Chip8::new();
+
+        assert_eq!(chip8.load_rom_bytes(&[0; 3584]), Ok(()), "a ROM filling all of memory fits");
+        assert_eq!(chip8.platform, Platform::Chip8);
+
+        assert_eq!(chip8.load_rom_bytes(&[0; 3585]), Ok(()), "larger unknown ROMs are loaded as XO-CHIP");
+        assert_eq!(chip8.platform, Platform::XoChip);
+
+        assert_eq!(chip8.load_rom_bytes(&[0; 0xFE01]), Err(Chip8Error::RomTooLarge { size: 0xFE01, max_size: 0xFE00 }));
+    }
+
+    #[test]
+    fn test_load_rom_in_memory_applies_catalog_settings() {
+        let mut chip8 = Chip8::new();
+
+        chip8.load_rom_in_memory("red_october".to_string()).unwrap();
+
+        assert_eq!(chip8.platform, Platform::XoChip);
+        assert_eq!(chip8.quirks, Quirks::XO_CHIP);
+        assert_eq!(chip8.instructions_per_frame, find_rom("red_october").unwrap().speed);
+        assert_eq!(&chip8.memory[0x200..0x200 + chip8.rom.len()], find_rom("red_october").unwrap().data);
+    }
+
+    #[test]
+    fn test_load_rom_bytes_applies_rom_database_settings() {
+        // Arrange
+        let rom = [0x00, 0xFF, 0x12, 0x02];
+        let json = format!(
+            r#"[{{"title": "Hires test", "roms": {{"{}": {{"platforms": ["superchip"], "quirkyPlatforms": {{"superchip": {{"vblank": true}}}}, "tickrate": 30}}}}}}]"#,
+            to_hex(&sha1(&rom))
+        );
+        let mut chip8 = Chip8::new();
+        chip8.set_rom_database(RomDatabase::from_json(&json).unwrap());
+
+        // Act
+        chip8.load_rom_bytes(&rom).unwrap();
+
+        // Assert
+        assert_eq!(chip8.platform, Platform::SuperChip);
+        assert_eq!(chip8.quirks, Quirks { display_wait: true, ..Quirks::SUPER_CHIP });
+        assert_eq!(chip8.instructions_per_frame, 30);
+        assert_eq!(chip8.detected_rom().map(|rom| rom.title.as_str()), Some("Hires test"));
+    }
+
+    #[test]
+    fn test_load_rom_bytes_falls_back_to_defaults_for_unknown_roms() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_in_memory("red_october".to_string()).unwrap();
+        assert_eq!(chip8.detected_rom().map(|rom| rom.title.as_str()), Some("Red October"), "bundled ROMs are recognised");
+
+        chip8.load_rom_bytes(&[0x12, 0x00]).unwrap();
+
+        assert_eq!(chip8.platform, Platform::Chip8);
+        assert_eq!(chip8.quirks, Quirks::default());
+        assert_eq!(chip8.instructions_per_frame, DEFAULT_INSTRUCTIONS_PER_FRAME);
+        assert!(chip8.detected_rom().is_none());
+    }
+
+    fn setup_with_debugger_test_program() -> Chip8 {
+        let mut chip8 = Chip8::new();
+        let rom = crate::assembler::assemble("
+                LD V0, 7        ; 0x200
+                LD I, 0x300     ; 0x202
+                LD [I], V0      ; 0x204
+                LD I, 0x300     ; 0x206
+                DRW V1, V1, 1   ; 0x208
+                ADD V2, 1       ; 0x20A
+                JP 0x20A        ; 0x20C
+        ").unwrap();
+        chip8.load_rom_bytes(&rom).unwrap();
+        chip8
+    }
+
+    #[test]
+    fn test_breakpoint_stops_before_instruction_and_resumes_past_it() {
+        // Arrange
+        let mut chip8 = setup_with_debugger_test_program();
+        chip8.add_breakpoint(0x204);
+
+        // Act & Assert
+        let first = chip8.run_until_break(100).unwrap();
+        assert_eq!(first, BreakReport { reason: BreakReason::Breakpoint, address: 0x204, instructions_executed: 2 });
+        assert_eq!(chip8.memory[0x300], 0, "the instruction at the breakpoint has not run yet");
+
+        let second = chip8.run_until_break(3).unwrap();
+        assert_eq!(second.reason, BreakReason::InstructionLimit);
+        assert_eq!(chip8.memory[0x300], 7);
+    }
+
+    #[test]
+    fn test_breakpoint_stops_run_frame_midway_and_the_frame_continues() {
+        // Arrange
+        let mut chip8 = setup_with_debugger_test_program();
+        chip8.set_instructions_per_frame(10);
+        chip8.delay_timer = 5;
+        chip8.add_breakpoint(0x206);
+
+        // Act
+        let stopped = chip8.run_frame().unwrap();
+        let resumed = chip8.run_frame().unwrap();
+
+        // Assert
+        assert!(stopped.break_hit);
+        assert_eq!((stopped.instructions_executed, stopped.frames_completed), (3, 0));
+        assert_eq!(chip8.last_break().map(|report| report.address), Some(0x206));
+        assert!(!resumed.break_hit);
+        assert_eq!((resumed.instructions_executed, resumed.frames_completed), (7, 1), "only the rest of the frame runs");
+        assert_eq!(chip8.delay_timer, 4);
+    }
+
+    #[test]
+    fn test_memory_watchpoints_see_register_stores_and_sprite_reads() {
+        // Arrange
+        let mut chip8 = setup_with_debugger_test_program();
+        chip8.add_memory_watchpoint(MemoryWatchpoint { start: 0x300, end: 0x300, on_read: true, on_write: true });
+
+        // Act
+        let write = chip8.run_until_break(100).unwrap();
+        let read = chip8.run_until_break(100).unwrap();
+
+        // Assert
+        assert_eq!(write.reason, BreakReason::MemoryWrite { address: 0x300, old: 0, new: 7 });
+        assert_eq!((write.address, write.instructions_executed), (0x204, 3));
+        assert_eq!(read.reason, BreakReason::MemoryRead { address: 0x300, value: 7 });
+        assert_eq!(read.address, 0x208);
+    }
+
+    #[test]
+    fn test_register_watchpoint_reports_old_and_new_value() {
+        let mut chip8 = setup_with_debugger_test_program();
+        chip8.watch_register(WatchedRegister::from_name("v2").unwrap());
+
+        let report = chip8.run_until_break(100).unwrap();
+
+        assert_eq!(report.reason, BreakReason::RegisterChanged { register: WatchedRegister::V(2), old: 0, new: 1 });
+        assert_eq!(report.address, 0x20A);
+        assert_eq!(chip8.position_in_memory, 0x20C, "watchpoints stop after the instruction");
+    }
+
+    #[test]
+    fn test_removed_watchpoints_no_longer_break() {
+        // Arrange
+        let mut chip8 = setup_with_debugger_test_program();
+        chip8.add_memory_watchpoint(MemoryWatchpoint { start: 0x300, end: 0x300, on_read: true, on_write: true });
+        chip8.watch_register(WatchedRegister::V(2));
+
+        // Act
+        let removed = chip8.remove_memory_watchpoint(0x300, 0x300);
+        let removed_again = chip8.remove_memory_watchpoint(0x300, 0x300);
+        chip8.unwatch_register(WatchedRegister::V(2));
+        let report = chip8.run_until_break(20).unwrap();
+
+        // Assert
+        assert!(removed);
+        assert!(!removed_again);
+        assert_eq!(report.reason, BreakReason::InstructionLimit);
+    }
+
+    #[test]
+    fn test_trace_records_newest_instructions_with_register_changes() {
+        // Arrange
+        let mut chip8 = setup_with_debugger_test_program();
+        chip8.set_trace_size(3);
+
+        // Act
+        chip8.run_until_break(6).unwrap();
+
+        // Assert
+        let entries: Vec<&TraceEntry> = chip8.trace().iter().collect();
+        assert_eq!(entries.len(), 3);
+        assert_eq!((entries[0].cycle, entries[0].address, entries[0].opcode), (3, 0x206, 0xA300));
+        assert_eq!(entries[0].changes, [RegisterChange { register: WatchedRegister::I, old: 0x301, new: 0x300 }]);
+        assert_eq!(entries[1].text(Syntax::Cowgod), "DRW V1, V1, 1");
+        assert!(entries[1].changes.is_empty());
+        assert_eq!(entries[2].changes, [RegisterChange { register: WatchedRegister::V(2), old: 0, new: 1 }]);
+
+        chip8.reset();
+        assert!(chip8.trace().is_empty());
+        assert_eq!(chip8.trace().cycles(), 0);
+    }
+
+    #[test]
+    fn test_inspection_reads_memory_stack_and_keys() {
+        // Arrange
+        let mut chip8 = setup_with_opcode(0x2300);
+        chip8.set_key(0x7, true).unwrap();
+
+        // Act
+        chip8.execute_step().unwrap();
+
+        // Assert
+        assert_eq!(chip8.get_memory(0x200, 2), [0x23, 0x00]);
+        assert_eq!(chip8.get_memory(chip8.get_memory_size() - 1, 10).len(), 1, "ranges stop at the end of memory");
+        assert!(chip8.get_memory(0x10000, 4).is_empty());
+        assert_eq!(chip8.get_stack(), [0x202]);
+        assert!(chip8.get_keys()[0x7]);
+        assert!(!chip8.get_keys()[0x8]);
+    }
+
+    #[test]
+    fn test_editing_registers_memory_and_timers() {
+        // Arrange
+        let mut chip8 = Chip8::new();
+        chip8.add_memory_watchpoint(MemoryWatchpoint { start: 0x300, end: 0x300, on_read: false, on_write: true });
+
+        // Act
+        chip8.set_register(0xF, 0x42).unwrap();
+        chip8.set_i(0x0ABC);
+        chip8.set_pc(0x300).unwrap();
+        chip8.poke(0x300, 0x61).unwrap();
+        chip8.poke(0x301, 0x05).unwrap();
+        chip8.set_timer(Timer::from_name("DT").unwrap(), 30);
+        chip8.set_timer(Timer::Sound, 2);
+
+        // Assert
+        let registers = chip8.get_register_snapshot();
+        assert_eq!((registers.VF, registers.I, registers.programm_counter), (0x42, 0x0ABC, 0x300));
+        assert_eq!((registers.delay_timer, registers.sound_timer), (30, 2));
+        assert_eq!(chip8.set_register(0x10, 1), Err(Chip8Error::InvalidRegister(0x10)));
+        assert!(chip8.set_pc(0xFFFF).is_err());
+        assert!(chip8.poke(0x1000, 0).is_err());
+        assert_eq!(chip8.run_until_break(1).unwrap().reason, BreakReason::InstructionLimit, "pokes don't trigger watchpoints");
+        assert_eq!(chip8.get_register_snapshot().V1, 0x05, "the patched instruction ran");
+    }
+
+    #[test]
+    fn test_ram_search_finds_counter() {
+        // Arrange
+        let mut chip8 = Chip8::new();
+        let rom = crate::assembler::assemble("
+                LD I, 0x300
+                LD V0, 5
+                LD [I], V0
+                ADD V0, 0xFF
+                LD I, 0x300
+                LD [I], V0
+                EXIT
+        ").unwrap();
+        chip8.load_rom_bytes(&rom).unwrap();
+        chip8.set_platform(Platform::SuperChip);
+        chip8.start_ram_search();
+
+        // Act
+        chip8.run_until_break(3).unwrap();
+        chip8.filter_ram_search(SearchFilter::EqualTo(5));
+        chip8.run_until_break(100).unwrap();
+        let left = chip8.filter_ram_search(SearchFilter::DecreasedBy(1));
+
+        // Assert
+        assert_eq!(left, 1);
+        assert_eq!(chip8.ram_search_candidates(), [0x300]);
+    }
+
+    #[test]
+    fn test_cheats_are_reapplied_every_frame() {
+        // Arrange
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_in_memory("invaders".to_string()).unwrap();
+        let cheats = "# Space Invaders\nrom f100197f0f2f05b4f3c8c31ab9c2c3930d3e9571\n0318:00 Invaders never descend\n0320:00\n";
+
+        // Act
+        chip8.import_cheats(cheats).unwrap();
+        chip8.memory[0x318] = 0x02;
+        chip8.run_frame().unwrap();
+
+        // Assert
+        assert_eq!(chip8.memory[0x318], 0x00);
+        assert_eq!(chip8.export_cheats(), cheats);
+        assert_eq!(chip8.remove_cheat(0x320), Ok(true));
+        assert_eq!(chip8.remove_cheat(0x320), Ok(false));
+
+        chip8.load_rom_in_memory("pong".to_string()).unwrap();
+        assert!(chip8.cheats().is_empty(), "cheats belong to the ROM they were made for");
+        assert_eq!(chip8.import_cheats(cheats), Err(Chip8Error::CheatRomMismatch));
+    }
+
+    #[test]
+    fn test_profiler_counts_hot_spots_and_key_waits() {
+        // Arrange
+        let mut chip8 = Chip8::new();
+        let rom = crate::assembler::assemble("
+                CALL 0x206      ; 0x200
+                LD V1, K        ; 0x202
+                JP 0x204        ; 0x204
+                ADD V0, 1       ; 0x206
+                RET             ; 0x208
+        ").unwrap();
+        chip8.load_rom_bytes(&rom).unwrap();
+        chip8.set_instructions_per_frame(5);
+        chip8.set_profiling(true);
+
+        // Act
+        chip8.run_frame().unwrap();
+        chip8.run_frame().unwrap();
+
+        // Assert
+        let profiler = chip8.profiler().unwrap();
+        assert_eq!(profiler.instructions(), 10);
+        assert_eq!(profiler.frames(), 2);
+        assert_eq!(profiler.hot_spots(1), [(0x202, 7)]);
+        assert_eq!(profiler.key_wait(), (7, 2));
+        assert_eq!(profiler.subroutines(), [SubroutineProfile { address: 0x206, calls: 1, instructions: 3 }]);
+        assert_eq!(chip8.profile_heat_map()[0x202], 255);
+        assert!(chip8.profile_report(3).contains("  0x0202  LD V1, K"));
+
+        chip8.reset();
+        assert_eq!(chip8.profiler().unwrap().instructions(), 0);
+        chip8.set_profiling(false);
+        assert!(chip8.profiler().is_none());
+    }
+
+    #[test]
+    fn test_coverage_marks_code_sprites_and_data() {
+        // Arrange
+        let mut chip8 = setup_with_debugger_test_program();
+
+        // Act
+        chip8.run_until_break(6).unwrap();
+
+        // Assert
+        let coverage = chip8.coverage();
+        assert_eq!(coverage.flags(0x200), CoverageMap::OPCODE);
+        assert_eq!(coverage.flags(0x201), CoverageMap::OPERAND);
+        assert_eq!(coverage.flags(0x300), CoverageMap::WRITTEN | CoverageMap::SPRITE);
+        assert_eq!(coverage.flags(0x20C), 0, "JP has not run yet");
+        assert_eq!(coverage.count(CoverageMap::CODE), 12);
+        assert_eq!(coverage.as_bytes().len(), chip8.get_memory_size());
+
+        let lines = chip8.disassemble_with_coverage(0x2FE, 0x302, Syntax::Cowgod);
+        assert_eq!(lines[1].text, "DB 0x07", "the sprite byte is shown as data");
+
+        chip8.reset();
+        assert_eq!(chip8.coverage().count(CoverageMap::CODE | CoverageMap::DATA), 0);
+    }
+
+    #[test]
+    fn test_dxyn_marks_wrapped_sprite_pieces_dirty_until_presented() {
+        let mut chip8 = setup_with_opcode(0xD012);
+        chip8.quirks.clip_sprites = false;
+        chip8.quirks.display_wait = false;
+        chip8.registers[0] = 62;
+        chip8.registers[1] = 5;
+        chip8.i_register = 0x300;
+        chip8.memory[0x300] = 0xFF;
+        chip8.memory[0x301] = 0x81;
+        chip8.present_display();
+        assert!(!chip8.is_display_dirty());
+
+        // Act
+        chip8.execute_step().unwrap();
+
+        // Assert: The part left of the right edge and the part wrapped to the left are separate
+        assert_eq!(chip8.get_dirty_regions(), [
+            DirtyRect { x: 62, y: 5, width: 2, height: 2 },
+            DirtyRect { x: 0, y: 5, width: 6, height: 2 },
+        ]);
+        chip8.present_display();
+        assert!(!chip8.is_display_dirty());
+    }
+
+    #[test]
+    fn test_only_display_changes_mark_it_dirty() {
+        let mut chip8 = setup_with_opcode(0x00E0);
+        chip8.memory[0x202] = 0x00;
+        chip8.memory[0x203] = 0xE0;
+        chip8.display[3][4] = 1;
+        chip8.display[7][9] = 1;
+        chip8.present_display();
+
+        // Act & Assert: Clearing dirties what was lit, clearing a blank display nothing
+        chip8.execute_step().unwrap();
+        assert_eq!(chip8.get_dirty_regions(), [DirtyRect { x: 4, y: 3, width: 6, height: 5 }]);
+        chip8.present_display();
+        chip8.execute_step().unwrap();
+        assert!(!chip8.is_display_dirty());
+
+        chip8.reset();
+        assert_eq!(chip8.get_dirty_regions(), [DirtyRect { x: 0, y: 0, width: 64, height: 32 }]);
+    }
+
+    #[test]
+    fn test_trace_ends_with_the_faulting_instruction() {
+        // Arrange: LD V0, 1 then RET with an empty stack
+        let mut chip8 = setup_with_opcode(0x6001);
+        chip8.memory[0x202] = 0x00;
+        chip8.memory[0x203] = 0xEE;
+        chip8.set_trace_size(10);
+        chip8.execute_step().unwrap();
+
+        // Act
+        let result = chip8.execute_step();
+
+        // Assert
+        assert_eq!(result, Err(Chip8Error::StackUnderflow { address: 0x202 }));
+        let last = chip8.trace().iter().last().unwrap();
+        assert_eq!((last.address, last.opcode, last.instruction), (0x202, 0x00EE, Instruction::Ret));
+        assert!(last.changes.is_empty());
+        assert_eq!(chip8.trace().len(), 2);
+    }
+
+    #[test]
+    fn test_movie_with_cheats_replays_identical_memory_and_display() {
+        // Arrange: LD I, 0x300 / LD V0, [I] / LD F, V0 / DRW V0, V1, 5 / JP 0x208 reads the cheat
+        // in the first frame
+        let rom = [0xA3, 0x00, 0xF0, 0x65, 0xF0, 0x29, 0xD0, 0x15, 0x12, 0x08];
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&rom).unwrap();
+        chip8.add_cheat(Cheat { address: 0x300, value: 0x0A, description: "Digit".to_string() }).unwrap();
+        chip8.start_recording(3);
+        let mut recorded = Vec::new();
+        for _ in 0..5 {
+            chip8.run_frame().unwrap();
+            recorded.push((chip8.get_memory(0, chip8.get_memory_size()).to_vec(), chip8.display.pixels().to_vec()));
+        }
+        let movie = chip8.stop_recording().unwrap();
+
+        // Act
+        chip8.start_playback(movie).unwrap();
+        let mut played = Vec::new();
+        for _ in 0..5 {
+            chip8.run_frame().unwrap();
+            played.push((chip8.get_memory(0, chip8.get_memory_size()).to_vec(), chip8.display.pixels().to_vec()));
+        }
+
+        // Assert
+        assert_eq!(played, recorded);
+        assert_eq!(chip8.registers[0], 0x0A, "the cheat is active from the first instruction");
+    }
+
+    #[test]
+    fn test_load_state_is_rejected_during_a_movie() {
+        // Arrange
+        let mut chip8 = setup_with_movie_test_program();
+        let state = chip8.save_state();
+        chip8.start_recording(1);
+        chip8.run_frame().unwrap();
+
+        // Act
+        let while_recording = chip8.load_state(&state);
+        let movie = chip8.stop_recording().unwrap();
+        chip8.start_playback(movie).unwrap();
+        let while_playing = chip8.load_state(&state);
+
+        // Assert
+        assert_eq!(while_recording, Err(Chip8Error::StateLockedByMovie));
+        assert_eq!(while_playing, Err(Chip8Error::StateLockedByMovie));
+    }
+}
@@ -0,0 +1,228 @@
+//! Input movies: a recording of every `set_key` transition that replays a session exactly.
+//!
+//! A movie starts from a freshly reset machine with a seeded random source and the cheats active
+//! at the time, so the key transitions are the only input that has to be stored. Cheats can't be
+//! changed until the movie ends. File layout, all integers big endian:
+//!
+//! | Size | Field                                                         |
+//! |------|---------------------------------------------------------------|
+//! | 4    | magic `C8MV`                                                  |
+//! | 2    | format version, currently 2                                   |
+//! | 20   | SHA-1 of the ROM the movie was recorded with                  |
+//! | var  | random source as in save states: 0 and the 8 byte seed, or 1, |
+//! |      | the 2 byte R9 and the 256 byte COSMAC VIP interpreter page    |
+//! | 1    | platform (0 CHIP-8, 1 SUPER-CHIP, 2 XO-CHIP)                  |
+//! | 1    | quirks, same bit layout as in save states                     |
+//! | 4    | instructions per frame                                        |
+//! | 2    | number of cheats                                              |
+//! | 3*n  | cheats: address (2 bytes), value                              |
+//! | 4    | length of the movie in frames                                 |
+//! | 4    | number of key events                                          |
+//! | 5*n  | key events: frame (4 bytes), key in bits 0-3, bit 7 = pressed  |
+//!
+//! A key event applies before the frame with its number runs.
+
+use super::save_state::{platform_from_byte, platform_to_byte, quirks_from_byte, quirks_to_byte, read_random, write_random, Reader, Writer};
+use super::sha1::sha1;
+use super::{Chip8, Chip8Error, Cheat, Platform, Quirks, RandomSource};
+
+const MAGIC: &[u8; 4] = b"C8MV";
+// Version 2 replaced the seed with the whole random source and added the cheats
+pub const MOVIE_VERSION: u16 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub frame: u32,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Movie {
+    pub rom_sha1: [u8; 20],
+    /// The random source right after seeding, before the first frame.
+    pub random: RandomSource,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    pub instructions_per_frame: u32,
+    /// Cheats active during the whole movie, descriptions are not stored.
+    pub cheats: Vec<Cheat>,
+    pub frames: u32,
+    pub events: Vec<KeyEvent>,
+}
+
+pub(super) enum MovieMode {
+    Idle,
+    Recording(Movie),
+    Playing { movie: Movie, frame: u32, next_event: usize },
+}
+
+impl Movie {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+        writer.bytes(MAGIC);
+        writer.u16(MOVIE_VERSION);
+        writer.bytes(&self.rom_sha1);
+        write_random(&mut writer, &self.random);
+        writer.u8(platform_to_byte(self.platform));
+        writer.u8(quirks_to_byte(self.quirks));
+        writer.u32(self.instructions_per_frame);
+        writer.u16(self.cheats.len() as u16);
+        for cheat in &self.cheats {
+            writer.u16(cheat.address);
+            writer.u8(cheat.value);
+        }
+        writer.u32(self.frames);
+        writer.u32(self.events.len() as u32);
+        for event in &self.events {
+            writer.u32(event.frame);
+            writer.u8(event.key | (event.pressed as u8) << 7);
+        }
+        writer.buffer
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Movie, Chip8Error> {
+        let mut reader = Reader { buffer: bytes, position: 0 };
+        let movie = Movie::read(&mut reader).map_err(|error| match error {
+            Chip8Error::InvalidSaveState(reason) => Chip8Error::InvalidMovie(reason),
+            error => error,
+        })?;
+
+        if reader.position != bytes.len() {
+            return Err(Chip8Error::InvalidMovie("unexpected data after the last event".to_string()));
+        }
+        Ok(movie)
+    }
+
+    fn read(reader: &mut Reader) -> Result<Movie, Chip8Error> {
+        if reader.bytes(MAGIC.len())? != MAGIC {
+            return Err(invalid("missing movie header"));
+        }
+        let version = reader.u16()?;
+        if version != MOVIE_VERSION {
+            return Err(invalid(&format!("version {} is not supported, expected {}", version, MOVIE_VERSION)));
+        }
+
+        let rom_sha1 = reader.array()?;
+        let random = read_random(reader)?;
+        let platform = platform_from_byte(reader.u8()?)?;
+        let quirks = quirks_from_byte(reader.u8()?)?;
+        let instructions_per_frame = reader.u32()?;
+        let cheat_count = reader.u16()?;
+        let mut cheats = Vec::new();
+        for _ in 0..cheat_count {
+            cheats.push(Cheat { address: reader.u16()?, value: reader.u8()?, description: String::new() });
+        }
+        let frames = reader.u32()?;
+        let count = reader.u32()? as usize;
+
+        let mut events = Vec::with_capacity(count.min(reader.buffer.len() / 5));
+        for _ in 0..count {
+            let frame = reader.u32()?;
+            let key = reader.u8()?;
+            if frame > frames || events.last().is_some_and(|last: &KeyEvent| last.frame > frame) {
+                return Err(invalid("key events are out of order"));
+            }
+            events.push(KeyEvent { frame, key: key & 0xF, pressed: key & 0x80 != 0 });
+        }
+
+        Ok(Movie { rom_sha1, random, platform, quirks, instructions_per_frame, cheats, frames, events })
+    }
+}
+
+fn invalid(reason: &str) -> Chip8Error {
+    Chip8Error::InvalidMovie(reason.to_string())
+}
+
+impl Chip8 {
+    /// Resets the machine with `seed` for its current random source and records every key
+    /// transition from here on.
+    pub fn start_recording(&mut self, seed: u64) {
+        self.reset();
+        self.set_seed(seed);
+        // Playback applies the cheats before its first frame as well
+        self.apply_cheats();
+        self.movie = MovieMode::Recording(Movie {
+            rom_sha1: sha1(&self.rom),
+            random: self.random.clone(),
+            platform: self.platform,
+            quirks: self.quirks,
+            instructions_per_frame: self.instructions_per_frame,
+            cheats: self.cheats.clone(),
+            frames: 0,
+            events: Vec::new(),
+        });
+    }
+
+    /// Ends the recording and returns the movie, `None` if nothing was being recorded.
+    pub fn stop_recording(&mut self) -> Option<Movie> {
+        match std::mem::replace(&mut self.movie, MovieMode::Idle) {
+            MovieMode::Recording(movie) => Some(movie),
+            mode => {
+                self.movie = mode;
+                None
+            }
+        }
+    }
+
+    /// Resets the machine to the movie's settings, random source and cheats and replays its inputs.
+    /// The currently loaded ROM must be the one the movie was recorded with. User input is ignored
+    /// until it ends.
+    pub fn start_playback(&mut self, movie: Movie) -> Result<(), Chip8Error> {
+        if movie.rom_sha1 != sha1(&self.rom) {
+            return Err(Chip8Error::MovieRomMismatch);
+        }
+
+        self.set_platform(movie.platform);
+        self.quirks = movie.quirks;
+        self.instructions_per_frame = movie.instructions_per_frame;
+        self.reset();
+        self.random = movie.random.clone();
+        self.cheats = movie.cheats.clone();
+        self.apply_cheats();
+        self.movie = MovieMode::Playing { movie, frame: 0, next_event: 0 };
+        Ok(())
+    }
+
+    pub fn stop_playback(&mut self) {
+        if self.is_playing_movie() {
+            self.movie = MovieMode::Idle;
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        matches!(self.movie, MovieMode::Recording(_))
+    }
+
+    pub fn is_playing_movie(&self) -> bool {
+        matches!(self.movie, MovieMode::Playing { .. })
+    }
+
+    pub(super) fn record_key(&mut self, key: u8, pressed: bool) {
+        if let MovieMode::Recording(movie) = &mut self.movie {
+            movie.events.push(KeyEvent { frame: movie.frames, key, pressed });
+        }
+    }
+
+    pub(super) fn apply_movie_inputs(&mut self) {
+        if let MovieMode::Playing { movie, frame, next_event } = &mut self.movie {
+            while let Some(event) = movie.events.get(*next_event).filter(|event| event.frame == *frame) {
+                self.keyboard[event.key as usize] = event.pressed;
+                *next_event += 1;
+            }
+        }
+    }
+
+    pub(super) fn advance_movie(&mut self) {
+        match &mut self.movie {
+            MovieMode::Recording(movie) => movie.frames += 1,
+            MovieMode::Playing { movie, frame, .. } => {
+                *frame += 1;
+                if *frame >= movie.frames {
+                    self.movie = MovieMode::Idle;
+                }
+            }
+            MovieMode::Idle => {}
+        }
+    }
+}
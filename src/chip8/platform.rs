@@ -0,0 +1,51 @@
+use super::Quirks;
+
+/// Instruction set and machine model the interpreter emulates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Platform {
+    Chip8,
+    SuperChip,
+    XoChip,
+}
+
+impl Platform {
+    pub fn from_name(name: &str) -> Option<Platform> {
+        match name {
+            "chip8" => Some(Platform::Chip8),
+            "super_chip" => Some(Platform::SuperChip),
+            "xo_chip" => Some(Platform::XoChip),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Platform::Chip8 => "chip8",
+            Platform::SuperChip => "super_chip",
+            Platform::XoChip => "xo_chip",
+        }
+    }
+
+    pub fn default_quirks(self) -> Quirks {
+        match self {
+            Platform::Chip8 => Quirks::default(),
+            Platform::SuperChip => Quirks::SUPER_CHIP,
+            Platform::XoChip => Quirks::XO_CHIP,
+        }
+    }
+
+    pub fn memory_size(self) -> usize {
+        match self {
+            Platform::XoChip => 0x10000,
+            _ => 0x1000,
+        }
+    }
+
+    pub fn supports_super_chip(self) -> bool {
+        self != Platform::Chip8
+    }
+
+    pub fn supports_xo_chip(self) -> bool {
+        self == Platform::XoChip
+    }
+}
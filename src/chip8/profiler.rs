@@ -0,0 +1,240 @@
+//! Opt-in execution profiler: where a ROM spends its instructions.
+//!
+//! Counts executions per address, instructions per frame, calls into every subroutine together
+//! with the instructions run inside it (nested calls included), and the `LD Vx, K` instructions
+//! spent waiting for a key press.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use super::{Chip8, Instruction, Syntax};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SubroutineProfile {
+    pub address: u16,
+    pub calls: u64,
+    /// Instructions from the CALL up to and including the matching RET, over all calls.
+    pub instructions: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Profiler {
+    hits: Vec<u64>,
+    instructions: u64,
+    frames: u64,
+    frame_instructions: u64,
+    min_frame_instructions: Option<u64>,
+    max_frame_instructions: u64,
+    subroutines: BTreeMap<u16, SubroutineProfile>,
+    // Subroutines that have not returned yet, with the instruction count at their CALL
+    open_calls: Vec<(u16, u64)>,
+    key_wait_instructions: u64,
+    key_wait_frames: u64,
+    waited_this_frame: bool,
+}
+
+impl Profiler {
+    pub fn hits(&self, address: u16) -> u64 {
+        self.hits.get(address as usize).copied().unwrap_or(0)
+    }
+
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    /// Completed frames since profiling started.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Fewest and most instructions run in a completed frame, `None` before the first frame.
+    pub fn frame_instruction_range(&self) -> Option<(u64, u64)> {
+        self.min_frame_instructions.map(|min| (min, self.max_frame_instructions))
+    }
+
+    /// `LD Vx, K` executions that found no key pressed and frames in which that happened.
+    pub fn key_wait(&self) -> (u64, u64) {
+        (self.key_wait_instructions, self.key_wait_frames)
+    }
+
+    /// The `count` most executed addresses with their hit counts, most executed first.
+    pub fn hot_spots(&self, count: usize) -> Vec<(u16, u64)> {
+        let mut spots: Vec<(u16, u64)> =
+            self.hits.iter().enumerate().filter(|(_, hits)| **hits > 0).map(|(address, &hits)| (address as u16, hits)).collect();
+        spots.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        spots.truncate(count);
+        spots
+    }
+
+    /// Subroutines by the instructions spent in them, most expensive first.
+    pub fn subroutines(&self) -> Vec<SubroutineProfile> {
+        let mut subroutines: Vec<SubroutineProfile> = self.subroutines.values().copied().collect();
+        subroutines.sort_by(|a, b| b.instructions.cmp(&a.instructions).then(a.address.cmp(&b.address)));
+        subroutines
+    }
+
+    /// One byte per address of `memory_size`, 0 for never executed up to 255 for the hottest
+    /// address. The scale is logarithmic so code that runs once still shows up.
+    pub fn heat_map(&self, memory_size: usize) -> Vec<u8> {
+        let max = self.hits.iter().copied().max().unwrap_or(0);
+        let scale = ((max + 1) as f64).ln();
+
+        (0..memory_size)
+            .map(|address| match self.hits.get(address) {
+                Some(&hits) if hits > 0 => (((hits + 1) as f64).ln() / scale * 255.0).round().max(1.0) as u8,
+                _ => 0,
+            })
+            .collect()
+    }
+
+    fn record(&mut self, address: u16, instruction: Instruction, waiting_for_key: bool) {
+        let address = address as usize;
+        if address >= self.hits.len() {
+            self.hits.resize(address + 1, 0);
+        }
+        self.hits[address] += 1;
+        self.instructions += 1;
+        self.frame_instructions += 1;
+
+        match instruction {
+            Instruction::CallAddr { nnn } => {
+                self.subroutines.entry(nnn).or_insert(SubroutineProfile { address: nnn, calls: 0, instructions: 0 }).calls += 1;
+                self.open_calls.push((nnn, self.instructions));
+            }
+            // Returns from calls made before profiling started are not attributed to anything
+            Instruction::Ret => {
+                if let Some((subroutine, called_at)) = self.open_calls.pop() {
+                    let profile = self.subroutines.get_mut(&subroutine).expect("open calls are counted");
+                    profile.instructions += self.instructions - called_at + 1;
+                }
+            }
+            Instruction::LdVxK { .. } if waiting_for_key => {
+                self.key_wait_instructions += 1;
+                self.waited_this_frame = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn end_frame(&mut self) {
+        self.frames += 1;
+        self.min_frame_instructions = Some(self.min_frame_instructions.map_or(self.frame_instructions, |min| min.min(self.frame_instructions)));
+        self.max_frame_instructions = self.max_frame_instructions.max(self.frame_instructions);
+        self.frame_instructions = 0;
+        self.key_wait_frames += self.waited_this_frame as u64;
+        self.waited_this_frame = false;
+    }
+}
+
+impl Chip8 {
+    /// Turning profiling on starts with empty counts, turning it off drops them.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profiler = enabled.then(Profiler::default);
+    }
+
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    /// Empties the counts if profiling is on.
+    pub fn reset_profiler(&mut self) {
+        if let Some(profiler) = &mut self.profiler {
+            *profiler = Profiler::default();
+        }
+    }
+
+    /// Summary, the `count` hottest addresses and the `count` most expensive subroutines as text.
+    pub fn profile_report(&self, count: usize) -> String {
+        let Some(profiler) = &self.profiler else {
+            return "Profiling is off\n".to_string();
+        };
+        let mut report = String::new();
+        let total = profiler.instructions.max(1) as f64;
+
+        let (min, max) = profiler.frame_instruction_range().unwrap_or((0, 0));
+        writeln!(report, "Instructions: {} in {} frames ({}-{} per frame)", profiler.instructions, profiler.frames, min, max).expect("writing to a String cannot fail");
+        let (waits, wait_frames) = profiler.key_wait();
+        writeln!(report, "Waiting for a key: {} instructions in {} frames", waits, wait_frames).expect("writing to a String cannot fail");
+
+        writeln!(report, "\nHot spots:").expect("writing to a String cannot fail");
+        for (address, hits) in profiler.hot_spots(count) {
+            let text = self.disassemble(address as usize, address as usize + 4, Syntax::Cowgod).first().map(|line| line.text.clone()).unwrap_or_default();
+            writeln!(report, "  0x{:04X}  {:<20} {:>10} {:>6.2}%", address, text, hits, hits as f64 / total * 100.0).expect("writing to a String cannot fail");
+        }
+
+        writeln!(report, "\nSubroutines:").expect("writing to a String cannot fail");
+        for subroutine in profiler.subroutines().iter().take(count) {
+            writeln!(
+                report,
+                "  0x{:04X}  {:>8} calls {:>10} instructions {:>6.2}%",
+                subroutine.address,
+                subroutine.calls,
+                subroutine.instructions,
+                subroutine.instructions as f64 / total * 100.0
+            )
+            .expect("writing to a String cannot fail");
+        }
+        report
+    }
+
+    pub fn profile_heat_map(&self) -> Vec<u8> {
+        match &self.profiler {
+            Some(profiler) => profiler.heat_map(self.memory.len()),
+            None => vec![0; self.memory.len()],
+        }
+    }
+
+    // Called by `execute_opcode` once the instruction ran.
+    pub(super) fn record_profile(&mut self, address: u16, instruction: Instruction) {
+        let waiting_for_key = self.position_in_memory == address as usize;
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(address, instruction, waiting_for_key);
+        }
+    }
+
+    pub(super) fn end_profiled_frame(&mut self) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.end_frame();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subroutines_count_nested_instructions() {
+        let mut profiler = Profiler::default();
+
+        profiler.record(0x200, Instruction::CallAddr { nnn: 0x300 }, false);
+        profiler.record(0x300, Instruction::CallAddr { nnn: 0x400 }, false);
+        profiler.record(0x400, Instruction::Ret, false);
+        profiler.record(0x302, Instruction::Ret, false);
+        profiler.record(0x202, Instruction::Ret, false);
+        profiler.end_frame();
+
+        assert_eq!(profiler.subroutines(), [
+            SubroutineProfile { address: 0x300, calls: 1, instructions: 4 },
+            SubroutineProfile { address: 0x400, calls: 1, instructions: 2 },
+        ]);
+        assert_eq!(profiler.frame_instruction_range(), Some((5, 5)));
+    }
+
+    #[test]
+    fn test_heat_map_scales_logarithmically() {
+        let mut profiler = Profiler::default();
+        for _ in 0..99 {
+            profiler.record(0x202, Instruction::Cls, false);
+        }
+        profiler.record(0x200, Instruction::Cls, false);
+
+        let heat_map = profiler.heat_map(0x1000);
+
+        assert_eq!(heat_map.len(), 0x1000);
+        assert_eq!(heat_map[0x202], 255);
+        assert_eq!(heat_map[0x200], 38, "ln(2) / ln(100) of the hottest address");
+        assert_eq!(heat_map[0x204], 0);
+        assert_eq!(profiler.hot_spots(1), [(0x202, 99)]);
+    }
+}
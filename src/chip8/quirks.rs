@@ -0,0 +1,98 @@
+/// How FX55 / FX65 leave the I register after a register store or load.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryIncrement {
+    /// I is left untouched (SUPER-CHIP 1.1).
+    None,
+    /// I = I + X (CHIP-48).
+    ByX,
+    /// I = I + X + 1 (COSMAC VIP, XO-CHIP).
+    ByXPlusOne,
+}
+
+/// Interpretation of the instructions that the various CHIP-8 implementations disagree on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    /// 8XY6 / 8XYE shift Vy into Vx instead of shifting Vx in place.
+    pub shift_uses_vy: bool,
+    pub memory_increment: MemoryIncrement,
+    /// BXNN jumps to XNN + VX instead of NNN + V0.
+    pub jump_uses_vx: bool,
+    /// 8XY1 / 8XY2 / 8XY3 reset VF to 0.
+    pub vf_reset: bool,
+    /// Sprites are clipped at the screen edges instead of wrapping around.
+    pub clip_sprites: bool,
+    /// DXYN waits for the next 60 Hz vertical blank before drawing.
+    pub display_wait: bool,
+}
+
+impl Quirks {
+    // The behaviour this interpreter has always had, kept so existing ROM setups don't change.
+    pub const DEFAULT: Quirks = Quirks {
+        shift_uses_vy: false,
+        memory_increment: MemoryIncrement::ByXPlusOne,
+        jump_uses_vx: false,
+        vf_reset: false,
+        clip_sprites: false,
+        display_wait: false,
+    };
+
+    pub const COSMAC_VIP: Quirks = Quirks {
+        shift_uses_vy: true,
+        memory_increment: MemoryIncrement::ByXPlusOne,
+        jump_uses_vx: false,
+        vf_reset: true,
+        clip_sprites: true,
+        display_wait: true,
+    };
+
+    pub const CHIP_48: Quirks = Quirks {
+        shift_uses_vy: false,
+        memory_increment: MemoryIncrement::ByX,
+        jump_uses_vx: true,
+        vf_reset: false,
+        clip_sprites: true,
+        display_wait: false,
+    };
+
+    pub const SUPER_CHIP: Quirks = Quirks {
+        shift_uses_vy: false,
+        memory_increment: MemoryIncrement::None,
+        jump_uses_vx: true,
+        vf_reset: false,
+        clip_sprites: true,
+        display_wait: false,
+    };
+
+    pub const XO_CHIP: Quirks = Quirks {
+        shift_uses_vy: true,
+        memory_increment: MemoryIncrement::ByXPlusOne,
+        jump_uses_vx: false,
+        vf_reset: false,
+        clip_sprites: false,
+        display_wait: false,
+    };
+
+    pub fn from_profile_name(name: &str) -> Option<Quirks> {
+        match name {
+            "default" => Some(Quirks::default()),
+            "cosmac_vip" => Some(Quirks::COSMAC_VIP),
+            "chip_48" => Some(Quirks::CHIP_48),
+            "super_chip" => Some(Quirks::SUPER_CHIP),
+            "xo_chip" => Some(Quirks::XO_CHIP),
+            _ => None,
+        }
+    }
+
+    /// Name of the profile these quirks match, `None` for a custom combination.
+    pub fn profile_name(self) -> Option<&'static str> {
+        ["default", "cosmac_vip", "chip_48", "super_chip", "xo_chip"]
+            .into_iter()
+            .find(|&name| Quirks::from_profile_name(name) == Some(self))
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks::DEFAULT
+    }
+}
@@ -0,0 +1,72 @@
+/// Where CXKK gets its random bytes from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RandomSource {
+    /// Per-instance wyrand generator, reproducible through [`super::Chip8::set_seed`].
+    Seeded(fastrand::Rng),
+    /// The COSMAC VIP interpreter's routine: R9 is incremented, the interpreter byte at
+    /// 0x100 + R9.0 is added to R9.1 and R9.1 is the random number.
+    CosmacVip { r9: u16, interpreter_page: Box<[u8; 256]> },
+}
+
+impl RandomSource {
+    /// `interpreter` is an image of the 512-byte VIP interpreter (0x000-0x1FF), which can't be
+    /// shipped with the crate.
+    pub fn cosmac_vip(interpreter: &[u8], seed: u16) -> Option<RandomSource> {
+        let interpreter_page: [u8; 256] = interpreter.get(0x100..0x200)?.try_into().ok()?;
+        Some(RandomSource::CosmacVip { r9: seed, interpreter_page: Box::new(interpreter_page) })
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        match self {
+            RandomSource::Seeded(rng) => rng.seed(seed),
+            RandomSource::CosmacVip { r9, .. } => *r9 = seed as u16,
+        }
+    }
+
+    pub fn next_byte(&mut self) -> u8 {
+        match self {
+            RandomSource::Seeded(rng) => rng.u8(..),
+            RandomSource::CosmacVip { r9, interpreter_page } => {
+                *r9 = r9.wrapping_add(1);
+                let [high, low] = r9.to_be_bytes();
+                let high = high.wrapping_add(interpreter_page[low as usize]);
+                *r9 = u16::from_be_bytes([high, low]);
+                high
+            }
+        }
+    }
+}
+
+impl Default for RandomSource {
+    fn default() -> Self {
+        RandomSource::Seeded(fastrand::Rng::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_sources_repeat() {
+        let mut first = RandomSource::default();
+        let mut second = RandomSource::default();
+        first.set_seed(42);
+        second.set_seed(42);
+
+        let first: Vec<u8> = (0..16).map(|_| first.next_byte()).collect();
+        let second: Vec<u8> = (0..16).map(|_| second.next_byte()).collect();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_cosmac_vip_adds_interpreter_bytes_to_r9_high() {
+        let interpreter: Vec<u8> = (0..0x200).map(|address| address as u8).collect();
+        let mut source = RandomSource::cosmac_vip(&interpreter, 0x0000).unwrap();
+
+        assert_eq!(source.next_byte(), 1); // 0x00 + page[0x01]
+        assert_eq!(source.next_byte(), 3); // 0x01 + page[0x02]
+        assert_eq!(source.next_byte(), 6); // 0x03 + page[0x03]
+        assert!(RandomSource::cosmac_vip(&interpreter[..0x1FF], 0).is_none());
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::VecDeque;
+
+// Changed bytes closer together than this are stored as one run, which is smaller than two run headers.
+const MAX_RUN_GAP: usize = 8;
+
+/// Bounded history of per-frame save states used to step gameplay backwards.
+///
+/// Only the newest state is kept in full. Every older frame is stored as the delta that turns its
+/// successor back into it, so a frame usually costs a few hundred bytes even with 64K of memory.
+pub struct RewindBuffer {
+    capacity: usize,
+    newest: Option<Vec<u8>>,
+    // `deltas[i]` turns frame `i + 1` back into frame `i`
+    deltas: VecDeque<Delta>,
+}
+
+enum Delta {
+    // Runs of previous bytes at their offsets, for states of equal length
+    Runs(Vec<(usize, Vec<u8>)>),
+    // Run-length encoded previous state, used when the state size changed (e.g. resolution switch)
+    Full(Vec<u8>),
+}
+
+impl RewindBuffer {
+    pub fn new(capacity: usize) -> Self {
+        RewindBuffer { capacity, newest: None, deltas: VecDeque::new() }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // Shrinking drops the oldest frames first.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        if capacity == 0 {
+            self.clear();
+        }
+        while self.len() > capacity {
+            self.deltas.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self.newest {
+            Some(_) => self.deltas.len() + 1,
+            None => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.newest.is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.newest = None;
+        self.deltas.clear();
+    }
+
+    pub fn push(&mut self, state: Vec<u8>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if let Some(previous) = self.newest.take() {
+            self.deltas.push_back(Delta::between(&state, previous));
+            if self.deltas.len() >= self.capacity {
+                self.deltas.pop_front();
+            }
+        }
+        self.newest = Some(state);
+    }
+
+    /// Drops up to `frames` of the newest states and returns how many were dropped together with
+    /// the state that is now the newest one. The oldest state is never dropped.
+    pub fn rewind(&mut self, frames: usize) -> (usize, Option<&[u8]>) {
+        let mut rewound = 0;
+
+        while rewound < frames {
+            let (Some(delta), Some(newest)) = (self.deltas.pop_back(), self.newest.as_mut()) else {
+                break;
+            };
+            delta.apply(newest);
+            rewound += 1;
+        }
+        (rewound, self.newest.as_deref())
+    }
+}
+
+impl Delta {
+    fn between(current: &[u8], previous: Vec<u8>) -> Delta {
+        if current.len() != previous.len() {
+            return Delta::Full(run_length_encode(&previous));
+        }
+
+        let mut runs: Vec<(usize, Vec<u8>)> = Vec::new();
+        for (offset, (&old, &new)) in previous.iter().zip(current).enumerate() {
+            if old == new {
+                continue;
+            }
+
+            match runs.last_mut() {
+                Some((start, bytes)) if offset - (*start + bytes.len()) <= MAX_RUN_GAP => {
+                    let run_end = *start + bytes.len();
+                    bytes.extend_from_slice(&previous[run_end..=offset]);
+                }
+                _ => runs.push((offset, vec![old])),
+            }
+        }
+        Delta::Runs(runs)
+    }
+
+    fn apply(self, state: &mut Vec<u8>) {
+        match self {
+            Delta::Runs(runs) => {
+                for (offset, bytes) in runs {
+                    state[offset..offset + bytes.len()].copy_from_slice(&bytes);
+                }
+            }
+            Delta::Full(encoded) => *state = run_length_decode(&encoded),
+        }
+    }
+}
+
+// Pairs of (repeat count, byte).
+fn run_length_encode(bytes: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::new();
+
+    for chunk in bytes.chunk_by(|a, b| a == b) {
+        for piece in chunk.chunks(u8::MAX as usize) {
+            encoded.push(piece.len() as u8);
+            encoded.push(piece[0]);
+        }
+    }
+    encoded
+}
+
+fn run_length_decode(encoded: &[u8]) -> Vec<u8> {
+    encoded.chunks_exact(2)
+        .flat_map(|pair| std::iter::repeat_n(pair[1], pair[0] as usize))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewind_restores_previous_states() {
+        let mut buffer = RewindBuffer::new(10);
+        buffer.push(vec![1, 2, 3, 4]);
+        buffer.push(vec![1, 9, 3, 4]);
+        buffer.push(vec![0; 20]);
+
+        assert_eq!(buffer.rewind(1), (1, Some(&[1, 9, 3, 4][..])));
+        assert_eq!(buffer.rewind(5), (1, Some(&[1, 2, 3, 4][..])));
+        assert_eq!(buffer.len(), 1);
+    }
+
+    #[test]
+    fn test_capacity_bounds_the_history() {
+        let mut buffer = RewindBuffer::new(3);
+        for frame in 0..10u8 {
+            buffer.push(vec![frame; 100]);
+        }
+
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.rewind(10), (2, Some(&[7; 100][..])));
+
+        buffer.set_capacity(0);
+        buffer.push(vec![1]);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_run_length_round_trip() {
+        let bytes: Vec<u8> = [vec![0; 600], vec![1, 2, 2, 3], vec![0xFF; 3]].concat();
+
+        assert_eq!(run_length_decode(&run_length_encode(&bytes)), bytes);
+        assert!(run_length_encode(&bytes).len() < 20);
+    }
+}
@@ -0,0 +1,192 @@
+//! Settings for known ROMs, looked up by the SHA-1 of the ROM image.
+//!
+//! `RomDatabase::from_json` reads the `programs.json` file of the community CHIP-8 database
+//! (<https://github.com/chip-8/chip-8-database>): an array of programs with a `title` and a
+//! `roms` object keyed by the lowercase hex SHA-1 of each ROM. Per ROM it uses `platforms`
+//! (in order of preference), `quirkyPlatforms`, `tickrate`, `colors` and `keys`. Platforms this
+//! interpreter doesn't emulate (CHIP-8X, MEGA-CHIP) are skipped.
+
+use super::json::Json;
+use super::sha1::parse_sha1;
+use super::{Chip8Error, MemoryIncrement, Platform, Quirks, RomInfo};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RomSettings {
+    pub title: String,
+    pub platform: Platform,
+    pub quirks: Quirks,
+    /// Instructions per frame, `None` if the database doesn't recommend a speed.
+    pub speed: Option<u32>,
+    /// Colours for every combination of set planes, as CSS colour strings.
+    pub pixel_colors: Vec<String>,
+    pub buzzer_color: Option<String>,
+    pub silence_color: Option<String>,
+    /// Game actions ("up", "a", ...) and the keypad key they are on.
+    pub keys: Vec<(String, u8)>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RomDatabase {
+    entries: Vec<([u8; 20], RomSettings)>,
+}
+
+impl RomDatabase {
+    pub fn from_json(json: &str) -> Result<RomDatabase, Chip8Error> {
+        let document = Json::parse(json).map_err(Chip8Error::InvalidRomDatabase)?;
+        let Json::Array(programs) = document else {
+            return Err(Chip8Error::InvalidRomDatabase("expected an array of programs".to_string()));
+        };
+
+        let mut entries = Vec::new();
+        for program in &programs {
+            let title = program.get("title").and_then(Json::as_str).unwrap_or_default();
+            for (hash, rom) in program.get("roms").map(Json::as_object).unwrap_or_default() {
+                let hash = parse_sha1(hash)
+                    .ok_or_else(|| Chip8Error::InvalidRomDatabase(format!("'{}' is not a SHA-1 hash", hash)))?;
+                if let Some(settings) = rom_settings(title, rom) {
+                    entries.push((hash, settings));
+                }
+            }
+        }
+        Ok(RomDatabase { entries })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn find(&self, sha1: &[u8; 20]) -> Option<&RomSettings> {
+        self.entries.iter().find(|(hash, _)| hash == sha1).map(|(_, settings)| settings)
+    }
+}
+
+impl From<&RomInfo> for RomSettings {
+    fn from(rom: &RomInfo) -> Self {
+        RomSettings {
+            title: rom.title.to_string(),
+            platform: rom.platform,
+            quirks: rom.quirks,
+            speed: Some(rom.speed),
+            pixel_colors: Vec::new(),
+            buzzer_color: None,
+            silence_color: None,
+            keys: rom.keys.iter().map(|&(key, action)| (action.to_string(), key)).collect(),
+        }
+    }
+}
+
+// The first supported platform of the ROM with its quirks, `None` if it runs on none of ours.
+fn rom_settings(title: &str, rom: &Json) -> Option<RomSettings> {
+    let (name, platform, mut quirks) = rom
+        .get("platforms")?
+        .as_array()
+        .iter()
+        .filter_map(Json::as_str)
+        .find_map(|name| database_platform(name).map(|(platform, quirks)| (name, platform, quirks)))?;
+
+    let overrides = rom.get("quirkyPlatforms").and_then(|platforms| platforms.get(name));
+    for (quirk, value) in overrides.map(Json::as_object).unwrap_or_default() {
+        if let Some(value) = value.as_bool() {
+            apply_quirk(&mut quirks, quirk, value);
+        }
+    }
+
+    let colors = rom.get("colors");
+    let color = |name: &str| colors.and_then(|colors| colors.get(name)).and_then(Json::as_str).map(str::to_string);
+    Some(RomSettings {
+        title: title.to_string(),
+        platform,
+        quirks,
+        speed: rom.get("tickrate").and_then(Json::as_f64).filter(|&rate| rate >= 1.0).map(|rate| rate as u32),
+        pixel_colors: colors
+            .and_then(|colors| colors.get("pixels"))
+            .map(|pixels| pixels.as_array().iter().filter_map(Json::as_str).map(str::to_string).collect())
+            .unwrap_or_default(),
+        buzzer_color: color("buzzer"),
+        silence_color: color("silence"),
+        keys: rom
+            .get("keys")
+            .map(Json::as_object)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|(action, key)| key.as_f64().filter(|&key| (0.0..16.0).contains(&key)).map(|key| (action.clone(), key as u8)))
+            .collect(),
+    })
+}
+
+// Platform ids of the database and the quirks it defines for them.
+fn database_platform(name: &str) -> Option<(Platform, Quirks)> {
+    match name {
+        "originalChip8" | "hybridVIP" => Some((Platform::Chip8, Quirks::COSMAC_VIP)),
+        "modernChip8" => Some((Platform::Chip8, Quirks { vf_reset: false, display_wait: false, ..Quirks::COSMAC_VIP })),
+        "chip48" => Some((Platform::SuperChip, Quirks::CHIP_48)),
+        "superchip1" => Some((Platform::SuperChip, Quirks { memory_increment: MemoryIncrement::ByX, ..Quirks::SUPER_CHIP })),
+        "superchip" => Some((Platform::SuperChip, Quirks::SUPER_CHIP)),
+        "xochip" => Some((Platform::XoChip, Quirks::XO_CHIP)),
+        _ => None,
+    }
+}
+
+// Quirk names of the database, where `true` means the behaviour deviates from the COSMAC VIP.
+fn apply_quirk(quirks: &mut Quirks, name: &str, value: bool) {
+    match name {
+        "shift" => quirks.shift_uses_vy = !value,
+        "memoryIncrementByX" if value => quirks.memory_increment = MemoryIncrement::ByX,
+        "memoryLeaveIUnchanged" if value => quirks.memory_increment = MemoryIncrement::None,
+        "memoryIncrementByX" | "memoryLeaveIUnchanged" => quirks.memory_increment = MemoryIncrement::ByXPlusOne,
+        "wrap" => quirks.clip_sprites = !value,
+        "jump" => quirks.jump_uses_vx = value,
+        "vblank" => quirks.display_wait = value,
+        "logic" => quirks.vf_reset = value,
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAMS: &str = r##"[
+        {
+            "title": "Example",
+            "roms": {
+                "0123456789abcdef0123456789abcdef01234567": {
+                    "file": "example.ch8",
+                    "platforms": ["megachip8", "superchip", "xochip"],
+                    "quirkyPlatforms": { "superchip": { "jump": false, "wrap": true } },
+                    "tickrate": 30,
+                    "colors": { "pixels": ["#000000", "#ff0000"], "buzzer": "#ffaa00" },
+                    "keys": { "left": 4, "right": 6, "bogus": 99 }
+                },
+                "89abcdef0123456789abcdef0123456789abcdef": { "platforms": ["chip8x"] }
+            }
+        }
+    ]"##;
+
+    #[test]
+    fn test_from_json_reads_community_schema() {
+        let database = RomDatabase::from_json(PROGRAMS).unwrap();
+        let hash = parse_sha1("0123456789abcdef0123456789abcdef01234567").unwrap();
+
+        let settings = database.find(&hash).unwrap();
+
+        assert_eq!(database.len(), 1, "ROMs only for unsupported platforms are skipped");
+        assert_eq!(settings.title, "Example");
+        assert_eq!(settings.platform, Platform::SuperChip);
+        assert_eq!(settings.quirks, Quirks { jump_uses_vx: false, clip_sprites: false, ..Quirks::SUPER_CHIP });
+        assert_eq!(settings.speed, Some(30));
+        assert_eq!(settings.pixel_colors, ["#000000", "#ff0000"]);
+        assert_eq!(settings.buzzer_color.as_deref(), Some("#ffaa00"));
+        assert_eq!(settings.keys, [("left".to_string(), 4), ("right".to_string(), 6)]);
+    }
+
+    #[test]
+    fn test_from_json_rejects_invalid_documents() {
+        assert!(matches!(RomDatabase::from_json("{}"), Err(Chip8Error::InvalidRomDatabase(_))));
+        assert!(matches!(RomDatabase::from_json(r#"[{"roms": {"xyz": {}}}]"#), Err(Chip8Error::InvalidRomDatabase(_))));
+    }
+}
@@ -0,0 +1,275 @@
+use super::{
+    Chip8, Chip8Error, Display, MemoryIncrement, Platform, Quirks, RandomSource, DISPLAY_HEIGHT, DISPLAY_WIDTH, HIGH_RES_DISPLAY_HEIGHT,
+    HIGH_RES_DISPLAY_WIDTH,
+};
+
+const MAGIC: &[u8; 4] = b"C8SS";
+// Version 2 added the COSMAC VIP random source
+pub const SAVE_STATE_VERSION: u16 = 2;
+
+impl Chip8 {
+    /// Serializes the complete machine state, including configuration and the RNG state.
+    ///
+    /// Layout (big endian): magic `C8SS`, format version, then every field in the order written below.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+        writer.bytes(MAGIC);
+        writer.u16(SAVE_STATE_VERSION);
+
+        writer.u8(platform_to_byte(self.platform));
+        writer.u8(quirks_to_byte(self.quirks));
+        writer.u32(self.instructions_per_frame);
+        writer.bytes(&self.registers);
+        writer.u16(self.i_register);
+        writer.u32(self.position_in_memory as u32);
+        for &address in &self.stack {
+            writer.u16(address);
+        }
+        writer.u8(self.stack_pointer as u8);
+        writer.u8(self.delay_timer);
+        writer.u8(self.sound_timer);
+        writer.u64(self.accumulator_timer.to_bits());
+        write_random(&mut writer, &self.random);
+        writer.u8(self.vertical_blank as u8);
+        writer.u8(self.high_resolution as u8);
+        writer.u8(self.halted as u8);
+        writer.u8(self.selected_planes);
+        writer.bytes(&self.rpl_flags);
+        writer.u16(self.keyboard.iter().enumerate().fold(0, |keys, (key, &pressed)| keys | ((pressed as u16) << key)));
+        writer.u32(self.memory.len() as u32);
+        writer.bytes(&self.memory);
+        writer.u16(self.display.width() as u16);
+        writer.u16(self.display.height() as u16);
+        writer.bytes(self.display.pixels());
+
+        writer.buffer
+    }
+
+    /// Restores a state written by [`Chip8::save_state`]. Nothing is changed if the state is rejected.
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), Chip8Error> {
+        // A movie only replays its inputs, so jumping to another state would desync it
+        if self.is_recording() || self.is_playing_movie() {
+            return Err(Chip8Error::StateLockedByMovie);
+        }
+        let mut reader = Reader { buffer: state, position: 0 };
+        if reader.bytes(MAGIC.len())? != MAGIC {
+            return Err(invalid("missing save state header"));
+        }
+        let version = reader.u16()?;
+        if version != SAVE_STATE_VERSION {
+            return Err(Chip8Error::UnsupportedSaveStateVersion(version));
+        }
+
+        let platform = platform_from_byte(reader.u8()?)?;
+        let quirks = quirks_from_byte(reader.u8()?)?;
+        let instructions_per_frame = reader.u32()?;
+        let registers: [u8; 16] = reader.array()?;
+        let i_register = reader.u16()?;
+        let position_in_memory = reader.u32()? as usize;
+        let mut stack = [0; 16];
+        for address in stack.iter_mut() {
+            *address = reader.u16()?;
+        }
+        let stack_pointer = reader.u8()? as usize;
+        let delay_timer = reader.u8()?;
+        let sound_timer = reader.u8()?;
+        let accumulator_timer = f64::from_bits(reader.u64()?);
+        let random = read_random(&mut reader)?;
+        let vertical_blank = reader.u8()? != 0;
+        let high_resolution = reader.u8()? != 0;
+        let halted = reader.u8()? != 0;
+        let selected_planes = reader.u8()?;
+        let rpl_flags: [u8; 16] = reader.array()?;
+        let keys = reader.u16()?;
+        let memory_size = reader.u32()? as usize;
+        let memory = reader.bytes(memory_size)?.to_vec();
+        let width = reader.u16()? as usize;
+        let height = reader.u16()? as usize;
+        let pixels = reader.bytes(width * height)?.to_vec();
+
+        if reader.position != state.len() {
+            return Err(invalid("unexpected data after the end of the state"));
+        }
+        if memory_size != platform.memory_size() || position_in_memory >= memory_size {
+            return Err(invalid("memory does not match the platform"));
+        }
+        if stack_pointer > stack.len() {
+            return Err(invalid("stack pointer out of range"));
+        }
+        let (expected_width, expected_height) = if high_resolution {
+            (HIGH_RES_DISPLAY_WIDTH as usize, HIGH_RES_DISPLAY_HEIGHT as usize)
+        } else {
+            (DISPLAY_WIDTH as usize, DISPLAY_HEIGHT as usize)
+        };
+        if (width, height) != (expected_width, expected_height) {
+            return Err(invalid("display size does not match the resolution"));
+        }
+        if selected_planes > 3 {
+            return Err(invalid("unknown bitplanes selected"));
+        }
+        if instructions_per_frame == 0 {
+            return Err(invalid("speed of 0 instructions per frame"));
+        }
+
+        self.platform = platform;
+        self.quirks = quirks;
+        self.instructions_per_frame = instructions_per_frame;
+        self.registers = registers;
+        self.i_register = i_register;
+        self.position_in_memory = position_in_memory;
+        self.stack = stack;
+        self.stack_pointer = stack_pointer;
+        self.delay_timer = delay_timer;
+        self.sound_timer = sound_timer;
+        self.accumulator_timer = accumulator_timer;
+        self.random = random;
+        self.vertical_blank = vertical_blank;
+        self.high_resolution = high_resolution;
+        self.halted = halted;
+        self.selected_planes = selected_planes;
+        self.rpl_flags = rpl_flags;
+        self.keyboard = std::array::from_fn(|key| keys & (1 << key) != 0);
+        self.memory = memory;
+        self.display = Display::from_pixels(width, height, pixels);
+        // States are taken between frames
+        self.frame_instructions = 0;
+        Ok(())
+    }
+}
+
+fn invalid(reason: &str) -> Chip8Error {
+    Chip8Error::InvalidSaveState(reason.to_string())
+}
+
+pub(super) fn platform_to_byte(platform: Platform) -> u8 {
+    match platform {
+        Platform::Chip8 => 0,
+        Platform::SuperChip => 1,
+        Platform::XoChip => 2,
+    }
+}
+
+pub(super) fn platform_from_byte(byte: u8) -> Result<Platform, Chip8Error> {
+    match byte {
+        0 => Ok(Platform::Chip8),
+        1 => Ok(Platform::SuperChip),
+        2 => Ok(Platform::XoChip),
+        _ => Err(invalid("unknown platform")),
+    }
+}
+
+pub(super) fn write_random(writer: &mut Writer, random: &RandomSource) {
+    match random {
+        RandomSource::Seeded(rng) => {
+            writer.u8(0);
+            writer.u64(rng.get_seed());
+        }
+        RandomSource::CosmacVip { r9, interpreter_page } => {
+            writer.u8(1);
+            writer.u16(*r9);
+            writer.bytes(&interpreter_page[..]);
+        }
+    }
+}
+
+pub(super) fn read_random(reader: &mut Reader) -> Result<RandomSource, Chip8Error> {
+    match reader.u8()? {
+        0 => Ok(RandomSource::Seeded(fastrand::Rng::with_seed(reader.u64()?))),
+        1 => Ok(RandomSource::CosmacVip { r9: reader.u16()?, interpreter_page: Box::new(reader.array()?) }),
+        _ => Err(invalid("unknown random source")),
+    }
+}
+
+// Bits 0-4 hold the boolean quirks, bits 5-6 the memory increment.
+pub(super) fn quirks_to_byte(quirks: Quirks) -> u8 {
+    let increment = match quirks.memory_increment {
+        MemoryIncrement::None => 0,
+        MemoryIncrement::ByX => 1,
+        MemoryIncrement::ByXPlusOne => 2,
+    };
+
+    quirks.shift_uses_vy as u8
+        | (quirks.jump_uses_vx as u8) << 1
+        | (quirks.vf_reset as u8) << 2
+        | (quirks.clip_sprites as u8) << 3
+        | (quirks.display_wait as u8) << 4
+        | increment << 5
+}
+
+pub(super) fn quirks_from_byte(byte: u8) -> Result<Quirks, Chip8Error> {
+    let memory_increment = match byte >> 5 {
+        0 => MemoryIncrement::None,
+        1 => MemoryIncrement::ByX,
+        2 => MemoryIncrement::ByXPlusOne,
+        _ => return Err(invalid("unknown memory increment quirk")),
+    };
+
+    Ok(Quirks {
+        shift_uses_vy: byte & 1 != 0,
+        jump_uses_vx: byte & 2 != 0,
+        vf_reset: byte & 4 != 0,
+        clip_sprites: byte & 8 != 0,
+        display_wait: byte & 16 != 0,
+        memory_increment,
+    })
+}
+
+#[derive(Default)]
+pub(super) struct Writer {
+    pub(super) buffer: Vec<u8>,
+}
+
+impl Writer {
+    pub(super) fn bytes(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    pub(super) fn u8(&mut self, value: u8) {
+        self.buffer.push(value);
+    }
+
+    pub(super) fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    pub(super) fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    pub(super) fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_be_bytes());
+    }
+}
+
+pub(super) struct Reader<'a> {
+    pub(super) buffer: &'a [u8],
+    pub(super) position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(super) fn bytes(&mut self, count: usize) -> Result<&'a [u8], Chip8Error> {
+        let bytes = self.buffer.get(self.position..self.position + count).ok_or_else(|| invalid("state is truncated"))?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    pub(super) fn array<const N: usize>(&mut self) -> Result<[u8; N], Chip8Error> {
+        Ok(self.bytes(N)?.try_into().expect("slice has the requested length"))
+    }
+
+    pub(super) fn u8(&mut self) -> Result<u8, Chip8Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub(super) fn u16(&mut self) -> Result<u16, Chip8Error> {
+        Ok(u16::from_be_bytes(self.array()?))
+    }
+
+    pub(super) fn u32(&mut self) -> Result<u32, Chip8Error> {
+        Ok(u32::from_be_bytes(self.array()?))
+    }
+
+    pub(super) fn u64(&mut self) -> Result<u64, Chip8Error> {
+        Ok(u64::from_be_bytes(self.array()?))
+    }
+}
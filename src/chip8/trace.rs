@@ -0,0 +1,217 @@
+//! Ring buffer of the most recently executed instructions, for debugging and crash reports.
+
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use super::disassembler::format_instruction;
+use super::{Chip8, Instruction, Syntax, WatchedRegister};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RegisterChange {
+    pub register: WatchedRegister,
+    pub old: u16,
+    pub new: u16,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// Instructions executed since the last reset before this one.
+    pub cycle: u64,
+    pub address: u16,
+    pub opcode: u16,
+    pub instruction: Instruction,
+    /// The address loaded by the four byte `LD I, LONG` of XO-CHIP.
+    pub long_operand: Option<u16>,
+    /// V registers (VF included) and I in the order V0-VF, I.
+    pub changes: Vec<RegisterChange>,
+}
+
+impl TraceEntry {
+    pub fn text(&self, syntax: Syntax) -> String {
+        format_instruction(self.instruction, self.long_operand, syntax)
+    }
+}
+
+/// Keeps the last `capacity` instructions, a capacity of 0 turns tracing off.
+pub struct ExecutionTrace {
+    capacity: usize,
+    entries: VecDeque<TraceEntry>,
+    cycles: u64,
+}
+
+impl ExecutionTrace {
+    pub fn new(capacity: usize) -> Self {
+        ExecutionTrace { capacity, entries: VecDeque::new(), cycles: 0 }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // Shrinking drops the oldest entries first.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.entries.len() > capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Instructions executed since the last reset, traced or not.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.cycles = 0;
+    }
+
+    /// Oldest entry first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &TraceEntry> {
+        self.entries.iter()
+    }
+
+    // The newest `count` entries, oldest first.
+    fn newest(&self, count: usize) -> impl Iterator<Item = &TraceEntry> {
+        self.entries.iter().skip(self.entries.len().saturating_sub(count))
+    }
+
+    /// One line per instruction for the newest `count` entries, oldest first.
+    pub fn to_text(&self, count: usize) -> String {
+        let mut text = String::new();
+
+        for entry in self.newest(count) {
+            let changes: Vec<String> = entry
+                .changes
+                .iter()
+                .map(|change| format!("{}: 0x{:02X} -> 0x{:02X}", change.register, change.old, change.new))
+                .collect();
+            let line = format!("{:>10} 0x{:04X}: {:04X}  {:<20} {}", entry.cycle, entry.address, entry.opcode, entry.text(Syntax::Cowgod), changes.join(", "));
+            writeln!(text, "{}", line.trim_end()).expect("writing to a String cannot fail");
+        }
+        text
+    }
+
+    /// The newest `count` entries as a JSON array, oldest first. Changes map register names to
+    /// `[old, new]`.
+    pub fn to_json(&self, count: usize) -> String {
+        let mut json = String::from("[");
+
+        for (index, entry) in self.newest(count).enumerate() {
+            let changes: Vec<String> = entry
+                .changes
+                .iter()
+                .map(|change| format!("\"{}\":[{},{}]", change.register, change.old, change.new))
+                .collect();
+            // Instruction texts are plain ASCII without quotes or backslashes, nothing to escape
+            write!(
+                json,
+                "{}{{\"cycle\":{},\"address\":{},\"opcode\":{},\"instruction\":\"{}\",\"changes\":{{{}}}}}",
+                if index == 0 { "" } else { "," },
+                entry.cycle,
+                entry.address,
+                entry.opcode,
+                entry.text(Syntax::Cowgod),
+                changes.join(",")
+            )
+            .expect("writing to a String cannot fail");
+        }
+        json.push(']');
+        json
+    }
+
+    fn push(&mut self, entry: TraceEntry) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+}
+
+impl Chip8 {
+    pub fn trace(&self) -> &ExecutionTrace {
+        &self.trace
+    }
+
+    /// Number of instructions kept in the trace, 0 turns tracing off and drops the entries.
+    pub fn set_trace_size(&mut self, instructions: usize) {
+        self.trace.set_capacity(instructions);
+    }
+
+    // Called by `execute_opcode` once the instruction ran, with the registers from before it.
+    pub(super) fn record_trace(&mut self, address: u16, opcode: u16, instruction: Instruction, registers: [u8; 16], i_register: u16) {
+        let cycle = self.trace.cycles;
+        self.trace.cycles += 1;
+        if self.trace.capacity == 0 {
+            return;
+        }
+
+        let mut changes: Vec<RegisterChange> = (0..16)
+            .filter(|&index| registers[index] != self.registers[index])
+            .map(|index| RegisterChange {
+                register: WatchedRegister::V(index as u8),
+                old: registers[index] as u16,
+                new: self.registers[index] as u16,
+            })
+            .collect();
+        if i_register != self.i_register {
+            changes.push(RegisterChange { register: WatchedRegister::I, old: i_register, new: self.i_register });
+        }
+
+        let long_operand = (instruction == Instruction::LdILong).then_some(self.i_register);
+        self.trace.push(TraceEntry { cycle, address, opcode, instruction, long_operand, changes });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(cycle: u64) -> TraceEntry {
+        TraceEntry {
+            cycle,
+            address: 0x200 + cycle as u16 * 2,
+            opcode: 0x6005,
+            instruction: Instruction::LdVxByte { x: 0, kk: 5 },
+            long_operand: None,
+            changes: vec![RegisterChange { register: WatchedRegister::V(0), old: 0, new: 5 }],
+        }
+    }
+
+    #[test]
+    fn test_push_keeps_newest_entries() {
+        let mut trace = ExecutionTrace::new(3);
+
+        for cycle in 0..5 {
+            trace.push(entry(cycle));
+        }
+        trace.set_capacity(2);
+
+        let cycles: Vec<u64> = trace.iter().map(|entry| entry.cycle).collect();
+        assert_eq!(cycles, [3, 4]);
+    }
+
+    #[test]
+    fn test_exports_newest_entries_as_text_and_json() {
+        let mut trace = ExecutionTrace::new(10);
+        for cycle in 0..3 {
+            trace.push(entry(cycle));
+        }
+
+        assert_eq!(trace.to_text(1), "         2 0x0204: 6005  LD V0, 0x05          V0: 0x00 -> 0x05\n");
+        assert_eq!(
+            trace.to_json(1),
+            r#"[{"cycle":2,"address":516,"opcode":24581,"instruction":"LD V0, 0x05","changes":{"V0":[0,5]}}]"#
+        );
+        assert_eq!(trace.to_json(usize::MAX).matches("cycle").count(), 3);
+        assert_eq!(ExecutionTrace::new(0).to_json(10), "[]");
+    }
+}
@@ -1,5 +1,9 @@
-mod chip8;
-use chip8::Chip8;
+pub mod assembler;
+pub mod chip8;
+use chip8::{
+    BreakReason, Cheat, Chip8, Chip8Error, DisassembledLine, MemoryWatchpoint, Movie, Platform, Quirks, RandomSource,
+    SearchFilter, Syntax, Timer, WatchedRegister,
+};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -8,6 +12,7 @@ pub struct Emulator {
 }
 
 #[wasm_bindgen]
+#[allow(non_snake_case)]
 pub struct RegistersSnapshot {
     pub V0: u8,
     pub V1: u8,
@@ -32,6 +37,135 @@ pub struct RegistersSnapshot {
     pub stack_pointer: u8,
 }
 
+#[wasm_bindgen]
+pub struct FrameReport {
+    pub instructions_executed: u32,
+    pub frames_completed: u32,
+    pub sound_active: bool,
+    pub last_opcode: u16,
+    pub break_hit: bool,
+}
+
+impl From<chip8::FrameReport> for FrameReport {
+    fn from(report: chip8::FrameReport) -> Self {
+        FrameReport {
+            instructions_executed: report.instructions_executed,
+            frames_completed: report.frames_completed,
+            sound_active: report.sound_active,
+            last_opcode: report.last_opcode,
+            break_hit: report.break_hit,
+        }
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct BreakReport {
+    /// breakpoint, memory_read, memory_write, register_changed, instruction_limit or halted
+    pub reason: String,
+    pub description: String,
+    pub address: u16,
+    pub instructions_executed: u32,
+}
+
+impl From<chip8::BreakReport> for BreakReport {
+    fn from(report: chip8::BreakReport) -> Self {
+        let reason = match report.reason {
+            BreakReason::Breakpoint => "breakpoint",
+            BreakReason::MemoryRead { .. } => "memory_read",
+            BreakReason::MemoryWrite { .. } => "memory_write",
+            BreakReason::RegisterChanged { .. } => "register_changed",
+            BreakReason::InstructionLimit => "instruction_limit",
+            BreakReason::Halted => "halted",
+        };
+
+        BreakReport {
+            reason: reason.to_string(),
+            description: report.reason.to_string(),
+            address: report.address,
+            instructions_executed: report.instructions_executed as u32,
+        }
+    }
+}
+
+/// A bundled ROM as listed in `chip8::CATALOG`, see `chip8::RomInfo` for the fields.
+#[wasm_bindgen(getter_with_clone)]
+pub struct RomInfo {
+    pub id: String,
+    pub title: String,
+    pub author: Option<String>,
+    pub year: Option<u16>,
+    pub description: String,
+    pub keys: Vec<u8>,
+    /// What each key in `keys` does, in the same order.
+    pub key_actions: Vec<String>,
+    pub speed: u32,
+    pub platform: String,
+    /// Quirks profile name, "custom" if the ROM needs a combination without a profile.
+    pub quirks: String,
+    pub known_working: bool,
+}
+
+impl From<&chip8::RomInfo> for RomInfo {
+    fn from(rom: &chip8::RomInfo) -> Self {
+        RomInfo {
+            id: rom.id.to_string(),
+            title: rom.title.to_string(),
+            author: rom.author.map(str::to_string),
+            year: rom.year,
+            description: rom.description.to_string(),
+            keys: rom.keys.iter().map(|&(key, _)| key).collect(),
+            key_actions: rom.keys.iter().map(|&(_, action)| action.to_string()).collect(),
+            speed: rom.speed,
+            platform: rom.platform.name().to_string(),
+            quirks: rom.quirks.profile_name().unwrap_or("custom").to_string(),
+            known_working: rom.known_working,
+        }
+    }
+}
+
+/// The community CHIP-8 database, see `chip8::RomDatabase`.
+#[wasm_bindgen]
+pub struct RomDatabase {
+    database: chip8::RomDatabase,
+}
+
+#[wasm_bindgen]
+impl RomDatabase {
+    /// Takes the `programs.json` of the database.
+    #[wasm_bindgen(constructor)]
+    pub fn new(programs_json: &str) -> Result<RomDatabase, JsError> {
+        Ok(RomDatabase { database: chip8::RomDatabase::from_json(programs_json)? })
+    }
+}
+
+impl Emulator {
+    // Errors thrown to JS carry the machine context they happened in.
+    fn to_js_error(&self, error: Chip8Error) -> JsError {
+        let pc = self.chip8.get_register_snapshot().programm_counter;
+
+        match self.chip8.peek_opcode() {
+            Ok(opcode) => JsError::new(&format!("{} (PC 0x{:04X}, opcode 0x{:04X})", error, pc, opcode)),
+            Err(_) => JsError::new(&format!("{} (PC 0x{:04X})", error, pc)),
+        }
+    }
+}
+
+impl Emulator {
+    fn syntax(octo_syntax: bool) -> Syntax {
+        if octo_syntax { Syntax::Octo } else { Syntax::Cowgod }
+    }
+
+    fn join_lines(lines: &[DisassembledLine]) -> String {
+        lines.iter().map(|line| line.to_string()).collect::<Vec<_>>().join("\n")
+    }
+}
+
+impl Default for Emulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[wasm_bindgen]
 impl Emulator {
     #[wasm_bindgen(constructor)]
@@ -42,8 +176,8 @@ impl Emulator {
         }
     }
 
-    pub fn set_key(&mut self, key: u8, is_pressed: bool) {
-        self.chip8.set_key(key, is_pressed);
+    pub fn set_key(&mut self, key: u8, is_pressed: bool) -> Result<(), JsError> {
+        self.chip8.set_key(key, is_pressed).map_err(|error| self.to_js_error(error))
     }
 
     pub fn get_display_ptr(&mut self) -> *const u8 {
@@ -51,27 +185,371 @@ impl Emulator {
     }
 
     pub fn get_display_width(&self) -> usize {
-        chip8::DISPLAY_WIDTH as usize
+        self.chip8.get_display_width()
     }
 
     pub fn get_display_height(&self) -> usize {
-        chip8::DISPLAY_HEIGHT as usize
+        self.chip8.get_display_height()
+    }
+
+    // Whether the display changed since `present_frame`, identical frames can be skipped
+    pub fn frame_changed(&self) -> bool {
+        self.chip8.is_display_dirty()
+    }
+
+    // x, y, width and height of every changed region, one after the other
+    pub fn get_dirty_regions(&self) -> Vec<u16> {
+        self.chip8
+            .get_dirty_regions()
+            .iter()
+            .flat_map(|region| [region.x, region.y, region.width, region.height].map(|value| value as u16))
+            .collect()
     }
 
-    pub fn execute_instruction(&mut self) -> u16 {
-        self.chip8.execute_step()
+    pub fn present_frame(&mut self) {
+        self.chip8.present_display();
+    }
+
+    pub fn execute_instruction(&mut self) -> Result<u16, JsError> {
+        self.chip8.execute_step().map_err(|error| self.to_js_error(error))
     }
 
     pub fn update_timers(&mut self, elapsed_ms: u16) {
         self.chip8.update_timers(elapsed_ms);
     }
 
+    pub fn run_for(&mut self, elapsed_ms: f64) -> Result<FrameReport, JsError> {
+        self.chip8.run_for(elapsed_ms)
+            .map(FrameReport::from)
+            .map_err(|error| self.to_js_error(error))
+    }
+
+    pub fn run_frame(&mut self) -> Result<FrameReport, JsError> {
+        self.chip8.run_frame()
+            .map(FrameReport::from)
+            .map_err(|error| self.to_js_error(error))
+    }
+
+    pub fn set_instructions_per_frame(&mut self, instructions: u32) {
+        self.chip8.set_instructions_per_frame(instructions);
+    }
+
+    pub fn set_cpu_frequency(&mut self, hz: u32) {
+        self.chip8.set_cpu_frequency(hz);
+    }
+
     pub fn is_sound_active(&self) -> bool {
         self.chip8.is_sound_active()
     }
 
-    pub fn load_rom(&mut self, name: String) {
-        self.chip8.load_rom_in_memory(name);
+    pub fn load_rom(&mut self, name: String) -> Result<(), JsError> {
+        self.chip8.load_rom_in_memory(name).map_err(|error| self.to_js_error(error))
+    }
+
+    pub fn rom_catalog() -> Vec<RomInfo> {
+        chip8::CATALOG.iter().map(RomInfo::from).collect()
+    }
+
+    pub fn load_rom_bytes(&mut self, rom: &[u8]) -> Result<(), JsError> {
+        self.chip8.load_rom_bytes(rom).map_err(JsError::from)
+    }
+
+    /// Detect the next ROM loads with `database`. The page keeps the database and hands it to
+    /// every new instance, so it is only parsed once.
+    pub fn set_rom_database(&mut self, database: &RomDatabase) {
+        self.chip8.set_rom_database(database.database.clone());
+    }
+
+    pub fn get_detected_rom_title(&self) -> Option<String> {
+        self.chip8.detected_rom().map(|rom| rom.title.clone())
+    }
+
+    pub fn save_state(&self) -> Vec<u8> {
+        self.chip8.save_state()
+    }
+
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), JsError> {
+        self.chip8.load_state(state).map_err(JsError::from)
+    }
+
+    pub fn set_rewind_depth(&mut self, seconds: f64) {
+        self.chip8.set_rewind_depth(seconds);
+    }
+
+    pub fn get_rewind_frames(&self) -> u32 {
+        self.chip8.get_rewind_frames() as u32
+    }
+
+    pub fn rewind(&mut self, frames: u32) -> u32 {
+        self.chip8.rewind(frames as usize) as u32
+    }
+
+    pub fn set_rewinding(&mut self, active: bool) {
+        self.chip8.set_rewinding(active);
+    }
+
+    pub fn is_rewinding(&self) -> bool {
+        self.chip8.is_rewinding()
+    }
+
+    pub fn set_platform(&mut self, name: String) -> Result<(), JsError> {
+        let platform = Platform::from_name(name.as_str())
+            .ok_or_else(|| JsError::new(&format!("Unknown platform: '{}'", name)))?;
+
+        self.chip8.set_platform(platform);
+        Ok(())
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.chip8.is_halted()
+    }
+
+    pub fn set_quirks_profile(&mut self, name: String) -> Result<(), JsError> {
+        let quirks = Quirks::from_profile_name(name.as_str())
+            .ok_or_else(|| JsError::new(&format!("Unknown quirks profile: '{}'", name)))?;
+
+        self.chip8.set_quirks(quirks);
+        Ok(())
+    }
+
+    // Resets the machine and records all key transitions into a movie.
+    pub fn start_recording(&mut self, seed: u64) {
+        self.chip8.start_recording(seed);
+    }
+
+    // Returns the movie file, empty if nothing was being recorded.
+    pub fn stop_recording(&mut self) -> Vec<u8> {
+        self.chip8.stop_recording().map(|movie| movie.to_bytes()).unwrap_or_default()
+    }
+
+    pub fn start_playback(&mut self, movie: &[u8]) -> Result<(), JsError> {
+        let movie = Movie::from_bytes(movie)?;
+        self.chip8.start_playback(movie).map_err(JsError::from)
+    }
+
+    pub fn stop_playback(&mut self) {
+        self.chip8.stop_playback();
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.chip8.is_recording()
+    }
+
+    pub fn is_playing_movie(&self) -> bool {
+        self.chip8.is_playing_movie()
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.chip8.set_seed(seed);
+    }
+
+    // Switches CXKK to the COSMAC VIP algorithm, which reads from a 512-byte VIP interpreter image.
+    pub fn use_cosmac_vip_random(&mut self, interpreter: &[u8], seed: u16) -> Result<(), JsError> {
+        let random = RandomSource::cosmac_vip(interpreter, seed)
+            .ok_or_else(|| JsError::new("The COSMAC VIP interpreter image must be at least 512 bytes"))?;
+
+        self.chip8.set_random_source(random);
+        Ok(())
+    }
+
+    pub fn use_seeded_random(&mut self, seed: u64) {
+        self.chip8.set_random_source(RandomSource::Seeded(fastrand::Rng::with_seed(seed)));
+    }
+
+    pub fn add_breakpoint(&mut self, address: u16) {
+        self.chip8.add_breakpoint(address);
+    }
+
+    pub fn remove_breakpoint(&mut self, address: u16) -> bool {
+        self.chip8.remove_breakpoint(address)
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.chip8.clear_breakpoints();
+    }
+
+    pub fn get_breakpoints(&self) -> Vec<u16> {
+        self.chip8.breakpoints()
+    }
+
+    // Watches the addresses start..=end.
+    pub fn watch_memory(&mut self, start: u16, end: u16, on_read: bool, on_write: bool) {
+        self.chip8.add_memory_watchpoint(MemoryWatchpoint { start, end, on_read, on_write });
+    }
+
+    pub fn unwatch_memory(&mut self, start: u16, end: u16) -> bool {
+        self.chip8.remove_memory_watchpoint(start, end)
+    }
+
+    // `name` is V0-VF or I.
+    pub fn watch_register(&mut self, name: String) -> Result<(), JsError> {
+        let register = WatchedRegister::from_name(&name)
+            .ok_or_else(|| JsError::new(&format!("Unknown register: '{}'", name)))?;
+
+        self.chip8.watch_register(register);
+        Ok(())
+    }
+
+    pub fn unwatch_register(&mut self, name: String) -> Result<(), JsError> {
+        let register = WatchedRegister::from_name(&name)
+            .ok_or_else(|| JsError::new(&format!("Unknown register: '{}'", name)))?;
+
+        self.chip8.unwatch_register(register);
+        Ok(())
+    }
+
+    pub fn clear_watchpoints(&mut self) {
+        self.chip8.clear_watchpoints();
+    }
+
+    pub fn run_until_break(&mut self, max_instructions: u32) -> Result<BreakReport, JsError> {
+        self.chip8.run_until_break(max_instructions as u64)
+            .map(BreakReport::from)
+            .map_err(|error| self.to_js_error(error))
+    }
+
+    // Why the last `run_for` / `run_frame` with `break_hit` set stopped.
+    pub fn get_last_break(&self) -> Option<BreakReport> {
+        self.chip8.last_break().map(BreakReport::from)
+    }
+
+    pub fn get_memory_size(&self) -> usize {
+        self.chip8.get_memory_size()
+    }
+
+    // Up to `length` bytes from `start`, cut off at the end of memory.
+    pub fn get_memory(&self, start: usize, length: usize) -> Vec<u8> {
+        self.chip8.get_memory(start, length).to_vec()
+    }
+
+    pub fn get_stack(&self) -> Vec<u16> {
+        self.chip8.get_stack().to_vec()
+    }
+
+    // Indices of the keypad keys that are held down.
+    pub fn get_pressed_keys(&self) -> Vec<u8> {
+        self.chip8.get_keys().iter().enumerate().filter(|(_, pressed)| **pressed).map(|(key, _)| key as u8).collect()
+    }
+
+    pub fn set_register(&mut self, index: u8, value: u8) -> Result<(), JsError> {
+        self.chip8.set_register(index, value).map_err(JsError::from)
+    }
+
+    pub fn set_i(&mut self, value: u16) {
+        self.chip8.set_i(value);
+    }
+
+    pub fn set_pc(&mut self, address: u16) -> Result<(), JsError> {
+        self.chip8.set_pc(address).map_err(JsError::from)
+    }
+
+    pub fn poke(&mut self, address: usize, value: u8) -> Result<(), JsError> {
+        self.chip8.poke(address, value).map_err(JsError::from)
+    }
+
+    // `name` is delay or sound.
+    pub fn set_timer(&mut self, name: String, value: u8) -> Result<(), JsError> {
+        let timer = Timer::from_name(&name)
+            .ok_or_else(|| JsError::new(&format!("Unknown timer: '{}'", name)))?;
+
+        self.chip8.set_timer(timer, value);
+        Ok(())
+    }
+
+    pub fn start_ram_search(&mut self) {
+        self.chip8.start_ram_search();
+    }
+
+    // `filter` is equal, changed, unchanged, increased, decreased, increased_by or decreased_by,
+    // `value` is used by the ones that compare with a number. Returns the number of candidates left.
+    pub fn filter_ram_search(&mut self, filter: String, value: u8) -> Result<u32, JsError> {
+        let filter = SearchFilter::from_name(&filter, value)
+            .ok_or_else(|| JsError::new(&format!("Unknown search filter: '{}'", filter)))?;
+
+        Ok(self.chip8.filter_ram_search(filter) as u32)
+    }
+
+    pub fn get_ram_search_candidates(&self) -> Vec<u16> {
+        self.chip8.ram_search_candidates().to_vec()
+    }
+
+    // Freezes `address` at `value` until the cheat is removed or another ROM is loaded.
+    pub fn add_cheat(&mut self, address: u16, value: u8, description: String) -> Result<(), JsError> {
+        self.chip8.add_cheat(Cheat { address, value, description }).map_err(JsError::from)
+    }
+
+    pub fn remove_cheat(&mut self, address: u16) -> Result<bool, JsError> {
+        self.chip8.remove_cheat(address).map_err(JsError::from)
+    }
+
+    pub fn clear_cheats(&mut self) -> Result<(), JsError> {
+        self.chip8.clear_cheats().map_err(JsError::from)
+    }
+
+    // The active cheats as a code list, to be saved per ROM.
+    pub fn export_cheats(&self) -> String {
+        self.chip8.export_cheats()
+    }
+
+    pub fn import_cheats(&mut self, text: &str) -> Result<(), JsError> {
+        self.chip8.import_cheats(text).map_err(JsError::from)
+    }
+
+    // Turning profiling on starts with empty counts.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.chip8.set_profiling(enabled);
+    }
+
+    pub fn reset_profiler(&mut self) {
+        self.chip8.reset_profiler();
+    }
+
+    // The `count` hottest addresses and most expensive subroutines.
+    pub fn get_profile_report(&self, count: usize) -> String {
+        self.chip8.profile_report(count)
+    }
+
+    // One byte per memory address, 0 for never executed up to 255 for the hottest address.
+    pub fn get_profile_heat_map(&self) -> Vec<u8> {
+        self.chip8.profile_heat_map()
+    }
+
+    // 0 turns the execution trace off.
+    pub fn set_trace_size(&mut self, instructions: usize) {
+        self.chip8.set_trace_size(instructions);
+    }
+
+    // The newest `count` executed instructions, oldest first, one per line.
+    pub fn get_trace_text(&self, count: usize) -> String {
+        self.chip8.trace().to_text(count)
+    }
+
+    // Same as `get_trace_text` as a JSON array, for attaching to crash reports.
+    pub fn get_trace_json(&self, count: usize) -> String {
+        self.chip8.trace().to_json(count)
+    }
+
+    // One line per instruction, the current PC is marked with '>'.
+    pub fn disassemble(&self, start: u16, end: u16, octo_syntax: bool) -> String {
+        let lines = self.chip8.disassemble(start as usize, end as usize, Emulator::syntax(octo_syntax));
+        Emulator::join_lines(&lines)
+    }
+
+    // Like `disassemble`, guided by the coverage map so data used by the program isn't decoded.
+    pub fn disassemble_with_coverage(&self, start: u16, end: u16, octo_syntax: bool) -> String {
+        let lines = self.chip8.disassemble_with_coverage(start as usize, end as usize, Emulator::syntax(octo_syntax));
+        Emulator::join_lines(&lines)
+    }
+
+    // One byte of flags per memory address: 0x01 opcode, 0x02 operand, 0x04 sprite data, 0x08 read
+    // and 0x10 written since the last reset.
+    pub fn get_coverage(&self) -> Vec<u8> {
+        self.chip8.coverage().as_bytes().to_vec()
+    }
+
+    pub fn disassemble_around_pc(&self, context: usize, octo_syntax: bool) -> String {
+        let lines = self.chip8.disassemble_around_pc(context, Emulator::syntax(octo_syntax));
+        Emulator::join_lines(&lines)
     }
 
     pub fn get_register_snapshot(&self) -> RegistersSnapshot {
@@ -1,25 +1,10 @@
 // AI generated 
-import init, { Emulator } from './wasm/chip_8_emulator.js';
+import init, { Emulator, RomDatabase } from './wasm/chip_8_emulator.js';
 
 // --- Global State ---
 let emulator;
 let animationFrameId = null;
 
-// The list of ROMs you provided, ready for the dropdown.
-/*
-const ROMS = [
-  "octajam_title", "red_october", "puzzle_15", "blinky", "blitz", "brix", "connect", 
-  "guess", "hidden", "invaders", "kaleid", "maze", "merlin", "missile", 
-  "pong", "pong2", "puzzle", "syzygy", "tank", "tetris", "tictac", "ufo", 
-  "vbrix", "vers", "wipeoff"
-];*/
-
-const ROMS = [
-  "octajam_title", "puzzle_15", 
-  "guess", "invaders",
-  "pong", "pong2", "tank",
-];
-
 const keyMap = {
   '1': 0x1,
   '2': 0x2,
@@ -39,9 +24,22 @@ const keyMap = {
   'v': 0xF
 }; 
 
-let currentRom = ROMS[0];
+// Id of the bundled ROM or the bytes of a user file
+let currentRom = null;
+
+// Holding the rewind key steps the game back, up to REWIND_SECONDS
+const REWIND_KEY = 'backspace';
+const REWIND_SECONDS = 10;
+// Executed instructions kept for the debug panel and crash reports
+const TRACE_SIZE = 4096;
+const TRACE_LINES_SHOWN = 10;
 
 window.addEventListener('keydown', (e) => {
+if (e.key.toLowerCase() === REWIND_KEY) {
+  emulator?.set_rewinding(true);
+  e.preventDefault();
+  return;
+}
 const chip8Key = keyMap[e.key.toLowerCase()];
 if (chip8Key !== undefined) {
   emulator.set_key(chip8Key, true); // or whatever your key press handler is
@@ -49,6 +47,10 @@ if (chip8Key !== undefined) {
 });
 
 window.addEventListener('keyup', (e) => {
+if (e.key.toLowerCase() === REWIND_KEY) {
+  emulator?.set_rewinding(false);
+  return;
+}
 const chip8Key = keyMap[e.key.toLowerCase()];
 if (chip8Key !== undefined) {
   emulator.set_key(chip8Key, false);
@@ -62,50 +64,79 @@ async function run() {
   const module = await init();
 
   const beeper = new Beeper();
+  const romDatabase = await loadRomDatabase();
 
   // --- DOM Element References ---
   const romSelect = document.getElementById('rom-select');
+  const romFile = document.getElementById('rom-file');
+  const romHelp = document.getElementById('rom-help');
   const canvas = document.getElementById('screen');
   const registersView = document.getElementById('registers-view');
   const instructionList = document.getElementById('instruction-list');
+  const disassemblyView = document.getElementById('disassembly-view');
   const ctx = canvas.getContext('2d');
   
-  const width = 64; // Hardcoded since we know the CHIP-8 size
-  const height = 32;
-
-  canvas.width = width;
-  canvas.height = height;
+  // The resolution can change at runtime (SUPER-CHIP 00FE/00FF), so it is read from the emulator.
+  let width = 0;
+  let height = 0;
 
   // --- Renderer Setup (using the fast putImageData method) ---
-  const imageData = ctx.createImageData(width, height);
-  const canvasPixelData = imageData.data;
-  const onColor = [255, 255, 255, 255]; // White
-  const offColor = [0, 0, 0, 255];     // Black
+  let imageData = null;
+  let canvasPixelData = null;
+  // Each pixel holds one bit per XO-CHIP plane, so there are four possible colours
+  const palette = [
+    [0, 0, 0, 255],       // Black (no plane)
+    [255, 255, 255, 255], // White (plane 1)
+    [170, 170, 170, 255], // Light grey (plane 2)
+    [85, 85, 85, 255],    // Dark grey (both planes)
+  ];
+
+  function resizeIfNeeded() {
+    const newWidth = emulator.get_display_width();
+    const newHeight = emulator.get_display_height();
+    if (newWidth === width && newHeight === height) return;
+
+    width = newWidth;
+    height = newHeight;
+    canvas.width = width;
+    canvas.height = height;
+    imageData = ctx.createImageData(width, height);
+    canvasPixelData = imageData.data;
+  }
 
+  // Only repaints the regions drawn since the last frame, a resize marks the whole display
   function render() {
+    if (!emulator.frame_changed()) return;
+    resizeIfNeeded();
     const display_ptr = emulator.get_display_ptr();
     const chip8DisplayData = new Uint8Array(module.memory.buffer, display_ptr, width * height);
-
-    for (let i = 0; i < chip8DisplayData.length; i++) {
-        const canvasIdx = i * 4;
-        const color = chip8DisplayData[i] ? onColor : offColor;
-        canvasPixelData.set(color, canvasIdx);
+    const regions = emulator.get_dirty_regions();
+
+    for (let r = 0; r < regions.length; r += 4) {
+      const [x, y, w, h] = regions.subarray(r, r + 4);
+      for (let row = y; row < y + h; row++) {
+        for (let i = row * width + x; i < row * width + x + w; i++) {
+          canvasPixelData.set(palette[chip8DisplayData[i] & 0x3], i * 4);
+        }
+      }
+      ctx.putImageData(imageData, 0, 0, x, y, w, h);
     }
-    ctx.putImageData(imageData, 0, 0);
+    emulator.present_frame();
   }
 
   // --- Debug UI Update Functions ---
-  let last10Instructions = [];
-  function updateDebugUI(newOpcodes) {
-      // Update instruction list
-      last10Instructions.unshift(...newOpcodes);
-      if (last10Instructions.length > 10) {
-        last10Instructions.length = 10;
-      }
-      instructionList.innerHTML = last10Instructions
-        .map(op => `<li>0x${op.toString(16).toUpperCase().padStart(4, '0')}</li>`)
+  function updateDebugUI() {
+      // Update instruction list from the execution trace, newest first
+      const trace = emulator.get_trace_text(TRACE_LINES_SHOWN).trimEnd();
+      instructionList.innerHTML = trace === '' ? '' : trace
+        .split('\n')
+        .reverse()
+        .map(line => `<li>${line.trim()}</li>`)
         .join('');
 
+      // Update disassembly listing around the program counter
+      disassemblyView.textContent = emulator.disassemble_around_pc(5, false);
+
       // Update registers view
       const snapshot = emulator.get_register_snapshot();
       registersView.innerHTML = `
@@ -133,95 +164,173 @@ async function run() {
   }
 
   // --- Game Loop ---
+  // The emulator owns timing: it runs whole 60 Hz frames and carries the remaining time over.
   let lastTime = 0;
-  let lastDebugUpdateTime = 0;
-  const TARGET_CPS = 700; // Cycles per second
 
   function game_loop(currentTime) {
     animationFrameId = requestAnimationFrame(game_loop);
-    let deltaTime = currentTime - lastTime;
-    
-    if (deltaTime > 0) {
-      const cyclesToRun = deltaTime * (TARGET_CPS / 1000);
-      const opcodesThisFrame = [];
+    const deltaTime = lastTime === 0 ? 0 : currentTime - lastTime;
+    lastTime = currentTime;
 
-      emulator.update_timers(deltaTime);
+    let report;
+    try {
+      report = emulator.run_for(deltaTime);
+    } catch (error) {
+      // A faulty ROM only stops the emulation, not the whole page
+      cancelAnimationFrame(animationFrameId);
+      animationFrameId = null;
+      beeper.stop();
+      console.error(error);
+      console.error(`Last instructions:\n${emulator.get_trace_text(TRACE_SIZE)}`);
+      alert(`Emulation stopped: ${error.message}`);
+      return;
+    }
 
-      for (let i = 0; i < cyclesToRun; i++) {
-        const opcode = emulator.execute_instruction();
-        opcodesThisFrame.push(opcode);
-      }
-      
-      render();
-      updateDebugUI(opcodesThisFrame);
-      lastDebugUpdateTime = currentTime;
-
-      if (emulator.is_sound_active())
-      {
-        beeper.start()
-      }
-      else {
-        beeper.stop();
-      }
+    if (report.frames_completed === 0) {
+      report.free();
+      return;
     }
 
-    lastTime = currentTime;
+    render();
+    updateDebugUI();
+
+    if (report.sound_active) {
+      beeper.start();
+    } else {
+      beeper.stop();
+    }
+    report.free();
   }
 
   // --- Emulation Control ---
-  function startEmulator(romName) {
+  // `rom` is either the name of an embedded ROM or the bytes of a user file.
+  // Returns false if the ROM was rejected, the running game then carries on.
+  function startEmulator(rom) {
+    // Load into a fresh instance first and only replace the running one on success
+    const loaded = new Emulator();
+    if (romDatabase) {
+      loaded.set_rom_database(romDatabase);
+    }
+    try {
+      if (typeof rom === 'string') {
+        loaded.load_rom(rom);
+      } else {
+        loaded.load_rom_bytes(rom);
+      }
+    } catch (error) {
+      loaded.free();
+      alert(`Cannot load ROM: ${error.message}`);
+      return false;
+    }
 
     if (animationFrameId) {
       cancelAnimationFrame(animationFrameId);
       animationFrameId = null;
     }
-    
-    // Create a fresh emulator instance for the new ROM
-    emulator = new Emulator();
-    emulator.load_rom(romName);
+    beeper.stop();
+    emulator?.free();
+    emulator = loaded;
+    currentRom = rom;
+    emulator.set_rewind_depth(REWIND_SECONDS);
+    emulator.set_trace_size(TRACE_SIZE);
+    showRomHelp(rom);
 
     // Reset UI elements
-    last10Instructions = [];
     instructionList.innerHTML = '';
     
     // Start the loop
     lastTime = 0;
-    lastDebugUpdateTime = 0;
-    game_loop(0);
+    animationFrameId = requestAnimationFrame(game_loop);
+    return true;
+  }
+
+  // --- ROM Catalog ---
+  // The menu and help text come from the catalog built into the emulator
+  const catalog = Emulator.rom_catalog();
+  const keyboardKeys = {};
+  for (const [keyboardKey, chip8Key] of Object.entries(keyMap)) {
+    keyboardKeys[chip8Key] ??= keyboardKey.toUpperCase();
+  }
+
+  function showRomHelp(romId) {
+    const rom = catalog.find(entry => entry.id === romId);
+    if (!rom) {
+      // User files are matched against the ROM database by their hash
+      const detectedTitle = emulator.get_detected_rom_title();
+      const recognised = detectedTitle ? `Recognised as ${detectedTitle}, settings applied. ` : '';
+      romHelp.textContent = recognised + "The specific actions for each key depend on the game. You'll need to experiment to discover how to play each one!";
+      return;
+    }
+
+    const credits = [rom.author, rom.year].filter(part => part !== undefined).join(', ');
+    const keys = Array.from(rom.keys, (key, index) => `${keyboardKeys[key]}: ${rom.key_actions[index]}`);
+    romHelp.textContent = `${rom.title}${credits ? ` (${credits})` : ''}. ${rom.description}`
+      + (keys.length > 0 ? ` Keys: ${keys.join(', ')}.` : '');
   }
 
-  // --- Initial Setup ---
-  ROMS.forEach(rom => {
+  const untestedGroup = document.createElement('optgroup');
+  untestedGroup.label = 'Untested';
+  catalog.forEach(rom => {
     const option = document.createElement('option');
-    option.value = rom;
-    // Capitalize first letter for display
-    option.textContent = rom.charAt(0).toUpperCase() + rom.slice(1).replace('_', ' ');
-    romSelect.appendChild(option);
+    option.value = rom.id;
+    option.textContent = rom.title;
+    (rom.known_working ? romSelect : untestedGroup).appendChild(option);
   });
+  romSelect.appendChild(untestedGroup);
+  currentRom = catalog.find(rom => rom.known_working).id;
+  romSelect.value = currentRom;
 
   romSelect.addEventListener('change', (event) => {
-    currentRom = event.target.value;
-    startEmulator(event.target.value);
+    if (!startEmulator(event.target.value)) {
+      romSelect.value = typeof currentRom === 'string' ? currentRom : '';
+    }
+  });
+
+  romFile.addEventListener('change', async () => {
+    const file = romFile.files[0];
+    if (!file) return;
+    if (startEmulator(new Uint8Array(await file.arrayBuffer()))) {
+      romSelect.value = '';
+    }
   });
 
+  // A hidden tab pauses the game where it was left. Its keyup events never arrive, so every key is released
+  let pausedByHiding = false;
   document.addEventListener('visibilitychange', () => {
     if (document.hidden) {
-      if (animationFrameId !== null) {
+      pausedByHiding = animationFrameId !== null;
+      if (pausedByHiding) {
         cancelAnimationFrame(animationFrameId);
         animationFrameId = null;
-        beeper.stop();
       }
-      emulator = null;
-    } else {
-      if (!animationFrameId) {
-        startEmulator(currentRom);
+      beeper.stop();
+      emulator?.set_rewinding(false);
+      for (let key = 0; key < 16; key++) {
+        emulator?.set_key(key, false);
       }
+    } else if (pausedByHiding) {
+      // A halted or stopped game stays as it was
+      pausedByHiding = false;
+      lastTime = 0;
+      animationFrameId = requestAnimationFrame(game_loop);
     }
   });
   // Load the first ROM by default
   startEmulator(currentRom);
 }
 
+// The community CHIP-8 database recognises user files, it is optional next to index.html
+async function loadRomDatabase() {
+  try {
+    const response = await fetch('programs.json');
+    if (!response.ok) return null;
+    return new RomDatabase(await response.text());
+  } catch (error) {
+    console.warn(`ROM database not loaded: ${error.message}`);
+    return null;
+  }
+}
+
 run();
 
 class Beeper {